@@ -0,0 +1,18 @@
+extern crate rustbox;
+use rustbox::{RustBox, Color, OutputMode, RB_NORMAL, ImageProtocol, ImageSource};
+fn main() {
+    let rb = RustBox::init(Default::default()).unwrap();
+    rb.set_output_mode(OutputMode::EightBit);
+    rb.print(0, 0, RB_NORMAL, Color::Default, Color::Default, "hi");
+    rb.add_layer("l", 1);
+    rb.with_layer("l", |l| l.print(0, 1, RB_NORMAL, Color::Red, Color::Default, "x"));
+    rb.set_preedit(0, 2, "ab", 1);
+    let a = rb.present().is_ok();
+    rb.set_debug_overlay(true);
+    let b = rb.present().is_ok();
+    rb.set_image_protocol(ImageProtocol::Placeholder);
+    let c = rb.draw_image(0, 0, 2, 2, ImageSource::Rgb { width: 1, height: 1, data: &[1, 2, 3] }).is_ok();
+    let d = rb.present().is_ok();
+    drop(rb);
+    println!("{} {} {} {}", a, b, c, d);
+}
@@ -6,7 +6,7 @@ use rustbox::{Color, RustBox, OutputMode};
 use rustbox::Key;
 
 fn main() {
-    let mut rustbox = match RustBox::init(Default::default()) {
+    let rustbox = match RustBox::init(Default::default()) {
         Result::Ok(v) => v,
         Result::Err(e) => panic!("{}", e),
     };
@@ -15,7 +15,7 @@ fn main() {
     rustbox.print(1, 1, rustbox::RB_BOLD, Color::Byte(0xa2), Color::Black, "Hello, world!");
     rustbox.print(1, 3, rustbox::RB_NORMAL, Color::Black, Color::Byte(0x9a), "Press 'q' to quit.");
     loop {
-        rustbox.present();
+        rustbox.present().unwrap();
         match rustbox.poll_event(false) {
             Ok(rustbox::Event::KeyEvent(key)) => {
                 match key {
@@ -15,7 +15,7 @@ fn main() {
     rustbox.print(1, 3, rustbox::RB_BOLD, Color::White, Color::Black,
                   "Press 'q' to quit.");
     loop {
-        rustbox.present();
+        rustbox.present().unwrap();
         match rustbox.poll_event(false) {
             Ok(rustbox::Event::KeyEvent(key)) => {
                 match key {
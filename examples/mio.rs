@@ -0,0 +1,41 @@
+extern crate mio;
+extern crate rustbox;
+
+use std::default::Default;
+
+use mio::{Events, Interest, Poll, Token};
+use rustbox::{Color, Event, Key, RustBox};
+
+const INPUT: Token = Token(0);
+
+fn main() {
+    let mut rustbox = match RustBox::init(Default::default()) {
+        Result::Ok(v) => v,
+        Result::Err(e) => panic!("{}", e),
+    };
+
+    let mut poll = Poll::new().unwrap();
+    poll.registry().register(&mut rustbox, INPUT, Interest::READABLE).unwrap();
+    let mut events = Events::with_capacity(16);
+
+    let mut keys = 0;
+    loop {
+        rustbox.print(1, 1, rustbox::RB_BOLD, Color::White, Color::Black,
+                      &format!("{} keys pressed. Press 'q' to quit.", keys));
+        rustbox.present().unwrap();
+
+        match poll.poll(&mut events, None) {
+            Ok(()) => {},
+            // SIGWINCH interrupts the wait when the terminal is resized.
+            Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => {},
+            Err(e) => panic!("{}", e),
+        }
+        for event in rustbox.drain_events(false).unwrap() {
+            match event {
+                Event::KeyEvent(Key::Char('q')) => return,
+                Event::KeyEvent(_) => keys += 1,
+                _ => {},
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// A `ratatui` backend, so that ratatui's widgets can be drawn with termbox.
+//
+// ratatui only ever hands over the cells that changed and then asks for a flush, which maps
+// directly onto termbox's back buffer and `present`.
+
+use std::io;
+
+use ratatui::backend::{Backend, ClearType, WindowSize};
+use ratatui::buffer::Cell as TuiCell;
+use ratatui::layout::{Position, Size};
+use ratatui::style::{Color as TuiColor, Modifier};
+
+use super::{Cell, Color, RustBox, RB_BOLD, RB_NORMAL, RB_REVERSE, RB_UNDERLINE};
+
+impl Backend for RustBox {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+        where I: Iterator<Item = (u16, u16, &'a TuiCell)>
+    {
+        for (x, y, cell) in content {
+            if !cell.skip {
+                self.set_cell(x as usize, y as usize, convert_cell(cell));
+            }
+        }
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        RustBox::hide_cursor(self);
+        Ok(())
+    }
+
+    /// termbox only shows the cursor at a position, so a hidden cursor is shown at the top left
+    /// until it is moved with `set_cursor_position`.
+    fn show_cursor(&mut self) -> io::Result<()> {
+        let (x, y) = cursor_or_origin(*self.cursor.lock().unwrap());
+        RustBox::set_cursor(self, x as isize, y as isize);
+        Ok(())
+    }
+
+    fn get_cursor_position(&mut self) -> io::Result<Position> {
+        let (x, y) = cursor_or_origin(*self.cursor.lock().unwrap());
+        Ok(Position::new(x, y))
+    }
+
+    fn set_cursor_position<P: Into<Position>>(&mut self, position: P) -> io::Result<()> {
+        let position = position.into();
+        RustBox::set_cursor(self, position.x as isize, position.y as isize);
+        Ok(())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        RustBox::clear(self);
+        Ok(())
+    }
+
+    fn clear_region(&mut self, clear_type: ClearType) -> io::Result<()> {
+        let (width, height) = (self.width(), self.height());
+        let (x, y) = cursor_or_origin(*self.cursor.lock().unwrap());
+        let (x, y) = (x as usize, y as usize);
+        match clear_type {
+            ClearType::All => RustBox::clear(self),
+            ClearType::AfterCursor => {
+                RustBox::clear_region(self, x, y, width, 1);
+                RustBox::clear_region(self, 0, y + 1, width, height);
+            },
+            ClearType::BeforeCursor => {
+                RustBox::clear_region(self, 0, 0, width, y);
+                RustBox::clear_region(self, 0, y, x + 1, 1);
+            },
+            ClearType::CurrentLine => RustBox::clear_region(self, 0, y, width, 1),
+            ClearType::UntilNewLine => RustBox::clear_region(self, x, y, width, 1),
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> io::Result<Size> {
+        Ok(Size::new(self.width() as u16, self.height() as u16))
+    }
+
+    /// The size in pixels isn't known, and is reported as zero.
+    fn window_size(&mut self) -> io::Result<WindowSize> {
+        Ok(WindowSize { columns_rows: self.size()?, pixels: Size::new(0, 0) })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.present()
+    }
+}
+
+fn cursor_or_origin((x, y): (i32, i32)) -> (u16, u16) {
+    if x < 0 || y < 0 {
+        (0, 0)
+    } else {
+        (x as u16, y as u16)
+    }
+}
+
+fn convert_cell(cell: &TuiCell) -> Cell {
+    let mut style = RB_NORMAL;
+    for &(modifier, flag) in &[(Modifier::BOLD, RB_BOLD), (Modifier::UNDERLINED, RB_UNDERLINE),
+                               (Modifier::REVERSED, RB_REVERSE)] {
+        if cell.modifier.contains(modifier) {
+            style.insert(flag);
+        }
+    }
+    Cell {
+        ch: cell.symbol().chars().next().unwrap_or(' '),
+        fg: convert_color(cell.fg),
+        bg: convert_color(cell.bg),
+        style,
+    }
+}
+
+// Colors that the output mode doesn't have are brought down to the nearest it does have when
+// they're drawn, like any other.
+fn convert_color(color: TuiColor) -> Color {
+    let index = match color {
+        TuiColor::Reset => return Color::Default,
+        TuiColor::Rgb(r, g, b) => return Color::Rgb(r, g, b),
+        TuiColor::Black => 0,
+        TuiColor::Red => 1,
+        TuiColor::Green => 2,
+        TuiColor::Yellow => 3,
+        TuiColor::Blue => 4,
+        TuiColor::Magenta => 5,
+        TuiColor::Cyan => 6,
+        TuiColor::Gray => 7,
+        TuiColor::DarkGray => 8,
+        TuiColor::LightRed => 9,
+        TuiColor::LightGreen => 10,
+        TuiColor::LightYellow => 11,
+        TuiColor::LightBlue => 12,
+        TuiColor::LightMagenta => 13,
+        TuiColor::LightCyan => 14,
+        TuiColor::White => 15,
+        TuiColor::Indexed(i) => i,
+    };
+    Color::from_256color(index as u16)
+}
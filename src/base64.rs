@@ -0,0 +1,40 @@
+// Minimal standard-alphabet base64, for the escape sequences that carry binary payloads.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize;
+        out.push(ALPHABET[n >> 18] as char);
+        out.push(ALPHABET[(n >> 12) & 63] as char);
+        out.push(if chunk.len() > 1 { ALPHABET[(n >> 6) & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { ALPHABET[n & 63] as char } else { '=' });
+    }
+    out
+}
+
+/// Returns `None` if `data` isn't valid base64. Whitespace is not allowed.
+pub fn decode(data: &str) -> Option<Vec<u8>> {
+    let data = data.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut n = 0;
+        for (i, &c) in chunk.iter().enumerate() {
+            let v = ALPHABET.iter().position(|&a| a == c)?;
+            n |= v << (18 - 6 * i);
+        }
+        out.push((n >> 16) as u8);
+        if chunk.len() > 2 {
+            out.push((n >> 8) as u8);
+        }
+        if chunk.len() > 3 {
+            out.push(n as u8);
+        }
+    }
+    Some(out)
+}
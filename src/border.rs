@@ -0,0 +1,158 @@
+// Lines and boxes made of Unicode box-drawing characters.
+//
+// Each cell of a line is described by the segments leaving its center. Where a line is drawn
+// over part of another one of the same weight, the segments are combined so that they join up
+// into the right corner or tee.
+
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BorderStyle {
+    /// `┌─┐`
+    Plain,
+    /// `╭─╮`
+    Rounded,
+    /// `╔═╗`
+    Double,
+    /// `┏━┓`
+    Heavy,
+    /// `+-+`, for terminals that can't show box-drawing characters.
+    Ascii,
+}
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+// Indexed by segments. A line with a single segment is drawn the same as one with both.
+const LIGHT: [char; 16] = [
+    ' ', '│', '│', '│', '─', '┘', '┐', '┤', '─', '└', '┌', '├', '─', '┴', '┬', '┼',
+];
+const HEAVY: [char; 16] = [
+    ' ', '┃', '┃', '┃', '━', '┛', '┓', '┫', '━', '┗', '┏', '┣', '━', '┻', '┳', '╋',
+];
+const DOUBLE: [char; 16] = [
+    ' ', '║', '║', '║', '═', '╝', '╗', '╣', '═', '╚', '╔', '╠', '═', '╩', '╦', '╬',
+];
+const ASCII: [char; 16] = [
+    ' ', '|', '|', '|', '-', '+', '+', '+', '-', '+', '+', '+', '-', '+', '+', '+',
+];
+
+fn table(style: BorderStyle) -> &'static [char; 16] {
+    match style {
+        BorderStyle::Plain | BorderStyle::Rounded => &LIGHT,
+        BorderStyle::Heavy => &HEAVY,
+        BorderStyle::Double => &DOUBLE,
+        BorderStyle::Ascii => &ASCII,
+    }
+}
+
+// The segments of a character drawn in `style`'s weight, if it is one.
+fn segments_of(ch: char, style: BorderStyle) -> Option<u8> {
+    let rounded = match ch {
+        '╯' => Some(UP | LEFT),
+        '╮' => Some(DOWN | LEFT),
+        '╰' => Some(UP | RIGHT),
+        '╭' => Some(DOWN | RIGHT),
+        _ => None,
+    };
+    if rounded.is_some() && table(style) == &LIGHT {
+        return rounded;
+    }
+    // Prefer the full line over a single segment, which maps to the same character.
+    table(style).iter().rposition(|&c| c == ch && ch != ' ').map(|i| {
+        match i {
+            1 | 2 => UP | DOWN,
+            4 | 8 => LEFT | RIGHT,
+            i => i as u8,
+        }
+    })
+}
+
+// The character to draw `segments` in `style` over the character `existing`.
+pub fn join(existing: char, segments: u8, style: BorderStyle) -> char {
+    let segments = segments | segments_of(existing, style).unwrap_or(0);
+    if style == BorderStyle::Rounded {
+        match segments {
+            s if s == UP | LEFT => return '╯',
+            s if s == DOWN | LEFT => return '╮',
+            s if s == UP | RIGHT => return '╰',
+            s if s == DOWN | RIGHT => return '╭',
+            _ => (),
+        }
+    }
+    table(style)[segments as usize]
+}
+
+// The cells of a horizontal line `len` cells long, with their segments.
+pub fn hline(x: usize, y: usize, len: usize) -> Vec<(usize, usize, u8)> {
+    (0..len).map(|i| {
+        let mut segments = 0;
+        if i > 0 {
+            segments |= LEFT;
+        }
+        if i + 1 < len {
+            segments |= RIGHT;
+        }
+        (x.saturating_add(i), y, if segments == 0 { LEFT | RIGHT } else { segments })
+    }).collect()
+}
+
+pub fn vline(x: usize, y: usize, len: usize) -> Vec<(usize, usize, u8)> {
+    (0..len).map(|i| {
+        let mut segments = 0;
+        if i > 0 {
+            segments |= UP;
+        }
+        if i + 1 < len {
+            segments |= DOWN;
+        }
+        (x, y.saturating_add(i), if segments == 0 { UP | DOWN } else { segments })
+    }).collect()
+}
+
+// The cells of the outline of a `w` by `h` box.
+pub fn rect(x: usize, y: usize, w: usize, h: usize) -> Vec<(usize, usize, u8)> {
+    if w == 0 || h == 0 {
+        return Vec::new();
+    }
+    if h == 1 {
+        return hline(x, y, w);
+    }
+    if w == 1 {
+        return vline(x, y, h);
+    }
+    let right = x.saturating_add(w - 1);
+    let bottom = y.saturating_add(h - 1);
+    let mut cells = BTreeMap::new();
+    let lines = [hline(x, y, w), hline(x, bottom, w), vline(x, y, h), vline(right, y, h)];
+    for &(cx, cy, segments) in lines.iter().flat_map(|line| line.iter()) {
+        *cells.entry((cy, cx)).or_insert(0) |= segments;
+    }
+    cells.into_iter().map(|((cy, cx), segments)| (cx, cy, segments)).collect()
+}
+
+// What to show in place of `ch` on a terminal that can only show ASCII: box-drawing characters
+// become `-`, `|` and `+`, block and braille characters something of about the same weight,
+// and an ellipsis a dot. Anything else is left as it is.
+pub fn ascii_fallback(ch: char) -> char {
+    match ch {
+        '═' => '=',
+        '─' | '━' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' | '╴' | '╶' | '╸' | '╺' | '╼' | '╾' => '-',
+        '║' => '|',
+        '│' | '┃' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' | '╵' | '╷' | '╹' | '╻' | '╽' | '╿' => '|',
+        '╱' => '/',
+        '╲' => '\\',
+        '╳' => 'X',
+        '\u{2500}'..='\u{257f}' => '+',
+        '░' => '.',
+        '▒' => ':',
+        '\u{2580}'..='\u{259f}' => '#',
+        '\u{2800}' => ' ',
+        '\u{2801}'..='\u{28ff}' => '.',
+        // As used by `text::truncate`.
+        '…' => '.',
+        ch => ch,
+    }
+}
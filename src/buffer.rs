@@ -0,0 +1,400 @@
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+use std::slice::Chunks;
+
+use super::{Color, Rect, Style, RB_BOLD, RB_NORMAL, RB_REVERSE, RB_UNDERLINE};
+use text::{self, Attr};
+
+/// A single character cell: the character drawn in it along with its colors and style.
+///
+/// ```
+/// use rustbox::{Cell, Color, RB_BOLD};
+///
+/// let cell = Cell::new('x').fg(Color::Red).style(RB_BOLD);
+/// assert_eq!(cell.bg, Color::Default);
+/// assert_eq!(Cell::default().ch, ' ');
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Color,
+    pub bg: Color,
+    pub style: Style,
+}
+
+impl Cell {
+    /// A cell showing `ch` in the terminal's default colors, with no style.
+    pub fn new(ch: char) -> Cell {
+        Cell { ch, ..BLANK }
+    }
+
+    pub fn ch(self, ch: char) -> Cell {
+        Cell { ch, ..self }
+    }
+
+    pub fn fg(self, fg: Color) -> Cell {
+        Cell { fg, ..self }
+    }
+
+    pub fn bg(self, bg: Color) -> Cell {
+        Cell { bg, ..self }
+    }
+
+    pub fn style(self, style: Style) -> Cell {
+        Cell { style, ..self }
+    }
+
+    /// Take the colors and style from `attr`.
+    pub fn attr(self, attr: Attr) -> Cell {
+        Cell { fg: attr.fg, bg: attr.bg, style: attr.style, ..self }
+    }
+}
+
+/// A blank space in the terminal's default colors.
+impl Default for Cell {
+    fn default() -> Cell {
+        BLANK
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CellError {
+    /// The given `(x, y)` position lies outside the buffer.
+    OutOfBounds(usize, usize),
+}
+
+impl fmt::Display for CellError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CellError::OutOfBounds(x, y) => write!(fmt, "Cell ({}, {}) is out of bounds", x, y),
+        }
+    }
+}
+
+impl Error for CellError {
+    fn description(&self) -> &str {
+        match *self {
+            CellError::OutOfBounds(..) => "Cell is out of bounds",
+        }
+    }
+}
+
+pub(crate) const BLANK: Cell = Cell { ch: ' ', fg: Color::Default, bg: Color::Default, style: RB_NORMAL };
+
+// The cells of `row` that are seen on screen: all but the one after each wide character, which
+// the character covers.
+pub(crate) fn shown_cells(row: &[Cell]) -> impl Iterator<Item = &Cell> {
+    let mut covered = false;
+    row.iter().filter(move |cell| {
+        let shown = !covered;
+        covered = shown && text::char_width(cell.ch) == 2;
+        shown
+    })
+}
+
+/// A rectangular grid of cells.
+///
+/// Cells are stored row by row, so the cell at `(x, y)` lives at index `y * width + x`.
+/// `RustBox::buffer` returns a copy of the back buffer in this form, and `RustBox::blit`
+/// writes one back.
+///
+/// A buffer can be any size, so content that is expensive to draw can be drawn once into an
+/// offscreen buffer and the part of it that's visible copied to the screen each frame with
+/// `RustBox::blit_region`.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize))]
+pub struct Buffer {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+// A buffer is checked to have a cell for every position before it's accepted.
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Buffer {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Buffer, D::Error> {
+        #[derive(::serde::Deserialize)]
+        struct Fields {
+            width: usize,
+            height: usize,
+            cells: Vec<Cell>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        if Some(fields.cells.len()) != fields.width.checked_mul(fields.height) {
+            return Err(::serde::de::Error::custom(format_args!(
+                "{} cells don't fill a {}x{} buffer", fields.cells.len(), fields.width, fields.height)));
+        }
+        Ok(Buffer { width: fields.width, height: fields.height, cells: fields.cells })
+    }
+}
+
+impl Buffer {
+    /// Create a buffer of the given size filled with blank cells.
+    pub fn new(width: usize, height: usize) -> Buffer {
+        Buffer {
+            width,
+            height,
+            cells: vec![BLANK; width * height],
+        }
+    }
+
+    pub(crate) fn from_cells(width: usize, height: usize, cells: Vec<Cell>) -> Buffer {
+        debug_assert_eq!(cells.len(), width * height);
+        Buffer { width, height, cells }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the cell at `(x, y)`, or `None` if it lies outside the buffer.
+    pub fn get(&self, x: usize, y: usize) -> Option<&Cell> {
+        if x < self.width && y < self.height {
+            self.cells.get(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    /// Replace the cell at `(x, y)`. Positions outside the buffer are ignored, the same way
+    /// termbox ignores out of range cells.
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = cell;
+        }
+    }
+
+    /// Like `set`, but reports positions outside the buffer instead of ignoring them.
+    pub fn try_set(&mut self, x: usize, y: usize, cell: Cell) -> Result<(), CellError> {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = cell;
+            Ok(())
+        } else {
+            Err(CellError::OutOfBounds(x, y))
+        }
+    }
+
+    /// Returns row `y` as a slice, or `None` if it lies outside the buffer.
+    pub fn row(&self, y: usize) -> Option<&[Cell]> {
+        if y < self.height {
+            Some(&self.cells[y * self.width..(y + 1) * self.width])
+        } else {
+            None
+        }
+    }
+
+    pub fn row_mut(&mut self, y: usize) -> Option<&mut [Cell]> {
+        if y < self.height {
+            Some(&mut self.cells[y * self.width..(y + 1) * self.width])
+        } else {
+            None
+        }
+    }
+
+    /// Copy the `src_rect` part of `src` into this buffer with its top left corner at `(x, y)`.
+    /// Whatever lies outside either buffer is clipped.
+    ///
+    /// ```
+    /// use rustbox::{Buffer, Rect};
+    ///
+    /// let mut src = Buffer::new(3, 3);
+    /// for y in 0..3 {
+    ///     src.row_mut(y).unwrap()[2].ch = 'x';
+    /// }
+    /// let mut dst = Buffer::new(2, 2);
+    /// dst.blit(0, 0, &src, Rect::new(1, 1, 2, 2));
+    /// assert_eq!(dst.text(), " x\n x");
+    /// ```
+    pub fn blit(&mut self, x: usize, y: usize, src: &Buffer, src_rect: Rect) {
+        let src_rect = src_rect.intersection(Rect::new(0, 0, src.width, src.height));
+        let w = cmp::min(src_rect.w, self.width.saturating_sub(x));
+        let h = cmp::min(src_rect.h, self.height.saturating_sub(y));
+        if w == 0 || h == 0 {
+            return;
+        }
+        for dy in 0..h {
+            let from = (src_rect.y + dy) * src.width + src_rect.x;
+            let to = (y + dy) * self.width + x;
+            self.cells[to..to + w].copy_from_slice(&src.cells[from..from + w]);
+        }
+    }
+
+    // Move the rows of `rect` up by `n` (or down, if `up` is false), filling those left behind
+    // with `fill`.
+    pub(crate) fn scroll(&mut self, rect: Rect, n: usize, up: bool, fill: Cell) {
+        let rect = rect.intersection(Rect::new(0, 0, self.width, self.height));
+        let old = self.clone();
+        for dy in 0..rect.h {
+            let from = scrolled_from(dy, rect.h, n, up);
+            for x in rect.x..rect.right() {
+                let cell = match from {
+                    Some(sy) => old.cells[(rect.y + sy) * self.width + x],
+                    None => fill,
+                };
+                self.cells[(rect.y + dy) * self.width + x] = cell;
+            }
+        }
+    }
+
+    /// Iterate over the rows of the buffer, top to bottom.
+    pub fn iter_rows<'a>(&'a self) -> Chunks<'a, Cell> {
+        // `chunks` panics on a zero size, but a zero-width buffer has no cells anyway.
+        self.cells.chunks(if self.width == 0 { 1 } else { self.width })
+    }
+
+    /// The characters in the buffer, one line per row, without any color or style. The cell
+    /// after a wide character, which it covers, is left out.
+    pub fn text(&self) -> String {
+        let rows: Vec<String> = self.iter_rows()
+            .map(|row| shown_cells(row).map(|cell| cell.ch).collect())
+            .collect();
+        rows.join("\n")
+    }
+
+    /// Like `text`, but with markup describing colors and style. Each row, and every change of
+    /// attributes within a row, starts with a tag such as `[fg=White bg=Black bold]`. Literal
+    /// `[` characters are doubled.
+    ///
+    /// This is meant for snapshot tests and debugging, where a diff should show formatting
+    /// changes as well as text changes.
+    pub fn styled_text(&self) -> String {
+        let mut out = String::new();
+        for (y, row) in self.iter_rows().enumerate() {
+            if y > 0 {
+                out.push('\n');
+            }
+            let mut last = None;
+            for cell in shown_cells(row) {
+                let attrs = (cell.fg, cell.bg, cell.style);
+                if last != Some(attrs) {
+                    out.push_str(&format!("[fg={:?} bg={:?}", cell.fg, cell.bg));
+                    for &(flag, name) in &[(RB_BOLD, "bold"), (RB_UNDERLINE, "underline"),
+                                           (RB_REVERSE, "reverse")] {
+                        if cell.style.contains(flag) {
+                            out.push(' ');
+                            out.push_str(name);
+                        }
+                    }
+                    out.push(']');
+                    last = Some(attrs);
+                }
+                if cell.ch == '[' {
+                    out.push('[');
+                }
+                out.push(cell.ch);
+            }
+        }
+        out
+    }
+
+    pub(crate) fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+}
+
+// The row of an `h` row region that row `dy` should show after scrolling it by `n`, or `None`
+// if it has been left empty.
+pub(crate) fn scrolled_from(dy: usize, h: usize, n: usize, up: bool) -> Option<usize> {
+    if up {
+        dy.checked_add(n).filter(|&sy| sy < h)
+    } else {
+        dy.checked_sub(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Buffer, Cell};
+    use Rect;
+
+    // A buffer with a row for each of `rows`, one character to a cell.
+    fn buffer(rows: &[&str]) -> Buffer {
+        let mut buffer = Buffer::new(rows[0].chars().count(), rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                buffer.set(x, y, Cell::new(ch));
+            }
+        }
+        buffer
+    }
+
+    #[test]
+    fn blit_clips_to_both_buffers() {
+        let src = buffer(&["abc", "def", "ghi"]);
+
+        let mut dst = buffer(&["....", "....", "...."]);
+        dst.blit(2, 1, &src, Rect::new(0, 0, 3, 3));
+        assert_eq!(dst.text(), "....\n..ab\n..de");
+
+        // A source rectangle partly outside the source.
+        let mut dst = buffer(&["....", "...."]);
+        dst.blit(0, 0, &src, Rect::new(1, 2, 5, 5));
+        assert_eq!(dst.text(), "hi..\n....");
+
+        let mut dst = buffer(&["....", "...."]);
+        dst.blit(0, 0, &src, Rect::new(usize::MAX, usize::MAX, 2, 2));
+        assert_eq!(dst.text(), "....\n....");
+    }
+
+    #[test]
+    fn blit_off_the_edges() {
+        let src = buffer(&["abc", "def"]);
+        let mut dst = buffer(&["...", "..."]);
+        for &(x, y) in &[(3, 0), (0, 2), (10, 10), (usize::MAX, 0), (0, usize::MAX)] {
+            dst.blit(x, y, &src, Rect::new(0, 0, 3, 2));
+        }
+        dst.blit(0, 0, &src, Rect::new(0, 0, 0, 2));
+        assert_eq!(dst.text(), "...\n...");
+
+        let mut empty = Buffer::new(0, 0);
+        empty.blit(0, 0, &src, Rect::new(0, 0, 3, 2));
+        dst.blit(0, 0, &empty, Rect::new(0, 0, 3, 2));
+        assert_eq!(dst.text(), "...\n...");
+    }
+
+    #[test]
+    fn scroll_within_a_rect() {
+        let fill = Cell::new('_');
+        let mut b = buffer(&["abc", "def", "ghi", "jkl"]);
+        b.scroll(Rect::new(1, 1, 2, 3), 1, true, fill);
+        assert_eq!(b.text(), "abc\ndhi\ngkl\nj__");
+        b.scroll(Rect::new(1, 1, 2, 3), 2, false, fill);
+        assert_eq!(b.text(), "abc\nd__\ng__\njhi");
+        b.scroll(Rect::new(0, 0, 3, 4), 0, true, fill);
+        assert_eq!(b.text(), "abc\nd__\ng__\njhi");
+    }
+
+    #[test]
+    fn scroll_by_more_than_the_rect() {
+        let fill = Cell::new('_');
+        for &n in &[2, 3, usize::MAX] {
+            let mut b = buffer(&["ab", "cd"]);
+            b.scroll(Rect::new(0, 0, 2, 2), n, true, fill);
+            assert_eq!(b.text(), "__\n__");
+            let mut b = buffer(&["ab", "cd"]);
+            b.scroll(Rect::new(0, 0, 2, 2), n, false, fill);
+            assert_eq!(b.text(), "__\n__");
+        }
+    }
+
+    #[test]
+    fn scroll_a_rect_partly_off_the_buffer() {
+        let fill = Cell::new('_');
+        let mut b = buffer(&["ab", "cd", "ef"]);
+        b.scroll(Rect::new(1, 1, 10, 10), 1, true, fill);
+        assert_eq!(b.text(), "ab\ncf\ne_");
+        b.scroll(Rect::new(5, 5, 2, 2), 1, true, fill);
+        b.scroll(Rect::new(usize::MAX, 0, 2, 2), 1, false, fill);
+        assert_eq!(b.text(), "ab\ncf\ne_");
+
+        let mut empty = Buffer::new(0, 0);
+        empty.scroll(Rect::new(0, 0, 1, 1), 1, true, fill);
+    }
+}
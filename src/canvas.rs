@@ -0,0 +1,228 @@
+//! Drawing with pixels smaller than a cell.
+//!
+//! A canvas is a grid of pixels that is turned into cells with `Canvas::render`, then drawn
+//! like any other `Buffer` with `RustBox::blit`. `HalfBlocks` packs two pixels of any color into
+//! each cell, stacked one above the other, and `Braille` packs a two by four grid of dots of a
+//! single color into each cell.
+//!
+//! ```
+//! use rustbox::Color;
+//! use rustbox::canvas::{Canvas, HalfBlocks};
+//!
+//! let mut canvas = HalfBlocks::new(4, 4);
+//! canvas.line(0, 0, 3, 3, Color::Red);
+//! let cells = canvas.render();
+//! assert_eq!((cells.width(), cells.height()), (4, 2));
+//! assert_eq!(cells.text(), "▀▄  \n  ▀▄");
+//! ```
+
+use super::{Buffer, Cell, Color, ImageError, ImageSource, RB_NORMAL};
+use buffer::BLANK;
+use image;
+use palette;
+
+/// The drawing operations shared by every kind of canvas. Coordinates are in pixels, and
+/// pixels outside the canvas are ignored.
+pub trait Canvas {
+    /// The width in pixels.
+    fn width(&self) -> usize;
+
+    /// The height in pixels.
+    fn height(&self) -> usize;
+
+    /// Returns the color of the pixel at `(x, y)`, or `None` if it is unset or outside the
+    /// canvas.
+    fn get(&self, x: usize, y: usize) -> Option<Color>;
+
+    /// Set the pixel at `(x, y)`, or unset it with `None`.
+    fn set(&mut self, x: usize, y: usize, color: Option<Color>);
+
+    /// The cells that show the canvas, one for every cell's worth of pixels. Cells with no
+    /// pixels set are blank.
+    fn render(&self) -> Buffer;
+
+    /// Unset every pixel.
+    fn clear(&mut self) {
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                self.set(x, y, None);
+            }
+        }
+    }
+
+    /// Draw a line from `(x0, y0)` to `(x1, y1)`, both ends included.
+    fn line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: Color) {
+        let (mut x, mut y) = (x0 as isize, y0 as isize);
+        let (x1, y1) = (x1 as isize, y1 as isize);
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (sx, sy) = (if x < x1 { 1 } else { -1 }, if y < y1 { 1 } else { -1 });
+        let mut err = dx + dy;
+        loop {
+            self.set(x as usize, y as usize, Some(color));
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Draw `source` with its top left pixel at `(x, y)`, at one pixel per image pixel. Each
+    /// pixel gets the nearest of the 256 colors of `OutputMode::EightBit`, and fully
+    /// transparent pixels are left alone.
+    fn draw_image(&mut self, x: usize, y: usize, source: &ImageSource) -> Result<(), ImageError> {
+        let pixels = image::Pixels::from_source(source)?;
+        for py in 0..pixels.height {
+            for px in 0..pixels.width {
+                if let Some((r, g, b)) = pixels.get(px, py) {
+                    let color = palette::nearest(r, g, b, 16..=255);
+                    self.set(x.saturating_add(px), y.saturating_add(py),
+                             Some(Color::from_256color(color as u16)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A canvas with two pixels to a cell, drawn with `▀` and `▄` so that each pixel can have a
+/// color of its own.
+#[derive(Clone, PartialEq, Debug)]
+pub struct HalfBlocks {
+    width: usize,
+    height: usize,
+    pixels: Vec<Option<Color>>,
+}
+
+impl HalfBlocks {
+    /// A canvas `width` pixels wide and `height` pixels high, which takes up `width` by
+    /// `height / 2` cells (rounded up).
+    pub fn new(width: usize, height: usize) -> HalfBlocks {
+        HalfBlocks { width, height, pixels: vec![None; width * height] }
+    }
+}
+
+impl Canvas for HalfBlocks {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get(&self, x: usize, y: usize) -> Option<Color> {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x]
+        } else {
+            None
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: Option<Color>) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    fn render(&self) -> Buffer {
+        let mut buffer = Buffer::new(self.width, self.height.div_ceil(2));
+        for cy in 0..buffer.height() {
+            for cx in 0..self.width {
+                let cell = match (self.get(cx, cy * 2), self.get(cx, cy * 2 + 1)) {
+                    (None, None) => BLANK,
+                    (Some(top), bottom) =>
+                        Cell { ch: '▀', fg: top, bg: bottom.unwrap_or(Color::Default), style: RB_NORMAL },
+                    (None, Some(bottom)) =>
+                        Cell { ch: '▄', fg: bottom, bg: Color::Default, style: RB_NORMAL },
+                };
+                buffer.set(cx, cy, cell);
+            }
+        }
+        buffer
+    }
+}
+
+/// A canvas with eight pixels to a cell, drawn with the Unicode braille patterns. A cell can
+/// only have one foreground color, so all of the dots in it take the color of the last one
+/// set.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Braille {
+    width: usize,
+    height: usize,
+    pixels: Vec<Option<Color>>,
+    // The color of each cell, as last set.
+    colors: Vec<Color>,
+}
+
+// The bit for each dot of a braille pattern, indexed by [y][x].
+const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+impl Braille {
+    /// A canvas `width` pixels wide and `height` pixels high, which takes up `width / 2` by
+    /// `height / 4` cells (rounded up).
+    pub fn new(width: usize, height: usize) -> Braille {
+        Braille {
+            width,
+            height,
+            pixels: vec![None; width * height],
+            colors: vec![Color::Default; width.div_ceil(2) * height.div_ceil(4)],
+        }
+    }
+}
+
+impl Canvas for Braille {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get(&self, x: usize, y: usize) -> Option<Color> {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x]
+        } else {
+            None
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: Option<Color>) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+            if let Some(color) = color {
+                self.colors[(y / 4) * self.width.div_ceil(2) + x / 2] = color;
+            }
+        }
+    }
+
+    fn render(&self) -> Buffer {
+        let mut buffer = Buffer::new(self.width.div_ceil(2), self.height.div_ceil(4));
+        for cy in 0..buffer.height() {
+            for cx in 0..buffer.width() {
+                let mut bits = 0;
+                for (dy, row) in DOTS.iter().enumerate() {
+                    for (dx, bit) in row.iter().enumerate() {
+                        if self.get(cx * 2 + dx, cy * 4 + dy).is_some() {
+                            bits |= bit;
+                        }
+                    }
+                }
+                if bits != 0 {
+                    let ch = ::std::char::from_u32(0x2800 + bits).unwrap();
+                    let fg = self.colors[cy * buffer.width() + cx];
+                    buffer.set(cx, cy, Cell { ch, fg, bg: Color::Default, style: RB_NORMAL });
+                }
+            }
+        }
+        buffer
+    }
+}
@@ -0,0 +1,237 @@
+// Working out what the terminal can do from the environment and its terminfo entry, without
+// asking it anything.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// How many colors the terminal can show, as worked out by `ColorSupport::detect`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ColorSupport {
+    Colors8,
+    Colors16,
+    Colors256,
+    /// 24-bit color.
+    TrueColor,
+}
+
+// Terminals that support 24-bit color whether or not they say so in COLORTERM or terminfo.
+const TRUECOLOR_TERMS: &[&str] = &["xterm-kitty", "xterm-ghostty", "alacritty", "wezterm", "foot", "contour"];
+const TRUECOLOR_PROGRAMS: &[&str] = &["iTerm.app", "WezTerm", "vscode", "Hyper", "ghostty"];
+
+impl ColorSupport {
+    /// Work out the terminal's color support from `COLORTERM`, the terminal's terminfo entry,
+    /// and a list of terminals known to support 24-bit color. Terminals that give no clue are
+    /// assumed to have 8 colors.
+    pub fn detect() -> ColorSupport {
+        let var = |name| env::var(name).unwrap_or_default();
+        let (term, program) = (var("TERM"), var("TERM_PROGRAM"));
+        let info = Terminfo::load(&term);
+        if ["truecolor", "24bit"].contains(&var("COLORTERM").as_str()) ||
+            info.as_ref().is_some_and(|info| info.rgb) ||
+            TRUECOLOR_TERMS.contains(&term.as_str()) ||
+            TRUECOLOR_PROGRAMS.contains(&program.as_str())
+        {
+            return ColorSupport::TrueColor;
+        }
+        match info.and_then(|info| info.colors) {
+            Some(n) if n >= 256 => ColorSupport::Colors256,
+            _ if term.ends_with("256color") => ColorSupport::Colors256,
+            Some(n) if n >= 16 => ColorSupport::Colors16,
+            _ => ColorSupport::Colors8,
+        }
+    }
+}
+
+// Whether the terminal can be expected to show Unicode box-drawing characters properly. Not
+// if the locale's character set isn't UTF-8, nor on the Linux console, whose fonts only have
+// some of them. With no locale set there's nothing to go on, so UTF-8 is assumed.
+pub fn unicode_supported() -> bool {
+    if env::var("TERM").ok().as_deref() == Some("linux") {
+        return false;
+    }
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty());
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        },
+        None => true,
+    }
+}
+
+// The little of a terminfo entry that matters here.
+struct Terminfo {
+    // The `colors` number.
+    colors: Option<i32>,
+    // Whether the `RGB` extended capability, or tmux's older `Tc`, is set.
+    rgb: bool,
+}
+
+// The index of `colors` among the standard numbers.
+const COLORS: usize = 13;
+
+impl Terminfo {
+    // Find and read the compiled entry for `term`, looking where ncurses looks.
+    fn load(term: &str) -> Option<Terminfo> {
+        let first = term.chars().next()?;
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        if let Some(dir) = env::var_os("TERMINFO") {
+            dirs.push(dir.into());
+        }
+        if let Some(home) = env::var_os("HOME") {
+            dirs.push(PathBuf::from(home).join(".terminfo"));
+        }
+        if let Ok(list) = env::var("TERMINFO_DIRS") {
+            dirs.extend(list.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from));
+        }
+        for dir in &["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo", "/usr/lib/terminfo"] {
+            dirs.push(dir.into());
+        }
+        // Entries are filed under their first letter, or on macOS its hex code.
+        let subdirs = [first.to_string(), format!("{:x}", first as u32)];
+        dirs.iter()
+            .flat_map(|dir| subdirs.iter().map(move |sub| dir.join(sub).join(term)))
+            .filter_map(|path| fs::read(path).ok())
+            .find_map(|data| Terminfo::parse(&data))
+    }
+
+    // Parse the compiled format described in term(5).
+    fn parse(data: &[u8]) -> Option<Terminfo> {
+        // The newer format has 32-bit numbers.
+        let num_size = match short(data, 0)? {
+            0o432 => 2,
+            0o1036 => 4,
+            _ => return None,
+        };
+        let (names, bools, nums) = (count(data, 2)?, count(data, 4)?, count(data, 6)?);
+        let (strings, table) = (count(data, 8)?, count(data, 10)?);
+        let nums_at = even(12 + names + bools);
+        let colors = if nums > COLORS {
+            number(data, nums_at + COLORS * num_size, num_size).filter(|&n| n >= 0)
+        } else {
+            None
+        };
+        let extended = even(nums_at + nums * num_size + strings * 2 + table);
+        let rgb = extended_flag(data, extended, num_size, b"RGB") ||
+            extended_flag(data, extended, num_size, b"Tc");
+        Some(Terminfo { colors, rgb })
+    }
+}
+
+// Whether the extended boolean capability `name` is set, in the extended section starting at
+// `at`. The names of extended capabilities come at the end of its string table, after the
+// values of the extended strings, bools first.
+fn extended_flag(data: &[u8], at: usize, num_size: usize, name: &[u8]) -> bool {
+    let find = || -> Option<bool> {
+        let (bools, nums, strings) = (count(data, at)?, count(data, at + 2)?, count(data, at + 4)?);
+        let table_size = count(data, at + 8)?;
+        let table_at = even(at + 10 + bools) + nums * num_size + strings * 2 + (bools + nums + strings) * 2;
+        let mut pieces: Vec<&[u8]> = data.get(table_at..table_at + table_size)?.split(|&b| b == 0).collect();
+        // The table ends with a NUL, which leaves an empty piece after it.
+        pieces.pop();
+        let names = pieces.get(pieces.len().checked_sub(bools + nums + strings)?..)?;
+        let i = names[..bools].iter().position(|&n| n == name)?;
+        Some(data.get(at + 10 + i) == Some(&1))
+    };
+    find().unwrap_or(false)
+}
+
+fn short(data: &[u8], at: usize) -> Option<i32> {
+    data.get(at..at + 2).map(|b| i16::from_le_bytes([b[0], b[1]]) as i32)
+}
+
+fn number(data: &[u8], at: usize, size: usize) -> Option<i32> {
+    match size {
+        2 => short(data, at),
+        _ => data.get(at..at + 4).map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+    }
+}
+
+fn count(data: &[u8], at: usize) -> Option<usize> {
+    short(data, at).filter(|&n| n >= 0).map(|n| n as usize)
+}
+
+fn even(at: usize) -> usize {
+    at + at % 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Terminfo, COLORS};
+
+    // A compiled entry with `colors` set, and the given extended bools, in the format with
+    // `num_size` byte numbers.
+    fn entry(num_size: usize, colors: i32, ext_bools: &[(&str, bool)]) -> Vec<u8> {
+        let names = b"test|a test terminal\0";
+        let nums = COLORS + 1;
+        let magic: i16 = if num_size == 2 { 0o432 } else { 0o1036 };
+        let mut data = Vec::new();
+        for &n in &[magic, names.len() as i16, 0, nums as i16, 0, 0] {
+            data.extend_from_slice(&n.to_le_bytes());
+        }
+        data.extend_from_slice(names);
+        if data.len() % 2 == 1 {
+            data.push(0);
+        }
+        for i in 0..nums {
+            let n = if i == COLORS { colors } else { -1 };
+            data.extend_from_slice(&n.to_le_bytes()[..num_size]);
+        }
+
+        let table: Vec<u8> = ext_bools.iter().flat_map(|&(name, _)| name.bytes().chain(Some(0))).collect();
+        let count = ext_bools.len() as i16;
+        for &n in &[count, 0, 0, count, table.len() as i16] {
+            data.extend_from_slice(&n.to_le_bytes());
+        }
+        data.extend(ext_bools.iter().map(|&(_, set)| set as u8));
+        if data.len() % 2 == 1 {
+            data.push(0);
+        }
+        let mut offset = 0;
+        for &(name, _) in ext_bools {
+            data.extend_from_slice(&(offset as i16).to_le_bytes());
+            offset += name.len() + 1;
+        }
+        data.extend_from_slice(&table);
+        data
+    }
+
+    #[test]
+    fn parses_16_bit_numbers() {
+        let info = Terminfo::parse(&entry(2, 256, &[])).unwrap();
+        assert_eq!(info.colors, Some(256));
+        assert!(!info.rgb);
+    }
+
+    #[test]
+    fn parses_32_bit_numbers() {
+        let info = Terminfo::parse(&entry(4, 0x1000000, &[])).unwrap();
+        assert_eq!(info.colors, Some(0x1000000));
+    }
+
+    #[test]
+    fn missing_colors() {
+        assert_eq!(Terminfo::parse(&entry(2, -1, &[])).unwrap().colors, None);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut data = entry(2, 8, &[]);
+        data[0] = 0;
+        assert!(Terminfo::parse(&data).is_none());
+        assert!(Terminfo::parse(&[]).is_none());
+    }
+
+    #[test]
+    fn rgb_extended_bools() {
+        for &num_size in &[2, 4] {
+            assert!(Terminfo::parse(&entry(num_size, 256, &[("AX", true), ("RGB", true)])).unwrap().rgb);
+            assert!(Terminfo::parse(&entry(num_size, 256, &[("Tc", true), ("XT", true)])).unwrap().rgb);
+            assert!(!Terminfo::parse(&entry(num_size, 256, &[("AX", true), ("RGB", false)])).unwrap().rgb);
+            assert!(!Terminfo::parse(&entry(num_size, 256, &[("AX", true), ("XT", true)])).unwrap().rgb);
+        }
+    }
+}
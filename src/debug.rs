@@ -0,0 +1,105 @@
+// The overlay shown by `RustBox::set_debug_overlay`.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::{Event, Key, Mouse, MouseKind, Stats, MOD_ALT, MOD_CTRL, MOD_META, MOD_SHIFT};
+#[cfg(feature = "log")]
+use logger;
+use text;
+
+// How many recent events, and log records, are shown.
+const EVENTS: usize = 4;
+#[cfg(feature = "log")]
+const LOGS: usize = 3;
+// The width of the overlay, in columns.
+pub const WIDTH: usize = 26;
+
+#[derive(Default)]
+pub struct Overlay {
+    presents: VecDeque<Instant>,
+    events: VecDeque<String>,
+}
+
+impl Overlay {
+    pub fn event(&mut self, event: &Event) {
+        if let Some(described) = describe(event) {
+            if self.events.len() == EVENTS {
+                self.events.pop_front();
+            }
+            self.events.push_back(described);
+        }
+    }
+
+    pub fn present(&mut self, now: Instant) {
+        self.presents.push_back(now);
+        while self.presents.front().is_some_and(|&t| now.duration_since(t) > Duration::from_secs(1)) {
+            self.presents.pop_front();
+        }
+    }
+
+    // The lines of the overlay, each cut to `WIDTH`: the frame rate over the last second,
+    // what the last frame wrote, the screen size, the latest events and, with the `log`
+    // feature, the latest log records kept by `logger::log_to_memory`.
+    pub fn lines(&self, last: &Stats, width: usize, height: usize) -> Vec<String> {
+        let mut lines = vec![
+            format!("{} fps, {} bytes", self.presents.len(), last.bytes),
+            format!("{}x{}, {} changed", width, height, last.changed),
+        ];
+        lines.extend(self.events.iter().cloned());
+        #[cfg(feature = "log")]
+        {
+            let logs = logger::recent_logs();
+            lines.extend(logs.into_iter().rev().take(LOGS).rev());
+        }
+        lines.iter().map(|line| format!("{:<1$}", text::truncate(line, WIDTH), WIDTH)).collect()
+    }
+}
+
+fn describe(event: &Event) -> Option<String> {
+    Some(match *event {
+        Event::KeyEvent(key) => describe_key(key),
+        Event::ModifiedKeyEvent(key, mods) => {
+            let names = [(MOD_CTRL, "Ctrl-"), (MOD_ALT, "Alt-"), (MOD_SHIFT, "Shift-"), (MOD_META, "Meta-")];
+            let prefix: String = names.iter().filter(|&&(m, _)| mods.contains(m)).map(|&(_, n)| n).collect();
+            describe_key(key).replacen("key ", &format!("key {}", prefix), 1)
+        },
+        Event::KeyEventRaw(emod, key, ch) => format!("raw key {} {} {}", emod, key, ch),
+        Event::ResizeEvent(w, h) => format!("resize {}x{}", w, h),
+        Event::MouseEvent(button, x, y) => format!("mouse {} at {},{}", describe_button(button), x, y),
+        Event::Mouse(kind, button, x, y) => {
+            let kind = match kind {
+                MouseKind::Press => "press",
+                MouseKind::Release => "release",
+                MouseKind::Drag => "drag",
+                MouseKind::Moved => "move",
+            };
+            format!("mouse {} {} at {},{}", kind, describe_button(button), x, y)
+        },
+        Event::Interrupt => String::from("interrupt"),
+        Event::Quit => String::from("quit"),
+        Event::NoEvent => return None,
+    })
+}
+
+fn describe_button(button: Mouse) -> &'static str {
+    match button {
+        Mouse::Left => "left",
+        Mouse::Right => "right",
+        Mouse::Middle => "middle",
+        Mouse::Release => "release",
+        Mouse::WheelUp => "wheel up",
+        Mouse::WheelDown => "wheel down",
+    }
+}
+
+fn describe_key(key: Key) -> String {
+    match key {
+        Key::Char(' ') => "key Space".to_string(),
+        Key::Char(ch) => format!("key {}", ch),
+        Key::Ctrl(ch) => format!("key Ctrl-{}", ch),
+        Key::F(n) => format!("key F{}", n),
+        Key::Unknown(code) => format!("key unknown {}", code),
+        other => format!("key {:?}", other),
+    }
+}
@@ -0,0 +1,166 @@
+use super::{Buffer, Cell, Color, Rect, RustBox, Style, TestBackend, Text, Window};
+use text::ControlPolicy;
+
+/// Something that can be drawn into a `Surface`.
+///
+/// A widget implements `Draw`, drawing itself into whatever `Surface` it is given: the screen,
+/// a `Window` onto part of it, a `TestBackend`, or an offscreen `Buffer`. Widgets written against
+/// these traits work with all of them, and don't depend on any one type's methods.
+///
+/// ```
+/// use rustbox::{Color, Draw, Rect, Surface, TestBackend, RB_BOLD};
+///
+/// struct Title(&'static str);
+///
+/// impl Draw for Title {
+///     fn draw(&self, target: &mut dyn Surface, area: Rect) {
+///         let x = area.x + area.w.saturating_sub(self.0.len()) / 2;
+///         target.print(x, area.y, RB_BOLD, Color::White, Color::Default, self.0);
+///     }
+/// }
+///
+/// let mut rb = TestBackend::new(8, 1);
+/// Title("menu").draw(&mut rb, Rect::new(0, 0, 8, 1));
+/// rb.present().unwrap();
+/// assert_eq!(rb.buffer_as_string(), "  menu  ");
+/// ```
+pub trait Draw {
+    /// Draw into the `area` part of `target`.
+    fn draw(&self, target: &mut dyn Surface, area: Rect);
+}
+
+/// A grid of cells that widgets can draw into. Positions outside it are ignored.
+pub trait Surface {
+    fn width(&self) -> usize;
+
+    fn height(&self) -> usize;
+
+    /// Returns the cell at `(x, y)`, or `None` if it lies outside the surface.
+    fn cell(&self, x: usize, y: usize) -> Option<Cell>;
+
+    fn set_cell(&mut self, x: usize, y: usize, cell: Cell);
+
+    /// The whole surface, from `(0, 0)`.
+    fn area(&self) -> Rect {
+        Rect::new(0, 0, self.width(), self.height())
+    }
+
+    /// Print `s` starting at `(x, y)`, a character to a cell, with control characters dealt
+    /// with as the default `ControlPolicy` says, or the surface's own where it has one.
+    fn print(&mut self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+        for (dx, dy, ch) in ControlPolicy::default().place(s) {
+            self.set_cell(x.saturating_add(dx), y.saturating_add(dy), Cell { ch, fg, bg, style: sty });
+        }
+    }
+
+    fn print_text(&mut self, x: usize, y: usize, text: &Text) -> usize {
+        for (dx, ch, attr) in text.columns() {
+            self.set_cell(x.saturating_add(dx), y, Cell::new(ch).attr(attr));
+        }
+        text.width()
+    }
+
+    fn fill_rect(&mut self, rect: Rect, cell: Cell) {
+        let rect = rect.intersection(self.area());
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                self.set_cell(x, y, cell);
+            }
+        }
+    }
+}
+
+impl Surface for Buffer {
+    fn width(&self) -> usize {
+        Buffer::width(self)
+    }
+
+    fn height(&self) -> usize {
+        Buffer::height(self)
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        self.get(x, y).cloned()
+    }
+
+    fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        self.set(x, y, cell);
+    }
+}
+
+/// Draws into the back buffer.
+impl Surface for RustBox {
+    fn width(&self) -> usize {
+        RustBox::width(self)
+    }
+
+    fn height(&self) -> usize {
+        RustBox::height(self)
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        RustBox::cell(self, x, y)
+    }
+
+    fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        RustBox::set_cell(self, x, y, cell);
+    }
+
+    fn print(&mut self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+        RustBox::print(self, x, y, sty, fg, bg, s);
+    }
+
+    fn fill_rect(&mut self, rect: Rect, cell: Cell) {
+        RustBox::fill_rect(self, rect, cell);
+    }
+}
+
+/// Draws in the window's own coordinates, clipped to it.
+impl<'a> Surface for Window<'a> {
+    fn width(&self) -> usize {
+        Window::width(self)
+    }
+
+    fn height(&self) -> usize {
+        Window::height(self)
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        Window::cell(self, x, y)
+    }
+
+    fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        Window::set_cell(self, x, y, cell);
+    }
+
+    fn print(&mut self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+        Window::print(self, x, y, sty, fg, bg, s);
+    }
+
+    fn fill_rect(&mut self, rect: Rect, cell: Cell) {
+        Window::fill_rect(self, rect, cell);
+    }
+}
+
+/// Draws into the back buffer.
+impl Surface for TestBackend {
+    fn width(&self) -> usize {
+        TestBackend::width(self)
+    }
+
+    fn height(&self) -> usize {
+        TestBackend::height(self)
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        TestBackend::cell(self, x, y)
+    }
+
+    fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        TestBackend::set_cell(self, x, y, cell);
+    }
+
+    fn print(&mut self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+        TestBackend::print(self, x, y, sty, fg, bg, s);
+    }
+}
@@ -0,0 +1,141 @@
+//! Converting buffers into formats for use outside the terminal.
+
+use super::{Buffer, Cell, Color, RB_BOLD, RB_REVERSE, RB_UNDERLINE};
+use buffer::shown_cells;
+
+/// Render `buffer` as a self-contained stream of text and ANSI escape sequences, one line per
+/// row, that reproduces it when written to a terminal. It starts and ends with an attribute
+/// reset, and makes no assumptions about the terminal's state, so it can be saved to a file
+/// and shown later with `cat`.
+pub fn ansi(buffer: &Buffer) -> Vec<u8> {
+    let mut out = String::from("\x1b[0m");
+    for (y, row) in buffer.iter_rows().enumerate() {
+        if y > 0 {
+            out.push_str("\x1b[0m\n");
+        }
+        push_ansi_row(&mut out, row);
+    }
+    out.push_str("\x1b[0m");
+    out.into_bytes()
+}
+
+// Append the text of `row` to `out`, with SGR sequences wherever the attributes change. The
+// cell after a wide character is left out, since the terminal puts the character over it.
+pub(crate) fn push_ansi_row(out: &mut String, row: &[Cell]) {
+    let mut last = None;
+    for cell in shown_cells(row) {
+        let attrs = (cell.fg, cell.bg, cell.style);
+        if last != Some(attrs) {
+            out.push_str(&sgr(cell));
+            last = Some(attrs);
+        }
+        out.push(cell.ch);
+    }
+}
+
+// A full SGR sequence selecting the cell's attributes, starting from a reset.
+pub(crate) fn sgr(cell: &Cell) -> String {
+    let mut params = vec![String::from("0")];
+    if cell.style.contains(RB_BOLD) {
+        params.push(String::from("1"));
+    }
+    if cell.style.contains(RB_UNDERLINE) {
+        params.push(String::from("4"));
+    }
+    if cell.style.contains(RB_REVERSE) {
+        params.push(String::from("7"));
+    }
+    params.push(sgr_color(cell.fg, 30));
+    params.push(sgr_color(cell.bg, 40));
+    format!("\x1b[{}m", params.join(";"))
+}
+
+// `base` is 30 for foreground colors and 40 for background colors.
+fn sgr_color(color: Color, base: u16) -> String {
+    match color {
+        Color::Default => (base + 9).to_string(),
+        Color::Byte(b) => format!("{};5;{}", base + 8, b),
+        Color::Rgb(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+        named => (base + named.as_256color()).to_string(),
+    }
+}
+
+// What `Color::Default` looks like in HTML output.
+const HTML_DEFAULT_FG: (u8, u8, u8) = (0xe5, 0xe5, 0xe5);
+const HTML_DEFAULT_BG: (u8, u8, u8) = (0x00, 0x00, 0x00);
+
+/// Render `buffer` as an HTML `<pre>` block with inline styles, e.g. for embedding screenshots
+/// of an application in its documentation.
+///
+/// Colors are given the values of the standard xterm palette, and `Color::Default` is shown as
+/// light gray on black.
+pub fn html(buffer: &Buffer) -> String {
+    let mut out = format!("<pre style=\"color:{};background-color:{}\">",
+                          css_color(HTML_DEFAULT_FG), css_color(HTML_DEFAULT_BG));
+    for (y, row) in buffer.iter_rows().enumerate() {
+        if y > 0 {
+            out.push('\n');
+        }
+        let row: Vec<&Cell> = shown_cells(row).collect();
+        let mut start = 0;
+        while start < row.len() {
+            let attrs = (row[start].fg, row[start].bg, row[start].style);
+            let len = row[start..].iter()
+                .take_while(|cell| (cell.fg, cell.bg, cell.style) == attrs)
+                .count();
+            out.push_str(&format!("<span style=\"{}\">", css(row[start])));
+            for cell in &row[start..start + len] {
+                match cell.ch {
+                    '&' => out.push_str("&amp;"),
+                    '<' => out.push_str("&lt;"),
+                    '>' => out.push_str("&gt;"),
+                    '"' => out.push_str("&quot;"),
+                    ch => out.push(ch),
+                }
+            }
+            out.push_str("</span>");
+            start += len;
+        }
+    }
+    out.push_str("</pre>");
+    out
+}
+
+fn css(cell: &Cell) -> String {
+    let mut fg = cell.fg.rgb().unwrap_or(HTML_DEFAULT_FG);
+    let mut bg = cell.bg.rgb().unwrap_or(HTML_DEFAULT_BG);
+    if cell.style.contains(RB_REVERSE) {
+        ::std::mem::swap(&mut fg, &mut bg);
+    }
+    let mut css = format!("color:{};background-color:{}", css_color(fg), css_color(bg));
+    if cell.style.contains(RB_BOLD) {
+        css.push_str(";font-weight:bold");
+    }
+    if cell.style.contains(RB_UNDERLINE) {
+        css.push_str(";text-decoration:underline");
+    }
+    css
+}
+
+fn css_color((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ansi, html};
+    use {Color, TestBackend, RB_NORMAL};
+
+    #[test]
+    fn wide_characters_cover_the_next_cell() {
+        let mut rb = TestBackend::new(5, 1);
+        rb.print(0, 0, RB_NORMAL, Color::Default, Color::Default, "漢a漢");
+        rb.present().unwrap();
+        let buffer = rb.presented();
+        assert_eq!(buffer.text(), "漢a漢");
+        assert_eq!(rb.buffer_as_string(), "漢a漢");
+        assert_eq!(buffer.styled_text(), "[fg=Default bg=Default]漢a漢");
+        assert_eq!(ansi(&buffer), "\x1b[0m\x1b[0;39;49m漢a漢\x1b[0m".as_bytes());
+        assert!(html(&buffer).contains(">漢a漢</span>"));
+    }
+}
@@ -0,0 +1,106 @@
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::{Event, EventResult, RustBox};
+
+// How often the input thread started by `RustBox::spawn_input_thread` checks whether it should
+// stop, which is also how long dropping the `RenderHandle` can take.
+const STOP_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The event-reading half of a `RustBox`, as returned by `RustBox::split`.
+///
+/// This can be moved to another thread that sits in `poll_event` while the `RenderHandle` keeps
+/// drawing.
+pub struct InputHandle {
+    rb: Arc<RustBox>,
+}
+
+impl InputHandle {
+    pub fn poll_event(&self, raw: bool) -> EventResult {
+        self.rb.poll_event(raw)
+    }
+
+    pub fn peek_event(&self, timeout: Duration, raw: bool) -> EventResult {
+        self.rb.peek_event(timeout, raw)
+    }
+}
+
+/// The drawing half of a `RustBox`, as returned by `RustBox::split`. It dereferences to the
+/// `RustBox` itself, so all of the drawing methods are available on it.
+pub struct RenderHandle {
+    rb: Arc<RustBox>,
+    reader: Option<Reader>,
+}
+
+// The thread started by `RustBox::spawn_input_thread`.
+struct Reader {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl Deref for RenderHandle {
+    type Target = RustBox;
+
+    fn deref(&self) -> &RustBox {
+        &self.rb
+    }
+}
+
+// The input thread is stopped, and waited for, before the `RenderHandle` lets go of the
+// `RustBox`, so that termbox is shut down on the thread that dropped the handle rather than
+// whenever the input thread gets round to it, perhaps after the process has exited.
+impl Drop for RenderHandle {
+    fn drop(&mut self) {
+        if let Some(reader) = self.reader.take() {
+            reader.stop.store(true, Ordering::SeqCst);
+            let _ = reader.thread.join();
+        }
+    }
+}
+
+pub fn split(rb: RustBox) -> (InputHandle, RenderHandle) {
+    let rb = Arc::new(rb);
+    (InputHandle { rb: rb.clone() }, RenderHandle { rb, reader: None })
+}
+
+pub fn spawn_input_thread(rb: RustBox, capacity: usize, raw: bool) -> (Receiver<EventResult>, RenderHandle) {
+    let rb = Arc::new(rb);
+    let (sender, receiver) = mpsc::sync_channel(capacity);
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let (rb, stop) = (rb.clone(), stop.clone());
+        thread::spawn(move || read_input(&rb, &sender, &stop, raw))
+    };
+    (receiver, RenderHandle { rb, reader: Some(Reader { stop, thread }) })
+}
+
+// Send events until told to stop, the receiver is dropped, or there is an error, which is sent
+// on as the last result.
+fn read_input(rb: &RustBox, sender: &SyncSender<EventResult>, stop: &AtomicBool, raw: bool) {
+    while !stop.load(Ordering::SeqCst) {
+        let mut result = match rb.peek_event(STOP_INTERVAL, raw) {
+            Ok(Event::NoEvent) => continue,
+            result => result,
+        };
+        let last = result.is_err();
+        // Waiting for room in the channel has to give way to being told to stop too.
+        loop {
+            match sender.try_send(result) {
+                Ok(()) => break,
+                Err(TrySendError::Full(_)) if stop.load(Ordering::SeqCst) => return,
+                Err(TrySendError::Full(r)) => {
+                    result = r;
+                    thread::sleep(STOP_INTERVAL);
+                },
+                Err(TrySendError::Disconnected(_)) => return,
+            }
+        }
+        if last {
+            return;
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Images drawn with `RustBox::draw_image`.
+//!
+//! termbox only knows about characters, so images are sent to the terminal separately, straight
+//! after `present`, using whichever graphics protocol the terminal supports. The cells an image
+//! covers are filled with a special blank in the back buffer, which lets `present` tell when
+//! something has been drawn over a sixel or iTerm2 image and it needs to be sent again. Kitty images live
+//! on their own layer below the text, so they are only ever sent once, and deleted by id once
+//! they have been cleared.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+use base64;
+use palette::CUBE_LEVELS;
+
+/// The pixels of an image, row by row from the top left corner.
+#[derive(Clone, Copy, Debug)]
+pub enum ImageSource<'a> {
+    /// Three bytes per pixel.
+    Rgb { width: usize, height: usize, data: &'a [u8] },
+    /// Four bytes per pixel. Pixels with an alpha below 128 are left transparent.
+    Rgba { width: usize, height: usize, data: &'a [u8] },
+}
+
+/// How images are shown.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageProtocol {
+    /// The kitty graphics protocol, supported by kitty, WezTerm, Ghostty and Konsole.
+    Kitty,
+    /// iTerm2's inline images (OSC 1337), also supported by WezTerm and mintty.
+    Iterm2,
+    /// DEC sixel graphics, supported by xterm (with `-ti vt340`), mlterm, foot, WezTerm and
+    /// others.
+    Sixel,
+    /// No graphics support: each cell is filled with the average color of the part of the
+    /// image it covers.
+    Placeholder,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageError {
+    /// The image data was the wrong length for its size. Holds the actual and the expected
+    /// length in bytes.
+    WrongLength(usize, usize),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ImageError::WrongLength(actual, expected) =>
+                write!(fmt, "Image data is {} bytes long, expected {}", actual, expected),
+        }
+    }
+}
+
+impl Error for ImageError {
+    fn description(&self) -> &str {
+        match *self {
+            ImageError::WrongLength(..) => "Image data is the wrong length",
+        }
+    }
+}
+
+// What the cells covered by an image are filled with. It looks like a space, but isn't one, so
+// that drawing ordinary spaces over an image makes termbox repaint those cells.
+pub const FILL: char = '\u{a0}';
+
+// An image decoded into pixels, with `None` for transparent ones.
+pub struct Pixels {
+    pub width: usize,
+    pub height: usize,
+    data: Vec<Option<(u8, u8, u8)>>,
+}
+
+impl Pixels {
+    pub fn from_source(source: &ImageSource) -> Result<Pixels, ImageError> {
+        let (width, height, data, bytes) = match *source {
+            ImageSource::Rgb { width, height, data } => (width, height, data, 3),
+            ImageSource::Rgba { width, height, data } => (width, height, data, 4),
+        };
+        let expected = width * height * bytes;
+        if data.len() != expected {
+            return Err(ImageError::WrongLength(data.len(), expected));
+        }
+        let data = data.chunks(bytes)
+            .map(|p| if bytes == 4 && p[3] < 128 { None } else { Some((p[0], p[1], p[2])) })
+            .collect();
+        Ok(Pixels { width, height, data })
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<(u8, u8, u8)> {
+        self.data[y * self.width + x]
+    }
+
+    // Four bytes per pixel, with transparent pixels fully transparent.
+    pub fn rgba(&self) -> Vec<u8> {
+        self.data.iter()
+            .flat_map(|p| match *p {
+                Some((r, g, b)) => [r, g, b, 255],
+                None => [0, 0, 0, 0],
+            }.to_vec())
+            .collect()
+    }
+
+    // Scale to fit within `width` by `height` pixels, keeping the aspect ratio.
+    pub fn fit(&self, width: usize, height: usize) -> Pixels {
+        if self.width == 0 || self.height == 0 {
+            return Pixels { width: 0, height: 0, data: Vec::new() };
+        }
+        // Compare width / self.width with height / self.height without dividing.
+        let (w, h) = if width * self.height <= height * self.width {
+            (width, (self.height * width / self.width).max(1))
+        } else {
+            ((self.width * height / self.height).max(1), height)
+        };
+        let mut data = Vec::with_capacity(w * h);
+        for y in 0..h {
+            for x in 0..w {
+                data.push(self.get(x * self.width / w, y * self.height / h));
+            }
+        }
+        Pixels { width: w, height: h, data }
+    }
+
+    // The average of the opaque pixels in the given rectangle, or `None` if they are all
+    // transparent.
+    pub fn average(&self, x1: usize, y1: usize, x2: usize, y2: usize) -> Option<(u8, u8, u8)> {
+        let (mut r, mut g, mut b, mut n) = (0, 0, 0, 0);
+        for y in y1..y2.min(self.height) {
+            for x in x1..x2.min(self.width) {
+                if let Some((pr, pg, pb)) = self.get(x, y) {
+                    r += pr as usize;
+                    g += pg as usize;
+                    b += pb as usize;
+                    n += 1;
+                }
+            }
+        }
+        let average = |total: usize| total.checked_div(n).map(|v| v as u8);
+        Some((average(r)?, average(g)?, average(b)?))
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Encoded {
+    // An escape sequence that draws the image into the cells, with the cursor at their top left
+    // corner.
+    Cells(Vec<u8>),
+    // `width` by `height` RGBA pixels for the kitty graphics protocol, shown over `cols` by
+    // `rows` cells.
+    Kitty { width: usize, height: usize, cols: usize, rows: usize, rgba: Vec<u8> },
+}
+
+// An image waiting to be, or already, sent to the terminal.
+#[derive(Clone)]
+pub struct Placement {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+    pub image: Encoded,
+    // The kitty image id, or 0 if there isn't one (yet).
+    pub id: u32,
+    // The covered cells of the back buffer as they were when the image was last sent.
+    pub cells: Vec<(u32, u16, u16)>,
+}
+
+impl Placement {
+    pub fn new(x: usize, y: usize, w: usize, h: usize, image: Encoded) -> Placement {
+        Placement { x, y, w, h, image, id: 0, cells: Vec::new() }
+    }
+
+    pub fn same_image(&self, other: &Placement) -> bool {
+        (self.x, self.y, self.w, self.h) == (other.x, other.y, other.w, other.h) &&
+            self.image == other.image
+    }
+
+    pub fn is_kitty(&self) -> bool {
+        matches!(self.image, Encoded::Kitty { .. })
+    }
+
+    // The escape sequence that draws the image, with the cursor at its top left corner.
+    pub fn escape(&self) -> Vec<u8> {
+        match self.image {
+            Encoded::Cells(ref data) => data.clone(),
+            Encoded::Kitty { width, height, cols, rows, ref rgba } => {
+                // Transmit and place in one go, below the text (z=-1) and without moving the
+                // cursor (C=1). The payload has to be sent in chunks of at most 4096 bytes.
+                let payload = base64::encode(rgba);
+                let chunks: Vec<&[u8]> = payload.as_bytes().chunks(4096).collect();
+                let mut out = Vec::new();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = if i + 1 < chunks.len() { 1 } else { 0 };
+                    if i == 0 {
+                        out.extend_from_slice(format!(
+                            "\x1b_Ga=T,f=32,s={},v={},c={},r={},i={},z=-1,C=1,q=2,m={};",
+                            width, height, cols, rows, self.id, more).as_bytes());
+                    } else {
+                        out.extend_from_slice(format!("\x1b_Gm={};", more).as_bytes());
+                    }
+                    out.extend_from_slice(chunk);
+                    out.extend_from_slice(b"\x1b\\");
+                }
+                out
+            },
+        }
+    }
+
+    pub fn overlaps(&self, x: usize, y: usize, w: usize, h: usize) -> bool {
+        self.x < x.saturating_add(w) && x < self.x + self.w &&
+            self.y < y.saturating_add(h) && y < self.y + self.h
+    }
+}
+
+// Delete a kitty image, along with its data.
+pub fn kitty_delete(id: u32) -> Vec<u8> {
+    format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", id).into_bytes()
+}
+
+// Delete every kitty image.
+pub const KITTY_DELETE_ALL: &[u8] = b"\x1b_Ga=d,d=A,q=2\x1b\\";
+
+// Asks a terminal whether it supports the kitty graphics protocol, by having it check a 1x1
+// image without displaying it. Terminals that do reply with `ESC _ G i=31;OK ESC \`.
+pub const KITTY_QUERY: &[u8] = b"\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\";
+pub const KITTY_QUERY_OK: &[u8] = b"\x1b_Gi=31;OK";
+
+// Encode as an iTerm2 inline image, scaled by the terminal to fit `cols` by `rows` cells.
+pub fn iterm2(pixels: &Pixels, cols: usize, rows: usize) -> Vec<u8> {
+    let png = png(pixels);
+    let mut out = format!("\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:",
+                          png.len(), cols, rows).into_bytes();
+    out.extend_from_slice(base64::encode(&png).as_bytes());
+    out.extend_from_slice(b"\x07");
+    out
+}
+
+// Encode as an RGBA PNG. The image data is stored uncompressed, since it is only going to the
+// terminal and a deflate implementation isn't worth carrying for that.
+fn png(pixels: &Pixels) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend_from_slice(&(pixels.width as u32).to_be_bytes());
+    header.extend_from_slice(&(pixels.height as u32).to_be_bytes());
+    // 8 bits per channel, RGBA, and the default compression, filter and interlace methods.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // Each row starts with its filter type, which is always none.
+    let rgba = pixels.rgba();
+    let mut raw = Vec::with_capacity(rgba.len() + pixels.height);
+    for row in rgba.chunks(pixels.width * 4) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // A zlib stream of stored deflate blocks.
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(0xffff).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        zlib.push(if i + 1 == blocks.len() { 1 } else { 0 });
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    if blocks.is_empty() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    png_chunk(&mut out, b"IHDR", &header);
+    png_chunk(&mut out, b"IDAT", &zlib);
+    png_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+// The index into CUBE_LEVELS nearest to `v`.
+fn cube_level(v: u8) -> usize {
+    (0..CUBE_LEVELS.len())
+        .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+        .unwrap()
+}
+
+// Encode as sixel data. Colors are reduced to the 6x6x6 cube of the 256 color palette, which
+// every sixel terminal has enough color registers for.
+pub fn sixel(pixels: &Pixels) -> Vec<u8> {
+    let colors: Vec<Option<usize>> = pixels.data.iter()
+        .map(|p| p.map(|(r, g, b)| cube_level(r) * 36 + cube_level(g) * 6 + cube_level(b)))
+        .collect();
+    let (width, height) = (pixels.width, pixels.height);
+
+    // P2 = 1 leaves transparent pixels alone rather than painting them with the background.
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    let used: BTreeSet<usize> = colors.iter().filter_map(|&c| c).collect();
+    for &c in &used {
+        let percent = |level: usize| (CUBE_LEVELS[level] as usize * 100 + 127) / 255;
+        out.push_str(&format!("#{};2;{};{};{}", c, percent(c / 36), percent(c / 6 % 6),
+                              percent(c % 6)));
+    }
+
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let band_colors: BTreeSet<usize> = rows.clone()
+            .flat_map(|y| colors[y * width..(y + 1) * width].iter().filter_map(|&c| c))
+            .collect();
+        for (i, &color) in band_colors.iter().enumerate() {
+            if i > 0 {
+                // Back to the start of the band for the next color.
+                out.push('$');
+            }
+            out.push_str(&format!("#{}", color));
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let bits = rows.clone()
+                    .filter(|&y| colors[y * width + x] == Some(color))
+                    .fold(0, |bits, y| bits | 1 << (y - band));
+                let ch = (63 + bits) as u8 as char;
+                run = match run {
+                    Some((c, n)) if c == ch => Some((c, n + 1)),
+                    Some((c, n)) => {
+                        push_run(&mut out, c, n);
+                        Some((ch, 1))
+                    },
+                    None => Some((ch, 1)),
+                };
+            }
+            if let Some((c, n)) = run {
+                push_run(&mut out, c, n);
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out.into_bytes()
+}
+
+fn push_run(out: &mut String, ch: char, n: usize) {
+    if n > 3 {
+        out.push_str(&format!("!{}{}", n, ch));
+    } else {
+        for _ in 0..n {
+            out.push(ch);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+// The composition shown by `RustBox::set_preedit`.
+
+use text;
+
+pub struct Preedit {
+    pub x: usize,
+    pub y: usize,
+    pub text: String,
+    // The cursor's place in `text`, in characters.
+    pub cursor: usize,
+}
+
+impl Preedit {
+    // Where each character goes, as `(x, y, ch)`.
+    pub fn cells(&self) -> Vec<(usize, usize, char)> {
+        text::columns(&self.text).into_iter()
+            .map(|(dx, ch)| (self.x.saturating_add(dx), self.y, ch))
+            .collect()
+    }
+
+    // Where the hardware cursor goes, which is where terminals put their input method's
+    // candidate window.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let before: String = self.text.chars().take(self.cursor).collect();
+        (self.x.saturating_add(text::width(&before)), self.y)
+    }
+}
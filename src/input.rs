@@ -0,0 +1,376 @@
+//! Decoding terminal input without a terminal.
+//!
+//! RustBox's own events are decoded by termbox. `Parser` turns raw input bytes into the same
+//! events, following the same rules, but does no I/O of its own, so it can decode input that
+//! arrives some other way (over a socket, say) and is easy to test and fuzz. It also decodes
+//! the modifiers terminals send with keys, which termbox mostly drops: an escape before a key
+//! for Alt, and the modifier parameter of sequences such as `ESC [ 1 ; 5 D` for Ctrl+Left.
+//!
+//! ```
+//! use rustbox::{Event, Key, Mouse, MOD_ALT, MOD_CTRL};
+//! use rustbox::input::Parser;
+//!
+//! let mut parser = Parser::new();
+//! let events: Vec<Event> = parser.feed(b"h\x1b[A\x1b[M @!\x1b[1;5D\x1b\r").collect();
+//! assert_eq!(events, vec![
+//!     Event::KeyEvent(Key::Char('h')),
+//!     Event::KeyEvent(Key::Up),
+//!     Event::MouseEvent(Mouse::Left, 31, 0),
+//!     Event::ModifiedKeyEvent(Key::Left, MOD_CTRL),
+//!     Event::ModifiedKeyEvent(Key::Enter, MOD_ALT),
+//! ]);
+//!
+//! // A lone escape might be the start of a sequence, so it waits for more input, or a flush.
+//! assert_eq!(parser.feed(b"\x1b").count(), 0);
+//! assert_eq!(parser.flush().collect::<Vec<_>>(), vec![Event::KeyEvent(Key::Esc)]);
+//! ```
+
+use std::str;
+use std::vec;
+
+use super::{Event, Key, Modifiers, Mouse, MOD_ALT, MOD_SHIFT};
+
+// The escape sequences for special keys sent by the terminals termbox knows about.
+const KEYS: &[(&[u8], Key)] = &[
+    (b"\x1b[A", Key::Up), (b"\x1bOA", Key::Up),
+    (b"\x1b[B", Key::Down), (b"\x1bOB", Key::Down),
+    (b"\x1b[C", Key::Right), (b"\x1bOC", Key::Right),
+    (b"\x1b[D", Key::Left), (b"\x1bOD", Key::Left),
+    (b"\x1b[H", Key::Home), (b"\x1bOH", Key::Home), (b"\x1b[1~", Key::Home), (b"\x1b[7~", Key::Home),
+    (b"\x1b[F", Key::End), (b"\x1bOF", Key::End), (b"\x1b[4~", Key::End), (b"\x1b[8~", Key::End),
+    (b"\x1b[2~", Key::Insert),
+    (b"\x1b[3~", Key::Delete),
+    (b"\x1b[5~", Key::PageUp),
+    (b"\x1b[6~", Key::PageDown),
+    (b"\x1bOP", Key::F(1)), (b"\x1b[11~", Key::F(1)), (b"\x1b[[A", Key::F(1)),
+    (b"\x1bOQ", Key::F(2)), (b"\x1b[12~", Key::F(2)), (b"\x1b[[B", Key::F(2)),
+    (b"\x1bOR", Key::F(3)), (b"\x1b[13~", Key::F(3)), (b"\x1b[[C", Key::F(3)),
+    (b"\x1bOS", Key::F(4)), (b"\x1b[14~", Key::F(4)), (b"\x1b[[D", Key::F(4)),
+    (b"\x1b[15~", Key::F(5)), (b"\x1b[[E", Key::F(5)),
+    (b"\x1b[17~", Key::F(6)),
+    (b"\x1b[18~", Key::F(7)),
+    (b"\x1b[19~", Key::F(8)),
+    (b"\x1b[20~", Key::F(9)),
+    (b"\x1b[21~", Key::F(10)),
+    (b"\x1b[23~", Key::F(11)),
+    (b"\x1b[24~", Key::F(12)),
+];
+
+/// Turns terminal input into events. See the module documentation.
+#[derive(Clone, Debug, Default)]
+pub struct Parser {
+    // Input that might be the start of a sequence that hasn't all arrived yet.
+    pending: Vec<u8>,
+}
+
+// What a parse of the start of the input found.
+enum Parsed {
+    // An event, and how many bytes it took up.
+    Event(Event, usize),
+    // Bytes that mean nothing, such as an unknown escape sequence, to be skipped.
+    Skip(usize),
+    // The start of something that needs more input to finish.
+    Incomplete,
+}
+
+impl Parser {
+    pub fn new() -> Parser {
+        Parser { pending: Vec::new() }
+    }
+
+    /// Decode `bytes`, along with anything left over from before, returning the events in
+    /// them. Anything at the end that might be the start of a longer sequence is kept until
+    /// more input arrives, or `flush` is called.
+    pub fn feed(&mut self, bytes: &[u8]) -> vec::IntoIter<Event> {
+        self.pending.extend_from_slice(bytes);
+        self.parse(false)
+    }
+
+    /// Decode whatever input is being kept back, as though no more were coming. Terminals don't
+    /// mark where a lone Esc ends, so this should be called when no input has arrived for a
+    /// short while, e.g. 50ms.
+    pub fn flush(&mut self) -> vec::IntoIter<Event> {
+        self.parse(true)
+    }
+
+    fn parse(&mut self, flush: bool) -> vec::IntoIter<Event> {
+        let mut events = Vec::new();
+        let mut start = 0;
+        while start < self.pending.len() {
+            match parse_one(&self.pending[start..], flush) {
+                Parsed::Event(event, n) => {
+                    events.push(event);
+                    start += n;
+                },
+                Parsed::Skip(n) => start += n,
+                Parsed::Incomplete => break,
+            }
+        }
+        self.pending.drain(..start);
+        events.into_iter()
+    }
+}
+
+fn key(key: Key, n: usize) -> Parsed {
+    Parsed::Event(Event::KeyEvent(key), n)
+}
+
+// Decode the event at the start of `input`, which isn't empty. With `flush` set, nothing is
+// incomplete: the start of a sequence is taken as it is.
+fn parse_one(input: &[u8], flush: bool) -> Parsed {
+    match input[0] {
+        0x1b => parse_escape(input, flush),
+        0 => key(Key::Char('\0'), 1),
+        b if b < 0x80 => key(Key::from_code(b as u16).unwrap_or(Key::Char(b as char)), 1),
+        b => {
+            let len = match b {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => return Parsed::Skip(1),
+            };
+            if input.len() < len {
+                return if flush { Parsed::Skip(input.len()) } else { Parsed::Incomplete };
+            }
+            match str::from_utf8(&input[..len]).ok().and_then(|s| s.chars().next()) {
+                Some(ch) => key(Key::Char(ch), len),
+                None => Parsed::Skip(1),
+            }
+        },
+    }
+}
+
+fn parse_escape(input: &[u8], flush: bool) -> Parsed {
+    if let Some(&(seq, k)) = KEYS.iter().find(|&&(seq, _)| input.starts_with(seq)) {
+        return key(k, seq.len());
+    }
+    if input.starts_with(b"\x1b[Z") {
+        return Parsed::Event(Event::ModifiedKeyEvent(Key::Tab, MOD_SHIFT), 3);
+    }
+    if input.starts_with(b"\x1b[M") {
+        return parse_x10_mouse(input, flush);
+    }
+    if input.starts_with(b"\x1b[<") {
+        return parse_sgr_mouse(input, flush);
+    }
+    // Wait for the rest of anything that could still become a known sequence.
+    let prefix = KEYS.iter().any(|&(seq, _)| seq.starts_with(input)) || b"\x1b[Z".starts_with(input)
+        || b"\x1b[M".starts_with(input) || b"\x1b[<".starts_with(input);
+    if prefix && !flush {
+        return Parsed::Incomplete;
+    }
+    if input.starts_with(b"\x1b[") {
+        return match input[2..].iter().position(|&b| (0x40..=0x7e).contains(&b)) {
+            Some(i) => parse_modified_csi(&input[..i + 3]),
+            None if flush => Parsed::Skip(input.len()),
+            None => Parsed::Incomplete,
+        };
+    }
+    // Terminals send a key pressed with Alt as an escape followed by the key, as in
+    // `InputMode::Alt`. An escape that doesn't come before anything is a key of its own.
+    if input.len() > 1 && input[1] != 0x1b {
+        match parse_one(&input[1..], flush) {
+            Parsed::Event(Event::KeyEvent(k), n) => return Parsed::Event(Event::ModifiedKeyEvent(k, MOD_ALT), n + 1),
+            Parsed::Incomplete => return Parsed::Incomplete,
+            _ => (),
+        }
+    }
+    key(Key::Esc, 1)
+}
+
+// A complete CSI sequence that isn't in `KEYS`, which may be one of them with a modifier
+// parameter added: `ESC [ 1 ; m X` for the arrows, Home, End and F1 to F4, and
+// `ESC [ n ; m ~` for the rest. Anything else is skipped as a whole, rather than typed out.
+fn parse_modified_csi(seq: &[u8]) -> Parsed {
+    let last = seq[seq.len() - 1];
+    let params: Option<Vec<u32>> = str::from_utf8(&seq[2..seq.len() - 1]).ok()
+        .and_then(|params| params.split(';').map(|n| n.parse().ok()).collect());
+    let (n, m) = match params.as_ref().map(|params| &params[..]) {
+        Some(&[n, m]) => (n, m),
+        _ => return Parsed::Skip(seq.len()),
+    };
+    let plain = match last {
+        b'~' => format!("\x1b[{}~", n),
+        b'P'..=b'S' => format!("\x1bO{}", last as char),
+        _ => format!("\x1b[{}", last as char),
+    };
+    match KEYS.iter().find(|&&(seq, _)| seq == plain.as_bytes()) {
+        Some(&(_, k)) => {
+            let mods = Modifiers::from_param(m);
+            let event = if mods.is_empty() { Event::KeyEvent(k) } else { Event::ModifiedKeyEvent(k, mods) };
+            Parsed::Event(event, seq.len())
+        },
+        None => Parsed::Skip(seq.len()),
+    }
+}
+
+// `ESC [ M`, then the button and position, each plus 32.
+fn parse_x10_mouse(input: &[u8], flush: bool) -> Parsed {
+    if input.len() < 6 {
+        return if flush { Parsed::Skip(input.len()) } else { Parsed::Incomplete };
+    }
+    let b = input[3].wrapping_sub(32) as u32;
+    let x = input[4] as i32 - 33;
+    let y = input[5] as i32 - 33;
+    Parsed::Event(Event::MouseEvent(mouse_button(b, false), x, y), 6)
+}
+
+// `ESC [ <`, then the button and position separated by `;`, ending with `M`, or `m` on release.
+// A sequence that ends some other way is skipped up to where it goes wrong, taking its final
+// byte with it if it has one, so that what was typed after it isn't lost.
+fn parse_sgr_mouse(input: &[u8], flush: bool) -> Parsed {
+    let end = match input[3..].iter().position(|&b| !(b.is_ascii_digit() || b == b';')) {
+        Some(i) => i + 3,
+        None if flush || input.len() > 32 => return Parsed::Skip(input.len()),
+        None => return Parsed::Incomplete,
+    };
+    match input[end] {
+        b'M' | b'm' => (),
+        0x40..=0x7e => return Parsed::Skip(end + 1),
+        _ => return Parsed::Skip(end),
+    }
+    let params: Vec<Option<u32>> = str::from_utf8(&input[3..end]).unwrap_or("")
+        .split(';')
+        .map(|p| p.parse().ok())
+        .collect();
+    match params[..] {
+        [Some(b), Some(x), Some(y)] => {
+            let mouse = mouse_button(b, input[end] == b'm');
+            Parsed::Event(Event::MouseEvent(mouse, x as i32 - 1, y as i32 - 1), end + 1)
+        },
+        _ => Parsed::Skip(end + 1),
+    }
+}
+
+fn mouse_button(b: u32, released: bool) -> Mouse {
+    if b & 64 != 0 {
+        return if b & 1 == 0 { Mouse::WheelUp } else { Mouse::WheelDown };
+    }
+    match b & 3 {
+        _ if released => Mouse::Release,
+        0 => Mouse::Left,
+        1 => Mouse::Middle,
+        2 => Mouse::Right,
+        _ => Mouse::Release,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+    use {Event, Key, Mouse, MOD_ALT, MOD_CTRL, MOD_SHIFT};
+
+    fn parse(bytes: &[u8]) -> Vec<Event> {
+        Parser::new().feed(bytes).collect()
+    }
+
+    fn keys(keys: &[Key]) -> Vec<Event> {
+        keys.iter().map(|&k| Event::KeyEvent(k)).collect()
+    }
+
+    #[test]
+    fn sequences_split_across_feeds() {
+        let mut parser = Parser::new();
+        assert_eq!(parser.feed(b"a\x1b[").collect::<Vec<_>>(), keys(&[Key::Char('a')]));
+        assert_eq!(parser.feed(b"1;5").count(), 0);
+        assert_eq!(parser.feed(b"D").collect::<Vec<_>>(), vec![Event::ModifiedKeyEvent(Key::Left, MOD_CTRL)]);
+
+        assert_eq!(parser.feed(b"\xe6\xbc").count(), 0);
+        assert_eq!(parser.feed(b"\xa2").collect::<Vec<_>>(), keys(&[Key::Char('漢')]));
+
+        assert_eq!(parser.feed(b"\x1b[M ").count(), 0);
+        assert_eq!(parser.feed(b"!!").collect::<Vec<_>>(), vec![Event::MouseEvent(Mouse::Left, 0, 0)]);
+
+        assert_eq!(parser.feed(b"\x1b[<0;1").count(), 0);
+        assert_eq!(parser.feed(b"0;5m").collect::<Vec<_>>(), vec![Event::MouseEvent(Mouse::Release, 9, 4)]);
+    }
+
+    #[test]
+    fn escape_then_flush() {
+        let mut parser = Parser::new();
+        assert_eq!(parser.feed(b"\x1b").count(), 0);
+        assert_eq!(parser.flush().collect::<Vec<_>>(), keys(&[Key::Esc]));
+        assert_eq!(parser.flush().count(), 0);
+
+        // The start of a sequence that never finishes isn't typed out.
+        assert_eq!(parser.feed(b"\x1b[1;").count(), 0);
+        assert_eq!(parser.flush().count(), 0);
+
+        assert_eq!(parser.feed(b"\x1b\x1b").collect::<Vec<_>>(), keys(&[Key::Esc]));
+        assert_eq!(parser.flush().collect::<Vec<_>>(), keys(&[Key::Esc]));
+
+        assert_eq!(parse(b"\x1bx\x1b\r"), vec![
+            Event::ModifiedKeyEvent(Key::Char('x'), MOD_ALT),
+            Event::ModifiedKeyEvent(Key::Enter, MOD_ALT),
+        ]);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        assert_eq!(parse(b"\xffa\x80b"), keys(&[Key::Char('a'), Key::Char('b')]));
+        assert_eq!(parse(b"\xe6ab"), keys(&[Key::Char('a'), Key::Char('b')]));
+
+        let mut parser = Parser::new();
+        assert_eq!(parser.feed(b"a\xe6\xbc").collect::<Vec<_>>(), keys(&[Key::Char('a')]));
+        assert_eq!(parser.flush().count(), 0);
+        assert_eq!(parser.feed(b"b").collect::<Vec<_>>(), keys(&[Key::Char('b')]));
+    }
+
+    #[test]
+    fn csi_modifiers() {
+        assert_eq!(parse(b"\x1b[1;3A\x1b[3;2~\x1b[1;5P\x1b[1;1H\x1b[Z"), vec![
+            Event::ModifiedKeyEvent(Key::Up, MOD_ALT),
+            Event::ModifiedKeyEvent(Key::Delete, MOD_SHIFT),
+            Event::ModifiedKeyEvent(Key::F(1), MOD_CTRL),
+            Event::KeyEvent(Key::Home),
+            Event::ModifiedKeyEvent(Key::Tab, MOD_SHIFT),
+        ]);
+        assert_eq!(parse(b"\x1b[1;6C"), vec![Event::ModifiedKeyEvent(Key::Right, MOD_CTRL | MOD_SHIFT)]);
+        // Unknown sequences are skipped as a whole.
+        assert_eq!(parse(b"\x1b[99;5~\x1b[?1;2cz"), keys(&[Key::Char('z')]));
+    }
+
+    #[test]
+    fn kitty_reports_are_skipped() {
+        assert_eq!(parse(b"\x1b[97;5u\x1b[13u\x1b[97;1:2u\x1b[1;1:3Aa"), keys(&[Key::Char('a')]));
+    }
+
+    #[test]
+    fn x10_mouse() {
+        assert_eq!(parse(b"\x1b[M !!\x1b[M\"+&\x1b[M#!!\x1b[M`!!\x1b[Ma!!"), vec![
+            Event::MouseEvent(Mouse::Left, 0, 0),
+            Event::MouseEvent(Mouse::Right, 10, 5),
+            Event::MouseEvent(Mouse::Release, 0, 0),
+            Event::MouseEvent(Mouse::WheelUp, 0, 0),
+            Event::MouseEvent(Mouse::WheelDown, 0, 0),
+        ]);
+    }
+
+    #[test]
+    fn sgr_mouse() {
+        assert_eq!(parse(b"\x1b[<0;10;5M\x1b[<1;10;5M\x1b[<0;10;5m\x1b[<65;1;1M"), vec![
+            Event::MouseEvent(Mouse::Left, 9, 4),
+            Event::MouseEvent(Mouse::Middle, 9, 4),
+            Event::MouseEvent(Mouse::Release, 9, 4),
+            Event::MouseEvent(Mouse::WheelDown, 0, 0),
+        ]);
+        // Missing or extra parameters.
+        assert_eq!(parse(b"\x1b[<0;10M\x1b[<0;1;2;3Ma"), keys(&[Key::Char('a')]));
+    }
+
+    #[test]
+    fn malformed_sgr_mouse_keeps_what_follows() {
+        // A final byte ends the sequence, and goes with it.
+        assert_eq!(parse(b"\x1b[<1;2xab"), keys(&[Key::Char('a'), Key::Char('b')]));
+        // Other bytes are left to be read on their own.
+        assert_eq!(parse(b"\x1b[<1;2\x1bOAq"), keys(&[Key::Up, Key::Char('q')]));
+        assert_eq!(parse(b"\x1b[<1;2\xe6\xbc\xa2"), keys(&[Key::Char('漢')]));
+
+        // Far too long to be a mouse report, even before anything else arrives.
+        let mut long = b"\x1b[<".to_vec();
+        long.extend_from_slice(&[b'1'; 40]);
+        let mut parser = Parser::new();
+        assert_eq!(parser.feed(&long).count(), 0);
+        assert_eq!(parser.feed(b"a").collect::<Vec<_>>(), keys(&[Key::Char('a')]));
+    }
+}
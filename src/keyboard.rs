@@ -1,4 +1,5 @@
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub enum Key {
     Tab,
     Enter,
@@ -84,3 +85,34 @@ impl Key {
         }
     }
 }
+
+bitflags! {
+    /// The modifier keys held down with a key, in `Event::ModifiedKeyEvent`.
+    flags Modifiers: u8 {
+        const MOD_SHIFT = 0x01,
+        const MOD_ALT = 0x02,
+        const MOD_CTRL = 0x04,
+        const MOD_META = 0x08,
+    }
+}
+
+impl Modifiers {
+    // From the modifier parameter of a CSI sequence, which is one more than the flags.
+    pub(crate) fn from_param(param: u32) -> Modifiers {
+        Modifiers::from_bits_truncate(param.saturating_sub(1) as u8)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Modifiers {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bits.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Modifiers {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Modifiers, D::Error> {
+        u8::deserialize(deserializer).map(Modifiers::from_bits_truncate)
+    }
+}
@@ -0,0 +1,283 @@
+//! Layers drawn over the back buffer by `RustBox::present`, for popups and overlays that come
+//! and go without disturbing what is underneath.
+
+use std::cmp;
+
+use super::{Cell, Color, Rect, Style, Text};
+use text::ControlPolicy;
+
+/// A screen-sized sheet of cells drawn over the back buffer by `present`.
+///
+/// Cells start out transparent, so only what has been drawn on a layer covers what is beneath
+/// it. Since layers are composited afresh for every frame, removing a popup is a matter of
+/// clearing or removing its layer; the cells underneath were never touched. See
+/// `RustBox::add_layer`.
+///
+/// ```
+/// use rustbox::{Color, TestBackend, RB_NORMAL};
+///
+/// let mut rb = TestBackend::new(6, 1);
+/// rb.print(0, 0, RB_NORMAL, Color::Default, Color::Default, "abcdef");
+/// rb.add_layer("popup", 1);
+/// rb.layer("popup").unwrap().print(2, 0, RB_NORMAL, Color::Red, Color::Default, "XY");
+/// rb.present().unwrap();
+/// assert_eq!(rb.buffer_as_string(), "abXYef");
+///
+/// rb.remove_layer("popup");
+/// rb.present().unwrap();
+/// assert_eq!(rb.buffer_as_string(), "abcdef");
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct Layer {
+    width: usize,
+    height: usize,
+    cells: Vec<LayerCell>,
+    // The screen's, for `print`, as of when the layer was last handed out.
+    control_policy: ControlPolicy,
+}
+
+/// A cell on a layer, each part of which may be left transparent so that whatever is beneath
+/// shows through.
+///
+/// A cell with only `bg` set recolors what is underneath without hiding it, which makes for a
+/// scrim that dims the screen behind a dialog:
+///
+/// ```
+/// use rustbox::{Color, Rect, TestBackend, RB_NORMAL};
+/// use rustbox::layer::LayerCell;
+///
+/// let mut rb = TestBackend::new(4, 1);
+/// rb.print(0, 0, RB_NORMAL, Color::White, Color::Blue, "text");
+/// rb.add_layer("scrim", 1);
+/// rb.layer("scrim").unwrap().fill_partial(Rect::new(0, 0, 4, 1), LayerCell::default().bg(Color::Black));
+/// rb.present().unwrap();
+/// assert_eq!(rb.buffer_as_string(), "text");
+/// assert_eq!(rb.presented().get(0, 0).unwrap().bg, Color::Black);
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct LayerCell {
+    pub ch: Option<char>,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub style: Option<Style>,
+}
+
+impl LayerCell {
+    pub fn ch(self, ch: char) -> LayerCell {
+        LayerCell { ch: Some(ch), ..self }
+    }
+
+    pub fn fg(self, fg: Color) -> LayerCell {
+        LayerCell { fg: Some(fg), ..self }
+    }
+
+    pub fn bg(self, bg: Color) -> LayerCell {
+        LayerCell { bg: Some(bg), ..self }
+    }
+
+    pub fn style(self, style: Style) -> LayerCell {
+        LayerCell { style: Some(style), ..self }
+    }
+
+    /// Whether every part is left transparent.
+    pub fn is_transparent(&self) -> bool {
+        *self == LayerCell::default()
+    }
+
+    /// The cell that results from drawing this one over `below`.
+    pub fn over(&self, below: Cell) -> Cell {
+        Cell {
+            ch: self.ch.unwrap_or(below.ch),
+            fg: self.fg.unwrap_or(below.fg),
+            bg: self.bg.unwrap_or(below.bg),
+            style: self.style.unwrap_or(below.style),
+        }
+    }
+}
+
+/// Fully opaque.
+impl From<Cell> for LayerCell {
+    fn from(cell: Cell) -> LayerCell {
+        LayerCell { ch: Some(cell.ch), fg: Some(cell.fg), bg: Some(cell.bg), style: Some(cell.style) }
+    }
+}
+
+impl Layer {
+    /// A layer of the given size with every cell transparent.
+    pub fn new(width: usize, height: usize) -> Layer {
+        Layer {
+            width,
+            height,
+            cells: vec![LayerCell::default(); width * height],
+            control_policy: ControlPolicy::default(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the cell at `(x, y)`, or `None` if it lies outside the layer.
+    pub fn get(&self, x: usize, y: usize) -> Option<LayerCell> {
+        if x < self.width && y < self.height {
+            Some(self.cells[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    /// Replace the cell at `(x, y)` with an opaque one. Positions outside the layer are ignored.
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        self.set_partial(x, y, cell.into());
+    }
+
+    /// Replace the cell at `(x, y)`, leaving the parts of it that are `None` transparent.
+    pub fn set_partial(&mut self, x: usize, y: usize, cell: LayerCell) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = cell;
+        }
+    }
+
+    /// Make the cell at `(x, y)` transparent again.
+    pub fn erase(&mut self, x: usize, y: usize) {
+        self.set_partial(x, y, LayerCell::default());
+    }
+
+    /// Make every cell transparent.
+    pub fn clear(&mut self) {
+        for cell in &mut self.cells {
+            *cell = LayerCell::default();
+        }
+    }
+
+    /// Make every cell in `rect` transparent.
+    pub fn clear_rect(&mut self, rect: Rect) {
+        self.fill_partial(rect, LayerCell::default());
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, cell: Cell) {
+        self.fill_partial(rect, cell.into());
+    }
+
+    pub fn fill_partial(&mut self, rect: Rect, cell: LayerCell) {
+        let rect = rect.intersection(Rect::new(0, 0, self.width, self.height));
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                self.set_partial(x, y, cell);
+            }
+        }
+    }
+
+    /// Like `RustBox::print`: characters are placed by their width, and tabs, newlines and
+    /// other control characters are dealt with as the screen's control policy says.
+    pub fn print(&mut self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+        for (dx, dy, ch) in self.control_policy.place(s) {
+            self.set(x.saturating_add(dx), y.saturating_add(dy), Cell { ch, fg, bg, style: sty });
+        }
+    }
+
+    pub fn print_text(&mut self, x: usize, y: usize, text: &Text) -> usize {
+        for (dx, ch, attr) in text.columns() {
+            self.set(x.saturating_add(dx), y, Cell::new(ch).attr(attr));
+        }
+        text.width()
+    }
+
+    // Resize to match the screen, keeping whatever still fits.
+    pub(crate) fn resize(&mut self, width: usize, height: usize) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        let mut cells = vec![LayerCell::default(); width * height];
+        for y in 0..cmp::min(height, self.height) {
+            for x in 0..cmp::min(width, self.width) {
+                cells[y * width + x] = self.cells[y * self.width + x];
+            }
+        }
+        self.width = width;
+        self.height = height;
+        self.cells = cells;
+    }
+
+    pub(crate) fn set_control_policy(&mut self, policy: ControlPolicy) {
+        self.control_policy = policy;
+    }
+
+    pub(crate) fn cells(&self) -> &[LayerCell] {
+        &self.cells
+    }
+}
+
+// The layers of a screen, lowest first, along with their names and where they go in the stack.
+#[derive(Default)]
+pub(crate) struct Stack {
+    layers: Vec<(String, i32, Layer)>,
+}
+
+impl Stack {
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    // Add a transparent layer of the given size, or move an existing one to `z`. Layers with the
+    // same `z` stay in the order they were added.
+    pub fn add(&mut self, name: &str, z: i32, width: usize, height: usize) {
+        let layer = match self.layers.iter().position(|l| l.0 == name) {
+            Some(i) => self.layers.remove(i).2,
+            None => Layer::new(width, height),
+        };
+        let at = self.layers.iter().position(|l| l.1 > z).unwrap_or(self.layers.len());
+        self.layers.insert(at, (name.to_string(), z, layer));
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.layers.len();
+        self.layers.retain(|l| l.0 != name);
+        self.layers.len() != before
+    }
+
+    // The layer called `name`, resized to the screen first.
+    pub fn get_mut(&mut self, name: &str, width: usize, height: usize) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|l| l.0 == name).map(|l| {
+            l.2.resize(width, height);
+            &mut l.2
+        })
+    }
+
+    // Every layer, lowest first, resized to the screen.
+    pub fn resized(&mut self, width: usize, height: usize) -> Vec<&Layer> {
+        self.layers.iter_mut().map(|l| {
+            l.2.resize(width, height);
+            &l.2
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Color, TestBackend, RB_NORMAL};
+    use text::{ControlPolicy, Controls};
+
+    #[test]
+    fn print_follows_width_and_control_policy() {
+        let mut rb = TestBackend::new(12, 2);
+        rb.add_layer("l", 1);
+        rb.layer("l").unwrap().print(0, 0, RB_NORMAL, Color::Default, Color::Default, "\t漢a\nb");
+        let layer = rb.layer("l").unwrap();
+        assert!(layer.get(0, 0).unwrap().is_transparent());
+        assert_eq!(layer.get(8, 0).unwrap().ch, Some('漢'));
+        assert_eq!(layer.get(10, 0).unwrap().ch, Some('a'));
+        assert_eq!(layer.get(0, 1).unwrap().ch, Some('b'));
+
+        rb.set_control_policy(ControlPolicy { tab_width: 0, controls: Controls::Replace('?'), ..ControlPolicy::default() });
+        let layer = rb.layer("l").unwrap();
+        layer.clear();
+        layer.print(0, 0, RB_NORMAL, Color::Default, Color::Default, "\tx");
+        assert_eq!(layer.get(0, 0).unwrap().ch, Some('?'));
+        assert_eq!(layer.get(1, 0).unwrap().ch, Some('x'));
+    }
+}
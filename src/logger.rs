@@ -0,0 +1,126 @@
+//! A `log` backend that doesn't write over the screen.
+//!
+//! Anything printed to stdout or stderr while RustBox is running ends up on top of the user
+//! interface. With the `log` feature enabled, one of these functions can be used to install a
+//! logger that keeps records in memory, where the debug overlay shows the latest ones (see
+//! `RustBox::set_debug_overlay`), or appends them to a file, so that `log::warn!` and friends
+//! are safe to use anywhere in an application.
+//!
+//! ```no_run
+//! #[macro_use]
+//! extern crate log;
+//! extern crate rustbox;
+//!
+//! fn main() {
+//!     rustbox::logger::log_to_file("app.log", log::LevelFilter::Info).unwrap();
+//!     let rb = rustbox::RustBox::init(Default::default()).unwrap();
+//!     warn!("screen is {}x{}", rb.width(), rb.height());
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{self, LevelFilter, Log, Metadata, Record};
+
+// The records kept by `log_to_memory`, oldest first, and how many of them to keep.
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static CAPACITY: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+pub enum LoggerError {
+    /// A logger has already been installed, by this module or another one.
+    AlreadySet,
+    /// The log file couldn't be opened.
+    Io(io::Error),
+}
+
+impl fmt::Display for LoggerError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoggerError::Io(ref e) => write!(fmt, "Failed to open log file: {}", e),
+            LoggerError::AlreadySet => write!(fmt, "A logger has already been installed"),
+        }
+    }
+}
+
+impl Error for LoggerError {
+    fn description(&self) -> &str {
+        match *self {
+            LoggerError::AlreadySet => "A logger has already been installed",
+            LoggerError::Io(_) => "Failed to open log file",
+        }
+    }
+}
+
+struct Logger {
+    // Where records go, or `None` to keep them in memory.
+    file: Option<Mutex<File>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!("{} {}: {}", record.level(), record.target(), record.args());
+        match self.file {
+            Some(ref file) => {
+                let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                let mut file = file.lock().unwrap();
+                // There's nowhere to report a failure to log.
+                let _ = writeln!(file, "{}.{:03} {}", time.as_secs(), time.subsec_millis(), line);
+            },
+            None => {
+                let mut recent = RECENT.lock().unwrap();
+                if recent.len() >= CAPACITY.load(Ordering::SeqCst) {
+                    recent.pop_front();
+                }
+                recent.push_back(line);
+            },
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(ref file) = self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+fn install(logger: Logger, level: LevelFilter) -> Result<(), LoggerError> {
+    log::set_boxed_logger(Box::new(logger)).map_err(|_| LoggerError::AlreadySet)?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// Install a logger that keeps the latest `capacity` records at `level` or above in memory,
+/// for `recent_logs` and the debug overlay.
+pub fn log_to_memory(capacity: usize, level: LevelFilter) -> Result<(), LoggerError> {
+    install(Logger { file: None }, level)?;
+    CAPACITY.store(capacity.max(1), Ordering::SeqCst);
+    Ok(())
+}
+
+/// Install a logger that appends records at `level` or above to the file at `path`, creating
+/// it if need be. Each line starts with the time in seconds since the Unix epoch.
+pub fn log_to_file<P: AsRef<Path>>(path: P, level: LevelFilter) -> Result<(), LoggerError> {
+    let file = OpenOptions::new().create(true).append(true).open(path).map_err(LoggerError::Io)?;
+    install(Logger { file: Some(Mutex::new(file)) }, level)
+}
+
+/// The records kept by `log_to_memory`, oldest first.
+pub fn recent_logs() -> Vec<String> {
+    RECENT.lock().unwrap().iter().cloned().collect()
+}
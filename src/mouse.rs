@@ -1,4 +1,5 @@
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub enum Mouse {
     Left,
     Right,
@@ -21,3 +22,44 @@ impl Mouse {
         }
     }
 }
+
+/// What happened in an `Event::Mouse`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum MouseKind {
+    /// A button was pressed, or the wheel turned.
+    Press,
+    /// A button was let go. Terminals don't say which, so the button is `Mouse::Release`.
+    Release,
+    /// The mouse moved with a button held down.
+    Drag,
+    /// The mouse moved with no button held down, which is only reported with
+    /// `MouseTracking::Motion`. The button is `Mouse::Release`.
+    Moved,
+}
+
+/// Which mouse events are reported, and how. See `InitOptions::mouse_tracking`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MouseTracking {
+    /// Mouse events come as `Event::MouseEvent`, with drags showing up as more presses of the
+    /// button held down.
+    Clicks,
+    /// Mouse events come as `Event::Mouse`, which tells presses, releases and drags apart.
+    Drag,
+    /// Like `Drag`, but the mouse moving with no button held down is reported too, for hover
+    /// effects. Terminals send a lot of these.
+    Motion,
+}
+
+// Set in a mouse event's `emod` when the mouse moved rather than a button changing.
+const TB_MOD_MOTION: u8 = 0x02;
+
+// Tell what a mouse event from termbox is from its button and modifiers.
+pub(crate) fn kind(button: Mouse, emod: u8) -> MouseKind {
+    match (button, emod & TB_MOD_MOTION != 0) {
+        (Mouse::Release, true) => MouseKind::Moved,
+        (_, true) => MouseKind::Drag,
+        (Mouse::Release, false) => MouseKind::Release,
+        (_, false) => MouseKind::Press,
+    }
+}
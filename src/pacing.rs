@@ -0,0 +1,82 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Keeps a render loop to a steady frame rate without busy-waiting.
+///
+/// The loop waits for input with `peek_event`, for no longer than `timeout`, so it wakes up as
+/// soon as input arrives, and draws a frame whenever one is `due`.
+///
+/// ```no_run
+/// use rustbox::{Event, FrameLimiter, Key, RustBox};
+///
+/// let rb = RustBox::init(Default::default()).unwrap();
+/// let mut limiter = FrameLimiter::new(30);
+/// loop {
+///     match rb.peek_event(limiter.timeout(), false) {
+///         Ok(Event::KeyEvent(Key::Char('q'))) => break,
+///         _ => (),
+///     }
+///     if limiter.due() {
+///         // Draw the frame here.
+///         rb.present().unwrap();
+///         limiter.presented();
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct FrameLimiter {
+    interval: Duration,
+    next: Instant,
+}
+
+impl FrameLimiter {
+    /// A limiter allowing at most `fps` frames a second. The first frame is due straight away.
+    ///
+    /// Panics if `fps` is 0.
+    pub fn new(fps: u32) -> FrameLimiter {
+        assert!(fps > 0, "frame rate must be above 0");
+        FrameLimiter::with_interval(Duration::from_secs(1) / fps)
+    }
+
+    /// A limiter allowing at most one frame every `interval`.
+    pub fn with_interval(interval: Duration) -> FrameLimiter {
+        FrameLimiter { interval, next: Instant::now() }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Whether it is time for the next frame.
+    pub fn due(&self) -> bool {
+        Instant::now() >= self.next
+    }
+
+    /// How long until the next frame is due, which is how long to wait for input. Zero if it
+    /// is due already.
+    pub fn timeout(&self) -> Duration {
+        self.next.saturating_duration_since(Instant::now())
+    }
+
+    /// Note that a frame has just been presented, so the next one isn't due for another
+    /// interval.
+    ///
+    /// Frames are scheduled at fixed intervals, so an occasional slow frame doesn't lower the
+    /// rate. If the loop has fallen more than a frame behind, the schedule starts again from
+    /// now rather than rushing out frames to catch up.
+    pub fn presented(&mut self) {
+        let now = Instant::now();
+        self.next += self.interval;
+        if self.next < now {
+            self.next = now + self.interval;
+        }
+    }
+
+    /// Sleep until the next frame is due, for loops that don't wait for input.
+    pub fn wait(&self) {
+        let timeout = self.timeout();
+        if timeout > Duration::from_secs(0) {
+            thread::sleep(timeout);
+        }
+    }
+}
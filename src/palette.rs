@@ -0,0 +1,63 @@
+// The standard xterm 256 color palette, for when we need to know what a color number looks like.
+
+const ANSI: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00), (0xcd, 0x00, 0x00), (0x00, 0xcd, 0x00), (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee), (0xcd, 0x00, 0xcd), (0x00, 0xcd, 0xcd), (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f), (0xff, 0x00, 0x00), (0x00, 0xff, 0x00), (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff), (0xff, 0x00, 0xff), (0x00, 0xff, 0xff), (0xff, 0xff, 0xff),
+];
+
+// The channel values used by the 6x6x6 color cube (colors 16-231).
+pub const CUBE_LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+pub fn rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            (CUBE_LEVELS[(i / 36) as usize], CUBE_LEVELS[(i / 6 % 6) as usize], CUBE_LEVELS[(i % 6) as usize])
+        },
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        },
+    }
+}
+
+// The color in `candidates` closest to `(r, g, b)`.
+pub fn nearest<I: Iterator<Item = u8>>(r: u8, g: u8, b: u8, candidates: I) -> u8 {
+    candidates.min_by_key(|&index| distance((r, g, b), rgb(index))).unwrap_or(0)
+}
+
+// The levels used by the 4x4x4 color cube (colors 16-79) of the 88 color palette, and its
+// grays (80-87).
+const CUBE_LEVELS_88: [u8; 4] = [0x00, 0x8b, 0xcd, 0xff];
+const GRAYS_88: [u8; 8] = [0x2e, 0x5c, 0x73, 0x8b, 0xa2, 0xb9, 0xd0, 0xe7];
+
+// What `index` looks like in xterm's 88 color palette.
+pub fn rgb88(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI[index as usize],
+        16..=79 => {
+            let i = index - 16;
+            (CUBE_LEVELS_88[(i / 16) as usize], CUBE_LEVELS_88[(i / 4 % 4) as usize], CUBE_LEVELS_88[(i % 4) as usize])
+        },
+        _ => {
+            let level = GRAYS_88[(index.min(87) - 80) as usize];
+            (level, level, level)
+        },
+    }
+}
+
+// The color in the 88 color palette's cube and grays closest to `(r, g, b)`.
+pub fn nearest88(r: u8, g: u8, b: u8) -> u8 {
+    (16..=87).min_by_key(|&index| distance((r, g, b), rgb88(index))).unwrap_or(0)
+}
+
+// How different two colors look, going by the "redmean" approximation, which weights the
+// channels by how sensitive the eye is to them, and red and blue by how red the colors are.
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> i32 {
+    let mean = (r1 as i32 + r2 as i32) / 2;
+    let (dr, dg, db) = (r1 as i32 - r2 as i32, g1 as i32 - g2 as i32, b1 as i32 - b2 as i32);
+    (((512 + mean) * dr * dr) >> 8) + 4 * dg * dg + (((767 - mean) * db * db) >> 8)
+}
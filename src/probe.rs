@@ -0,0 +1,157 @@
+// Asking the terminal what it is and what it supports, for `RustBox::terminal_info`, and
+// keeping queries of all kinds from holding things up where nothing answers them.
+//
+// All of the queries go out together, followed by a primary device attributes query, which
+// every terminal answers, so that its reply shows that no more replies are coming. Terminals
+// ignore the queries they don't understand.
+
+use std::env;
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use image;
+
+// How long queries may go unanswered in all before the terminal isn't asked anything more, so
+// that one that never answers, or an expect script, holds the application up only this long.
+const UNANSWERED_LIMIT: Duration = Duration::from_millis(500);
+
+// Whether the terminal may still be asked things, for `Tty::query`.
+pub struct Budget {
+    // Not on dumb terminals, which answer nothing.
+    enabled: bool,
+    // How much longer queries may go unanswered.
+    left: Mutex<Duration>,
+}
+
+impl Budget {
+    pub fn new() -> Budget {
+        Budget {
+            enabled: env::var("TERM").ok().as_deref() != Some("dumb"),
+            left: Mutex::new(UNANSWERED_LIMIT),
+        }
+    }
+
+    // How long a query that would wait up to `timeout` for its reply may wait, or an error if
+    // it shouldn't be sent at all.
+    pub fn allow(&self, timeout: Duration) -> io::Result<Duration> {
+        if !self.enabled {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "TERM=dumb, so the terminal isn't asked"));
+        }
+        match *self.left.lock().unwrap() {
+            left if left == Duration::from_secs(0) =>
+                Err(io::Error::new(io::ErrorKind::TimedOut, "the terminal has stopped answering")),
+            left => Ok(timeout.min(left)),
+        }
+    }
+
+    // Note that a query went unanswered after waiting for `waited`.
+    pub fn unanswered(&self, waited: Duration) {
+        let mut left = self.left.lock().unwrap();
+        *left = left.checked_sub(waited).unwrap_or_default();
+    }
+}
+
+/// What the terminal said about itself when asked, as returned by `RustBox::terminal_info`.
+/// Anything the terminal didn't answer is left as `None`, empty or false.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct TerminalInfo {
+    /// The name from the terminal's XTVERSION reply, such as `kitty`, `WezTerm` or `XTerm`.
+    pub name: Option<String>,
+    /// The version that came with the name.
+    pub version: Option<String>,
+    /// The parameters of the primary device attributes (DA1) reply: the terminal's
+    /// conformance level, then the extensions it claims, such as 4 for sixel graphics.
+    pub attributes: Vec<u16>,
+    /// Whether the terminal answered the kitty graphics protocol's query.
+    pub kitty_graphics: bool,
+    /// Whether the terminal answered the kitty keyboard protocol's query. RustBox doesn't turn
+    /// the protocol on, since termbox can't decode its key reports.
+    pub kitty_keyboard: bool,
+    /// Whether the terminal supports synchronized output (mode 2026), which `present` then
+    /// uses so that frames are never shown half drawn.
+    pub synchronized_output: bool,
+}
+
+impl TerminalInfo {
+    /// Whether the terminal claims to support sixel graphics.
+    pub fn sixel(&self) -> bool {
+        self.attributes.iter().skip(1).any(|&a| a == 4)
+    }
+}
+
+// XTVERSION, the kitty keyboard protocol's query and DECRQM for mode 2026. The kitty graphics
+// query and DA1 come from elsewhere.
+const XTVERSION: &[u8] = b"\x1b[>0q";
+const KITTY_KEYBOARD: &[u8] = b"\x1b[?u";
+const SYNCHRONIZED_OUTPUT: &[u8] = b"\x1b[?2026$p";
+const DEVICE_ATTRIBUTES: &[u8] = b"\x1b[c";
+
+pub fn request() -> Vec<u8> {
+    [XTVERSION, image::KITTY_QUERY, KITTY_KEYBOARD, SYNCHRONIZED_OUTPUT, DEVICE_ATTRIBUTES].concat()
+}
+
+// Whether `reply` is complete, which it is once the device attributes have arrived.
+pub fn done(reply: &[u8]) -> bool {
+    parse_device_attributes(reply).is_some()
+}
+
+pub fn parse(reply: &[u8]) -> TerminalInfo {
+    let (name, version) = match parse_xtversion(reply) {
+        Some((name, version)) => (Some(name), version),
+        None => (None, None),
+    };
+    TerminalInfo {
+        name,
+        version,
+        attributes: parse_device_attributes(reply).unwrap_or_default(),
+        kitty_graphics: contains(reply, image::KITTY_QUERY_OK),
+        kitty_keyboard: private_reports(reply).any(|(_, last)| last == b'u'),
+        // 1 and 2 are set and reset, 3 permanently set; 0 and 4 mean it can't be used.
+        synchronized_output: private_reports(reply)
+            .any(|(body, last)| last == b'y' && [&b"2026;1$"[..], b"2026;2$", b"2026;3$"].contains(&body)),
+    }
+}
+
+// Find a primary device attributes report (`ESC [ ? Ps ; ... c`) in `reply`, and return its
+// parameters.
+pub fn parse_device_attributes(reply: &[u8]) -> Option<Vec<u16>> {
+    private_reports(reply).filter(|&(_, last)| last == b'c').find_map(|(body, _)| {
+        let body = ::std::str::from_utf8(body).ok()?;
+        body.split(';').map(|n| n.parse().ok()).collect()
+    })
+}
+
+// The reports in `reply` that start with `ESC [ ?`, as the bytes up to their final byte, and
+// that final byte.
+fn private_reports(reply: &[u8]) -> impl Iterator<Item = (&[u8], u8)> {
+    reply.windows(3).enumerate().filter(|&(_, w)| w == b"\x1b[?").filter_map(move |(i, _)| {
+        let body = &reply[i + 3..];
+        // Parameters and intermediate bytes, up to the final byte.
+        let len = body.iter().position(|&b| !(0x20..0x40).contains(&b))?;
+        Some((&body[..len], body[len]))
+    })
+}
+
+// The name and version from an XTVERSION reply, `DCS > | text ST`. Terminals give the text as
+// `name(version)` or `name version`.
+fn parse_xtversion(reply: &[u8]) -> Option<(String, Option<String>)> {
+    let start = reply.windows(4).position(|w| w == b"\x1bP>|")? + 4;
+    let len = reply[start..].iter().position(|&b| b == 0x1b || b == 0x07)?;
+    let text = String::from_utf8_lossy(&reply[start..start + len]).trim().to_string();
+    if text.is_empty() {
+        return None;
+    }
+    if let (Some(open), true) = (text.find('('), text.ends_with(')')) {
+        let version = text[open + 1..text.len() - 1].trim().to_string();
+        return Some((text[..open].trim().to_string(), Some(version).filter(|v| !v.is_empty())));
+    }
+    match text.find(' ') {
+        Some(space) => Some((text[..space].to_string(), Some(text[space + 1..].trim().to_string()))),
+        None => Some((text, None)),
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
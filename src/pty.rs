@@ -0,0 +1,691 @@
+//! Running a program in a pseudo terminal and showing its output inside a RustBox.
+//!
+//! `PtyView` starts a child process on a new pty and reads everything it writes on a background
+//! thread, interpreting the common VT100/xterm escape sequences into a `Buffer`. Drawing the
+//! view is a matter of blitting that buffer wherever it should appear, and keys are forwarded
+//! with `send_key`.
+//!
+//! ```no_run
+//! use std::process::Command;
+//! use rustbox::{Event, RustBox};
+//! use rustbox::pty::PtyView;
+//!
+//! let rb = RustBox::init(Default::default()).unwrap();
+//! let mut shell = PtyView::spawn(&mut Command::new("sh"), 80, 20).unwrap();
+//! while shell.try_wait().unwrap().is_none() {
+//!     rb.blit(0, 0, &shell.buffer());
+//!     rb.present().unwrap();
+//!     if let Ok(Event::KeyEvent(key)) = rb.peek_event(std::time::Duration::from_millis(20), false) {
+//!         shell.send_key(key).unwrap();
+//!     }
+//! }
+//! ```
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::ptr;
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use libc;
+
+use super::{Buffer, Cell, Color, Key, Style, RB_BOLD, RB_NORMAL, RB_REVERSE, RB_UNDERLINE};
+use buffer::BLANK;
+use palette;
+
+/// A child process running in a pseudo terminal, along with the screen it has drawn.
+pub struct PtyView {
+    master: File,
+    child: Child,
+    screen: Arc<Mutex<Screen>>,
+}
+
+impl PtyView {
+    /// Start `command` on a new `width` by `height` pty. Its standard input, output and error
+    /// are all connected to the pty, which also becomes its controlling terminal.
+    pub fn spawn(command: &mut Command, width: usize, height: usize) -> io::Result<PtyView> {
+        let (master, slave) = open_pty(width, height)?;
+        let stdio = || -> io::Result<Stdio> {
+            match unsafe { libc::dup(slave) } {
+                -1 => Err(io::Error::last_os_error()),
+                fd => Ok(unsafe { Stdio::from(File::from_raw_fd(fd)) }),
+            }
+        };
+        command.stdin(stdio()?).stdout(stdio()?).stderr(stdio()?);
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn();
+        // The child has its own copies now. Ours has to go, or reads from the master would never
+        // see the end of the output.
+        unsafe { libc::close(slave) };
+        let child = child?;
+
+        let screen = Arc::new(Mutex::new(Screen::new(width, height)));
+        let mut reader = master.try_clone()?;
+        let thread_screen = screen.clone();
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            // Linux reports EIO rather than end of file once the child has closed the pty.
+            while let Ok(n) = reader.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                thread_screen.lock().unwrap().feed(&buf[..n]);
+            }
+        });
+
+        Ok(PtyView { master, child, screen })
+    }
+
+    pub fn width(&self) -> usize {
+        self.screen.lock().unwrap().buffer.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.screen.lock().unwrap().buffer.height()
+    }
+
+    /// A copy of the screen as the child has drawn it so far.
+    pub fn buffer(&self) -> Buffer {
+        self.screen.lock().unwrap().buffer.clone()
+    }
+
+    /// Where the child's cursor is, or `None` if it has hidden it.
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        let screen = self.screen.lock().unwrap();
+        if screen.cursor_visible {
+            Some((screen.x, screen.y))
+        } else {
+            None
+        }
+    }
+
+    /// Whether any output has arrived since the last call, i.e. whether the view needs to be
+    /// drawn again.
+    pub fn take_changed(&self) -> bool {
+        let mut screen = self.screen.lock().unwrap();
+        let changed = screen.changed;
+        screen.changed = false;
+        changed
+    }
+
+    /// Without 256 color support, which is the default, the child's bright, 256 and true colors
+    /// are reduced to the eight basic colors, so that the buffer can be drawn in
+    /// `OutputMode::Normal`. Turn this on when using `OutputMode::EightBit`, which shows the
+    /// child's default colors as white on black, having none of its own.
+    pub fn set_256_colors(&self, enabled: bool) {
+        self.screen.lock().unwrap().eight_bit = enabled;
+    }
+
+    /// Send raw bytes to the child, as if they had been typed.
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.master.write_all(bytes)
+    }
+
+    /// Send the escape sequence a terminal would for `key`. Unknown keys are ignored.
+    pub fn send_key(&mut self, key: Key) -> io::Result<()> {
+        let mut utf8 = [0; 4];
+        let bytes: &[u8] = match key {
+            Key::Tab => b"\t",
+            Key::Enter => b"\r",
+            Key::Esc => b"\x1b",
+            Key::Backspace => b"\x7f",
+            Key::Right => b"\x1b[C",
+            Key::Left => b"\x1b[D",
+            Key::Up => b"\x1b[A",
+            Key::Down => b"\x1b[B",
+            Key::Delete => b"\x1b[3~",
+            Key::Insert => b"\x1b[2~",
+            Key::Home => b"\x1b[H",
+            Key::End => b"\x1b[F",
+            Key::PageUp => b"\x1b[5~",
+            Key::PageDown => b"\x1b[6~",
+            Key::Char(c) => c.encode_utf8(&mut utf8).as_bytes(),
+            Key::Ctrl('6') => b"\x1e",
+            Key::Ctrl('/') => b"\x1f",
+            Key::Ctrl(c) if c.is_ascii() => {
+                utf8[0] = c as u8 & 0x1f;
+                &utf8[..1]
+            },
+            Key::F(n) => match n {
+                1 => b"\x1bOP",
+                2 => b"\x1bOQ",
+                3 => b"\x1bOR",
+                4 => b"\x1bOS",
+                5 => b"\x1b[15~",
+                6 => b"\x1b[17~",
+                7 => b"\x1b[18~",
+                8 => b"\x1b[19~",
+                9 => b"\x1b[20~",
+                10 => b"\x1b[21~",
+                11 => b"\x1b[23~",
+                12 => b"\x1b[24~",
+                _ => return Ok(()),
+            },
+            Key::Ctrl(_) | Key::Unknown(_) => return Ok(()),
+        };
+        self.write(bytes)
+    }
+
+    /// Change the size of the pty, which sends the child SIGWINCH. Whatever fits of the
+    /// current screen is kept.
+    pub fn resize(&mut self, width: usize, height: usize) -> io::Result<()> {
+        set_size(&self.master, width, height)?;
+        self.screen.lock().unwrap().resize(width, height);
+        Ok(())
+    }
+
+    /// The child's exit status if it has exited, without waiting for it.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.child.try_wait()
+    }
+
+    /// Wait for the child to exit.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.child.wait()
+    }
+
+    /// Kill the child.
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.child.kill()
+    }
+}
+
+impl Drop for PtyView {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+fn winsize(width: usize, height: usize) -> libc::winsize {
+    libc::winsize { ws_row: height as u16, ws_col: width as u16, ws_xpixel: 0, ws_ypixel: 0 }
+}
+
+fn open_pty(width: usize, height: usize) -> io::Result<(File, RawFd)> {
+    let (mut master, mut slave) = (0, 0);
+    let ws = winsize(width, height);
+    if unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), &ws) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // Keep the master out of the child, so it sees the pty close when we drop it.
+    unsafe { libc::fcntl(master, libc::F_SETFD, libc::FD_CLOEXEC) };
+    Ok((unsafe { File::from_raw_fd(master) }, slave))
+}
+
+fn set_size(master: &File, width: usize, height: usize) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let ws = winsize(width, height);
+    if unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &ws) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Ground,
+    Escape,
+    // Designating a character set, whose final byte is ignored.
+    Charset,
+    Csi,
+    Osc,
+    OscEscape,
+}
+
+// The child's screen, and the state of the escape sequence parser that draws on it. This covers
+// what shells, editors and build tools commonly use, not the whole of what xterm understands.
+struct Screen {
+    buffer: Buffer,
+    x: usize,
+    y: usize,
+    // Set after writing to the last column, so that the next character wraps.
+    wrap_pending: bool,
+    saved: (usize, usize),
+    // The scrolling region, inclusive.
+    top: usize,
+    bottom: usize,
+    fg: Color,
+    bg: Color,
+    style: Style,
+    cursor_visible: bool,
+    eight_bit: bool,
+    changed: bool,
+
+    state: State,
+    params: String,
+    utf8: Vec<u8>,
+}
+
+impl Screen {
+    fn new(width: usize, height: usize) -> Screen {
+        Screen {
+            buffer: Buffer::new(width, height),
+            x: 0,
+            y: 0,
+            wrap_pending: false,
+            saved: (0, 0),
+            top: 0,
+            bottom: height.saturating_sub(1),
+            fg: Color::Default,
+            bg: Color::Default,
+            style: RB_NORMAL,
+            cursor_visible: true,
+            eight_bit: false,
+            changed: true,
+            state: State::Ground,
+            params: String::new(),
+            utf8: Vec::new(),
+        }
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        let mut buffer = Buffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                if let Some(cell) = self.buffer.get(x, y) {
+                    buffer.set(x, y, *cell);
+                }
+            }
+        }
+        self.buffer = buffer;
+        self.x = self.x.min(width.saturating_sub(1));
+        self.y = self.y.min(height.saturating_sub(1));
+        self.wrap_pending = false;
+        self.top = 0;
+        self.bottom = height.saturating_sub(1);
+        self.changed = true;
+    }
+
+    fn feed(&mut self, bytes: &[u8]) {
+        self.changed = true;
+        for &byte in bytes {
+            self.byte(byte);
+        }
+    }
+
+    fn byte(&mut self, byte: u8) {
+        match self.state {
+            State::Ground => self.ground(byte),
+            State::Escape => self.escape(byte),
+            State::Charset => self.state = State::Ground,
+            State::Csi => match byte {
+                0x20..=0x3f => self.params.push(byte as char),
+                0x40..=0x7e => {
+                    self.csi(byte);
+                    self.state = State::Ground;
+                },
+                0x1b => self.state = State::Escape,
+                _ => (),
+            },
+            State::Osc => match byte {
+                0x07 => self.state = State::Ground,
+                0x1b => self.state = State::OscEscape,
+                _ => (),
+            },
+            State::OscEscape => self.state = if byte == b'\\' { State::Ground } else { State::Osc },
+        }
+    }
+
+    fn ground(&mut self, byte: u8) {
+        if !self.utf8.is_empty() || byte >= 0x80 {
+            self.utf8.push(byte);
+            match str::from_utf8(&self.utf8) {
+                Ok(s) => {
+                    let ch = s.chars().next().unwrap();
+                    self.utf8.clear();
+                    self.put(ch);
+                },
+                // An incomplete sequence; wait for the rest.
+                Err(ref e) if e.error_len().is_none() => (),
+                Err(_) => {
+                    self.utf8.clear();
+                    self.put('\u{fffd}');
+                },
+            }
+            return;
+        }
+        match byte {
+            b'\r' => self.carriage_return(),
+            b'\n' | 0x0b | 0x0c => self.line_feed(),
+            0x08 => {
+                self.x = self.x.saturating_sub(1);
+                self.wrap_pending = false;
+            },
+            b'\t' => {
+                let last = self.buffer.width().saturating_sub(1);
+                self.x = ((self.x / 8 + 1) * 8).min(last);
+            },
+            0x1b => self.state = State::Escape,
+            0x20..=0x7e => self.put(byte as char),
+            _ => (),
+        }
+    }
+
+    fn escape(&mut self, byte: u8) {
+        self.state = State::Ground;
+        match byte {
+            b'[' => {
+                self.params.clear();
+                self.state = State::Csi;
+            },
+            b']' => self.state = State::Osc,
+            b'(' | b')' | b'*' | b'+' => self.state = State::Charset,
+            b'7' => self.saved = (self.x, self.y),
+            b'8' => self.restore_cursor(),
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.carriage_return();
+                self.line_feed();
+            },
+            b'M' => self.reverse_index(),
+            b'c' => {
+                let (width, height) = (self.buffer.width(), self.buffer.height());
+                let eight_bit = self.eight_bit;
+                *self = Screen::new(width, height);
+                self.eight_bit = eight_bit;
+            },
+            _ => (),
+        }
+    }
+
+    fn csi(&mut self, final_byte: u8) {
+        let private = self.params.starts_with('?');
+        // The child can send any number, so keep them small enough to do sums with.
+        let params: Vec<usize> = self.params.trim_start_matches('?').split(';')
+            .map(|p| match p.parse::<usize>() {
+                Ok(n) => n.min(u16::MAX as usize),
+                Err(_) if !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()) => u16::MAX as usize,
+                Err(_) => 0,
+            })
+            .collect();
+        let arg = |i: usize, default: usize| match params.get(i) {
+            Some(&0) | None => default,
+            Some(&n) => n,
+        };
+        let (width, height) = (self.buffer.width(), self.buffer.height());
+        self.wrap_pending = false;
+        match final_byte {
+            b'A' => self.y = self.y.saturating_sub(arg(0, 1)),
+            b'B' => self.y = self.y.saturating_add(arg(0, 1)).min(height.saturating_sub(1)),
+            b'C' => self.x = self.x.saturating_add(arg(0, 1)).min(width.saturating_sub(1)),
+            b'D' => self.x = self.x.saturating_sub(arg(0, 1)),
+            b'E' => {
+                self.y = self.y.saturating_add(arg(0, 1)).min(height.saturating_sub(1));
+                self.x = 0;
+            },
+            b'F' => {
+                self.y = self.y.saturating_sub(arg(0, 1));
+                self.x = 0;
+            },
+            b'G' | b'`' => self.x = (arg(0, 1) - 1).min(width.saturating_sub(1)),
+            b'd' => self.y = (arg(0, 1) - 1).min(height.saturating_sub(1)),
+            b'H' | b'f' => {
+                self.y = (arg(0, 1) - 1).min(height.saturating_sub(1));
+                self.x = (arg(1, 1) - 1).min(width.saturating_sub(1));
+            },
+            b'J' => match arg(0, 0) {
+                0 => {
+                    self.erase(self.x, self.y, width, self.y + 1);
+                    self.erase(0, self.y + 1, width, height);
+                },
+                1 => {
+                    self.erase(0, 0, width, self.y);
+                    self.erase(0, self.y, self.x + 1, self.y + 1);
+                },
+                _ => self.erase(0, 0, width, height),
+            },
+            b'K' => match arg(0, 0) {
+                0 => self.erase(self.x, self.y, width, self.y + 1),
+                1 => self.erase(0, self.y, self.x + 1, self.y + 1),
+                _ => self.erase(0, self.y, width, self.y + 1),
+            },
+            b'X' => self.erase(self.x, self.y, self.x.saturating_add(arg(0, 1)), self.y + 1),
+            b'@' => self.shift_row(arg(0, 1) as isize),
+            b'P' => self.shift_row(-(arg(0, 1) as isize)),
+            b'L' if self.y >= self.top && self.y <= self.bottom => self.scroll_down(self.y, arg(0, 1)),
+            b'M' if self.y >= self.top && self.y <= self.bottom => self.scroll_up(self.y, arg(0, 1)),
+            b'S' => self.scroll_up(self.top, arg(0, 1)),
+            b'T' => self.scroll_down(self.top, arg(0, 1)),
+            b'm' => self.sgr(&params),
+            b'r' => {
+                let top = arg(0, 1) - 1;
+                let bottom = arg(1, height) - 1;
+                if top < bottom && bottom < height {
+                    self.top = top;
+                    self.bottom = bottom;
+                    self.x = 0;
+                    self.y = 0;
+                }
+            },
+            b's' => self.saved = (self.x, self.y),
+            b'u' => self.restore_cursor(),
+            b'h' | b'l' if private => {
+                let set = final_byte == b'h';
+                for &mode in &params {
+                    match mode {
+                        25 => self.cursor_visible = set,
+                        // There is only the one screen, so the alternate screen is emulated by
+                        // clearing it on the way in and out.
+                        47 | 1047 | 1049 => {
+                            if set && mode == 1049 {
+                                self.saved = (self.x, self.y);
+                            }
+                            self.erase(0, 0, width, height);
+                            if !set && mode == 1049 {
+                                self.restore_cursor();
+                            }
+                        },
+                        _ => (),
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn sgr(&mut self, params: &[usize]) {
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => {
+                    self.fg = Color::Default;
+                    self.bg = Color::Default;
+                    self.style = RB_NORMAL;
+                },
+                1 => self.style.insert(RB_BOLD),
+                4 => self.style.insert(RB_UNDERLINE),
+                7 => self.style.insert(RB_REVERSE),
+                22 => self.style.remove(RB_BOLD),
+                24 => self.style.remove(RB_UNDERLINE),
+                27 => self.style.remove(RB_REVERSE),
+                n @ 30..=37 => self.fg = self.color((n - 30) as u8),
+                n @ 40..=47 => self.bg = self.color((n - 40) as u8),
+                n @ 90..=97 => self.fg = self.color((n - 90 + 8) as u8),
+                n @ 100..=107 => self.bg = self.color((n - 100 + 8) as u8),
+                39 => self.fg = Color::Default,
+                49 => self.bg = Color::Default,
+                n @ 38 | n @ 48 => {
+                    let index = match params.get(i + 1) {
+                        Some(&5) => {
+                            i += 2;
+                            params.get(i).map(|&c| c as u8)
+                        },
+                        Some(&2) => {
+                            i += 4;
+                            match (params.get(i - 2), params.get(i - 1), params.get(i)) {
+                                (Some(&r), Some(&g), Some(&b)) =>
+                                    Some(palette::nearest(r as u8, g as u8, b as u8, 16..=255)),
+                                _ => None,
+                            }
+                        },
+                        _ => None,
+                    };
+                    if let Some(index) = index {
+                        let color = self.color(index);
+                        if n == 38 { self.fg = color } else { self.bg = color }
+                    }
+                },
+                _ => (),
+            }
+            i += 1;
+        }
+    }
+
+    // The color to use for palette entry `index`, reduced to the basic eight if need be.
+    fn color(&self, index: u8) -> Color {
+        let index = if self.eight_bit || index < 8 {
+            index
+        } else if index < 16 {
+            index - 8
+        } else {
+            let (r, g, b) = palette::rgb(index);
+            palette::nearest(r, g, b, 0..=7)
+        };
+        Color::from_256color(index as u16)
+    }
+
+    fn put(&mut self, ch: char) {
+        let (width, height) = (self.buffer.width(), self.buffer.height());
+        if width == 0 || height == 0 {
+            return;
+        }
+        if self.wrap_pending {
+            self.carriage_return();
+            self.line_feed();
+        }
+        let cell = Cell { ch, fg: self.fg, bg: self.bg, style: self.style };
+        self.buffer.set(self.x, self.y, cell);
+        if self.x + 1 < width {
+            self.x += 1;
+        } else {
+            self.wrap_pending = true;
+        }
+    }
+
+    fn carriage_return(&mut self) {
+        self.x = 0;
+        self.wrap_pending = false;
+    }
+
+    fn line_feed(&mut self) {
+        self.wrap_pending = false;
+        if self.y == self.bottom {
+            self.scroll_up(self.top, 1);
+        } else if self.y + 1 < self.buffer.height() {
+            self.y += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        if self.y == self.top {
+            self.scroll_down(self.top, 1);
+        } else {
+            self.y = self.y.saturating_sub(1);
+        }
+    }
+
+    fn restore_cursor(&mut self) {
+        let (x, y) = self.saved;
+        self.x = x.min(self.buffer.width().saturating_sub(1));
+        self.y = y.min(self.buffer.height().saturating_sub(1));
+        self.wrap_pending = false;
+    }
+
+    // What erased cells are filled with: blank, but keeping the current background.
+    fn blank(&self) -> Cell {
+        Cell { bg: self.bg, ..BLANK }
+    }
+
+    // Erase the cells from column `x1` to `x2` and row `y1` to `y2`, exclusive.
+    fn erase(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
+        let blank = self.blank();
+        for y in y1..y2.min(self.buffer.height()) {
+            for x in x1..x2.min(self.buffer.width()) {
+                self.buffer.set(x, y, blank);
+            }
+        }
+    }
+
+    // Move the rows from `from` to the bottom of the scrolling region up by `n`.
+    fn scroll_up(&mut self, from: usize, n: usize) {
+        if self.buffer.height() == 0 {
+            return;
+        }
+        let n = n.min(self.bottom + 1 - from);
+        for y in from..self.bottom + 1 - n {
+            let row = self.buffer.row(y + n).unwrap().to_vec();
+            self.buffer.row_mut(y).unwrap().copy_from_slice(&row);
+        }
+        let width = self.buffer.width();
+        self.erase(0, self.bottom + 1 - n, width, self.bottom + 1);
+    }
+
+    // Move the rows from `from` to the bottom of the scrolling region down by `n`.
+    fn scroll_down(&mut self, from: usize, n: usize) {
+        if self.buffer.height() == 0 {
+            return;
+        }
+        let n = n.min(self.bottom + 1 - from);
+        for y in (from + n..=self.bottom).rev() {
+            let row = self.buffer.row(y - n).unwrap().to_vec();
+            self.buffer.row_mut(y).unwrap().copy_from_slice(&row);
+        }
+        let width = self.buffer.width();
+        self.erase(0, from, width, from + n);
+    }
+
+    // Insert (positive `n`) or delete (negative `n`) cells at the cursor, shifting the rest of
+    // the row.
+    fn shift_row(&mut self, n: isize) {
+        let blank = self.blank();
+        let x = self.x;
+        let row = match self.buffer.row_mut(self.y) {
+            Some(row) => row,
+            None => return,
+        };
+        let width = row.len();
+        let count = (n.unsigned_abs()).min(width.saturating_sub(x));
+        if n > 0 {
+            row[x..].rotate_right(count);
+            for cell in &mut row[x..x + count] {
+                *cell = blank;
+            }
+        } else {
+            row[x..].rotate_left(count);
+            for cell in &mut row[width - count..] {
+                *cell = blank;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Screen;
+
+    #[test]
+    fn huge_cursor_movements() {
+        let mut screen = Screen::new(10, 3);
+        screen.feed(b"a\x1b[18446744073709551615C");
+        assert_eq!((screen.x, screen.y), (9, 0));
+        screen.feed(b"\x1b[99999999999999999999999B");
+        assert_eq!((screen.x, screen.y), (9, 2));
+        screen.feed(b"\x1b[1;1H\x1b[18446744073709551615X\x1b[18446744073709551615E");
+        assert_eq!((screen.x, screen.y), (0, 2));
+        assert_eq!(screen.buffer.get(0, 0).unwrap().ch, ' ');
+    }
+}
@@ -0,0 +1,99 @@
+// Putting a terminal into raw mode and back, for code that reads the keyboard without RustBox.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use libc;
+
+/// Keeps a terminal in raw mode, where input arrives a byte at a time without being echoed,
+/// until it is dropped, when the terminal's settings are put back as they were.
+///
+/// RustBox puts the terminal into raw mode itself; this is for reading input some other way,
+/// such as a password prompt before RustBox starts, perhaps passing the bytes to an
+/// `input::Parser`.
+///
+/// ```no_run
+/// use std::io::Read;
+/// use rustbox::RawModeGuard;
+///
+/// let guard = RawModeGuard::new().unwrap();
+/// let mut key = [0];
+/// std::io::stdin().read_exact(&mut key).unwrap();
+/// drop(guard);
+/// println!("pressed {:?}", key[0] as char);
+/// ```
+pub struct RawModeGuard {
+    fd: RawFd,
+    original: libc::termios,
+    // The terminal opened by `new`, kept open for `fd` to stay valid.
+    _file: Option<File>,
+}
+
+impl RawModeGuard {
+    /// Put the controlling terminal into raw mode, whatever stdin and stdout are.
+    pub fn new() -> io::Result<RawModeGuard> {
+        let file = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let mut guard = RawModeGuard::for_fd(file.as_raw_fd())?;
+        guard._file = Some(file);
+        Ok(guard)
+    }
+
+    /// Put the terminal open as `fd` into raw mode. `fd` must stay open for as long as the
+    /// guard is around.
+    pub fn for_fd(fd: RawFd) -> io::Result<RawModeGuard> {
+        let original = get_attr(fd)?;
+        let mut raw = original;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        set_attr(fd, &raw)?;
+        Ok(RawModeGuard { fd, original, _file: None })
+    }
+
+    /// Put the terminal's settings back now, reporting whether that worked, which dropping the
+    /// guard can't.
+    pub fn restore(mut self) -> io::Result<()> {
+        let result = set_attr(self.fd, &self.original);
+        let file = self._file.take();
+        mem::forget(self);
+        drop(file);
+        result
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        // There's nowhere to report a failure to.
+        let _ = set_attr(self.fd, &self.original);
+    }
+}
+
+// Turn the terminal's signal keys and output processing back on, as asked.
+pub(crate) fn keep(fd: RawFd, signals: bool, output_processing: bool) -> io::Result<()> {
+    let mut termios = get_attr(fd)?;
+    if signals {
+        termios.c_lflag |= libc::ISIG;
+    }
+    if output_processing {
+        termios.c_oflag |= libc::OPOST;
+    }
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn get_attr(fd: RawFd) -> io::Result<libc::termios> {
+    let mut termios: libc::termios = unsafe { mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut termios) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(termios)
+}
+
+fn set_attr(fd: RawFd, termios: &libc::termios) -> io::Result<()> {
+    if unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, termios) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+// Recording output and input, for demos and for reproducing bugs.
+//
+// Presented frames are recorded as an asciicast v2 file, for replay with asciinema. termbox
+// doesn't let us see the bytes it writes, so rather than the actual output stream each frame is
+// recorded as a complete repaint of the back buffer. Played back, this looks the same.
+//
+// Input is recorded as the raw termbox events, one per line along with the time it arrived, so
+// that playing it back goes through exactly the same translation as live input.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::os::raw::c_int;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use termbox::RawEvent;
+
+use super::{Buffer, NIL_RAW_EVENT};
+use export;
+
+pub struct Recorder {
+    out: BufWriter<File>,
+    start: Instant,
+    size: (usize, usize),
+    // The first write error, reported when recording stops. Writing stops after an error.
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, width: usize, height: usize) -> io::Result<Recorder> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        writeln!(out, "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}}}",
+                 width, height, timestamp)?;
+        Ok(Recorder { out, start: Instant::now(), size: (width, height), error: None })
+    }
+
+    pub fn frame(&mut self, buffer: &Buffer) {
+        if self.error.is_some() {
+            return;
+        }
+        let mut data = String::new();
+        let size = (buffer.width(), buffer.height());
+        if size != self.size {
+            self.size = size;
+            if let Err(e) = self.event("r", &format!("{}x{}", size.0, size.1)) {
+                self.error = Some(e);
+                return;
+            }
+        }
+        data.push_str("\x1b[?25l");
+        for (y, row) in buffer.iter_rows().enumerate() {
+            data.push_str(&format!("\x1b[{};1H", y + 1));
+            export::push_ansi_row(&mut data, row);
+        }
+        data.push_str("\x1b[0m");
+        if let Err(e) = self.event("o", &data) {
+            self.error = Some(e);
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.out.flush(),
+        }
+    }
+
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        writeln!(self.out, "[{:.6}, \"{}\", {}]", time, code, json_string(data))
+    }
+}
+
+pub struct InputRecorder {
+    out: BufWriter<File>,
+    start: Instant,
+    // As for `Recorder`.
+    error: Option<io::Error>,
+}
+
+impl InputRecorder {
+    pub fn create(path: &Path) -> io::Result<InputRecorder> {
+        let out = BufWriter::new(File::create(path)?);
+        Ok(InputRecorder { out, start: Instant::now(), error: None })
+    }
+
+    // `rc` and `ev` are as returned by tb_peek_event/tb_poll_event. Timeouts and errors aren't
+    // recorded.
+    pub fn event(&mut self, rc: c_int, ev: &RawEvent) {
+        if rc <= 0 || self.error.is_some() {
+            return;
+        }
+        let result = writeln!(self.out, "{:.6} {} {} {} {} {} {} {} {}",
+                              self.start.elapsed().as_secs_f64(), rc, ev.emod, ev.key, ev.ch,
+                              ev.w, ev.h, ev.x, ev.y);
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.out.flush(),
+        }
+    }
+}
+
+pub struct RecordedEvent {
+    pub time: Duration,
+    pub rc: c_int,
+    pub ev: RawEvent,
+}
+
+/// Read back a file written by `InputRecorder`.
+pub fn read_input(path: &Path) -> io::Result<VecDeque<RecordedEvent>> {
+    let mut events = VecDeque::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_input_line(&line) {
+            Some(event) => events.push_back(event),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                              format!("malformed input recording line: {}", line))),
+        }
+    }
+    Ok(events)
+}
+
+fn parse_input_line(line: &str) -> Option<RecordedEvent> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 9 {
+        return None;
+    }
+    let time: f64 = fields[0].parse().ok()?;
+    if !time.is_finite() || time < 0.0 {
+        return None;
+    }
+    Some(RecordedEvent {
+        time: Duration::from_secs_f64(time),
+        rc: fields[1].parse().ok()?,
+        ev: RawEvent {
+            // termbox returns the event type as its result, so it doesn't need a field of its own.
+            etype: fields[1].parse().ok()?,
+            emod: fields[2].parse().ok()?,
+            key: fields[3].parse().ok()?,
+            ch: fields[4].parse().ok()?,
+            w: fields[5].parse().ok()?,
+            h: fields[6].parse().ok()?,
+            x: fields[7].parse().ok()?,
+            y: fields[8].parse().ok()?,
+        },
+    })
+}
+
+/// Hands out recorded input with the same timing it was recorded with, measured from when the
+/// playback was opened.
+pub struct Playback {
+    events: VecDeque<RecordedEvent>,
+    start: Instant,
+}
+
+impl Playback {
+    pub fn open(path: &Path) -> io::Result<Playback> {
+        Ok(Playback { events: read_input(path)?, start: Instant::now() })
+    }
+
+    // Wait for the next event, or until `timeout` runs out, in which case a timeout (0) is
+    // returned just like tb_peek_event would. Returns `None` once the recording is used up.
+    pub fn next(&mut self, timeout: Option<Duration>) -> Option<(c_int, RawEvent)> {
+        let due = match self.events.front() {
+            Some(event) => self.start + event.time,
+            None => return None,
+        };
+        let now = Instant::now();
+        if let Some(timeout) = timeout {
+            if now + timeout < due {
+                thread::sleep(timeout);
+                return Some((0, NIL_RAW_EVENT));
+            }
+        }
+        if due > now {
+            thread::sleep(due - now);
+        }
+        self.events.pop_front().map(|event| (event.rc, event.ev))
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,44 @@
+use std::cmp;
+
+/// A rectangle of cells, given by its top left corner and its size.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, w: usize, h: usize) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    /// The column just past the right edge.
+    pub fn right(&self) -> usize {
+        self.x.saturating_add(self.w)
+    }
+
+    /// The row just past the bottom edge.
+    pub fn bottom(&self) -> usize {
+        self.y.saturating_add(self.h)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.w == 0 || self.h == 0
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// The part of this rectangle that also lies within `other`, which is empty if they don't
+    /// overlap.
+    pub fn intersection(&self, other: Rect) -> Rect {
+        let x = cmp::max(self.x, other.x);
+        let y = cmp::max(self.y, other.y);
+        let right = cmp::min(self.right(), other.right());
+        let bottom = cmp::min(self.bottom(), other.bottom());
+        Rect::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+    }
+}
@@ -1,37 +1,149 @@
 extern crate gag;
+extern crate libc;
 extern crate num_traits;
 extern crate termbox_sys as termbox;
+#[cfg(feature = "bidi")]
+extern crate unicode_bidi;
+extern crate unicode_width;
+#[cfg(feature = "log")]
+extern crate log;
+#[cfg(feature = "mio")]
+extern crate mio;
+#[cfg(feature = "ratatui")]
+extern crate ratatui;
+#[cfg(feature = "serde")]
+extern crate serde;
 #[macro_use] extern crate bitflags;
 
 pub use self::style::{Style, RB_BOLD, RB_UNDERLINE, RB_REVERSE, RB_NORMAL};
 
+use std::env;
 use std::error::Error;
 use std::fmt;
 use std::io;
+use std::mem;
 use std::char;
+use std::cmp;
 use std::default::Default;
 use std::ops::FnOnce;
+use std::path::Path;
+use std::slice;
 use std::sync::Mutex;
+use std::sync::mpsc::Receiver;
+use std::thread;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
 
 use num_traits::FromPrimitive;
-use termbox::RawEvent;
+use termbox::{RawCell, RawEvent};
 use std::os::raw::c_int;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
 use gag::Hold;
-use std::time::Duration;
-
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "ratatui")]
+mod backend;
+mod base64;
+mod border;
+mod debug;
+mod draw;
+pub mod buffer;
+pub mod canvas;
+mod capability;
+pub mod export;
+mod handle;
+mod ime;
+pub mod image;
+pub mod input;
 pub mod keyboard;
+pub mod layer;
+#[cfg(feature = "log")]
+pub mod logger;
 pub mod mouse;
+mod pacing;
+mod palette;
+pub mod pty;
+mod raw_mode;
+mod probe;
+mod rect;
+mod record;
+mod signals;
+#[cfg(feature = "mio")]
+mod source;
+mod stats;
+pub mod testing;
+pub mod text;
+pub mod theme;
+mod tty;
+mod winch;
+pub mod widgets;
+mod window;
 
 pub use self::running::running;
-pub use keyboard::Key;
-pub use mouse::Mouse;
 
-#[derive(Clone, Copy, Debug)]
+/// Put the terminal back to a usable state, for crash handlers and signal handlers that run
+/// when RustBox can't clean up after itself. Only async-signal-safe calls are made.
+///
+/// While a RustBox is running, this restores the terminal's settings from before `init`,
+/// after resetting the colors, showing the cursor, turning off mouse reporting and leaving the
+/// alternate screen. Otherwise it turns line editing, echo, the signal keys and output
+/// processing back on.
+///
+/// ```no_run
+/// use std::{panic, process};
+///
+/// // Exiting from the hook skips dropping the RustBox, whose shutdown would clear the screen,
+/// // and the message with it.
+/// panic::set_hook(Box::new(|info| {
+///     let _ = rustbox::reset_terminal();
+///     eprintln!("{}", info);
+///     process::exit(101);
+/// }));
+/// ```
+pub fn reset_terminal() -> io::Result<()> {
+    signals::reset()
+}
+pub use border::BorderStyle;
+pub use buffer::{Buffer, Cell, CellError};
+pub use capability::ColorSupport;
+pub use draw::{Draw, Surface};
+pub use handle::{InputHandle, RenderHandle};
+pub use image::{ImageError, ImageProtocol, ImageSource};
+pub use keyboard::{Key, Modifiers, MOD_ALT, MOD_CTRL, MOD_META, MOD_SHIFT};
+pub use layer::Layer;
+pub use mouse::{Mouse, MouseKind, MouseTracking};
+pub use pacing::FrameLimiter;
+pub use probe::TerminalInfo;
+pub use raw_mode::RawModeGuard;
+pub use rect::Rect;
+pub use stats::Stats;
+pub use testing::TestBackend;
+pub use text::{Alignment, Text};
+pub use window::Window;
+
+use text::ControlPolicy;
+use theme::{BackgroundKind, Role, Theme};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub enum Event {
     KeyEventRaw(u8, u16, u32),
     KeyEvent(Key),
+    /// A key pressed with modifiers other than those `Key` covers, such as Alt+Enter or
+    /// Ctrl+Left. termbox only reports Alt, in `InputMode::Alt`; `input::Parser` decodes the
+    /// rest from the sequences terminals send.
+    ///
+    /// Keys pressed with Alt in `InputMode::Alt` used to arrive as plain `KeyEvent`s, with Alt
+    /// dropped; they now arrive as `ModifiedKeyEvent(key, MOD_ALT)`, so a `match` that only
+    /// looks at `KeyEvent` no longer sees them.
+    ModifiedKeyEvent(Key, Modifiers),
     ResizeEvent(i32, i32),
     MouseEvent(Mouse, i32, i32),
+    /// A mouse event, with `MouseTracking::Drag` or `MouseTracking::Motion`.
+    Mouse(MouseKind, Mouse, i32, i32),
+    /// Ctrl-C, with `SignalKeys::Events`.
+    Interrupt,
+    /// Ctrl-\\, with `SignalKeys::Events`.
+    Quit,
     NoEvent
 }
 
@@ -43,7 +155,8 @@ pub enum InputMode {
     /// ESC sequence => ESC means TB_KEY_ESC
     Esc     = 0x01,
     /// When ESC sequence is in the buffer and it doesn't match any known
-    /// sequence => ESC enables TB_MOD_ALT modifier for the next keyboard event.
+    /// sequence => ESC enables TB_MOD_ALT modifier for the next keyboard event, which then
+    /// arrives as `Event::ModifiedKeyEvent`.
     Alt     = 0x02,
     /// Same as `Esc` but enables mouse events
     EscMouse = 0x05,
@@ -51,6 +164,70 @@ pub enum InputMode {
     AltMouse = 0x06
 }
 
+/// What Ctrl-C and Ctrl-\\ do, since termbox turns off the terminal's signal keys. See
+/// `RustBox::set_signal_keys`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SignalKeys {
+    /// Arrive as `Key::Ctrl('c')` and `Key::Ctrl('\\')`, like other keys.
+    Keys,
+    /// Arrive as `Event::Interrupt` and `Event::Quit`.
+    Events,
+    /// Raise SIGINT and SIGQUIT, as the terminal would with its signal keys on. Should the
+    /// process carry on, the event is `Event::NoEvent`.
+    Raise,
+}
+
+impl SignalKeys {
+    // What becomes of `event` under this policy.
+    pub(crate) fn apply(self, event: Event) -> Event {
+        let (signal, replacement) = match event {
+            Event::KeyEvent(Key::Ctrl('c')) => (libc::SIGINT, Event::Interrupt),
+            Event::KeyEvent(Key::Ctrl('\\')) => (libc::SIGQUIT, Event::Quit),
+            _ => return event,
+        };
+        match self {
+            SignalKeys::Keys => event,
+            SignalKeys::Events => replacement,
+            SignalKeys::Raise => {
+                unsafe { libc::raise(signal) };
+                Event::NoEvent
+            },
+        }
+    }
+}
+
+/// Where RustBox draws. See `InitOptions::screen`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScreenMode {
+    /// Take over the terminal's alternate screen, which is put away on exit, leaving the
+    /// shell as it was.
+    Alternate,
+    /// Draw in this many lines at the shell prompt, scrolling the terminal up to make room if
+    /// need be, like a picker that appears under the command that ran it. The lines are left
+    /// as they are on exit, with the cursor below them. Only the rows of `RustBox::viewport`
+    /// are drawn; the rest of the screen, which holds whatever the shell had there, is left
+    /// alone. The terminal clears the whole screen when it is resized.
+    Inline(usize),
+    /// Take over the whole of the main screen, scrolling what was on it into the scrollback
+    /// first. What is on screen when RustBox exits is left there, to scroll back to later, with
+    /// the cursor below it.
+    Main,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CursorStyle {
+    Block,
+    Underline,
+    Bar,
+}
+
+/// The terminal's default colors as 8-bit RGB, as reported to `RustBox::query_default_colors`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DefaultColors {
+    pub foreground: (u8, u8, u8),
+    pub background: (u8, u8, u8),
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum OutputMode {
     Current = 0,
@@ -58,10 +235,26 @@ pub enum OutputMode {
     EightBit = 2,  // 256 Colors
     WebSafe = 3,   // 216 Colors
     Grayscale = 4,
+    /// Whichever of `EightBit` and `Normal` suits the terminal, going by
+    /// `ColorSupport::detect`. termbox has no 24-bit mode, so terminals with 24-bit color get
+    /// `EightBit`, which has no default colors, so `Color::Default` is shown as white on black.
+    Auto = 5,
+}
+
+impl OutputMode {
+    // The mode `Auto` stands for on this terminal, or the mode itself.
+    fn resolve(self) -> OutputMode {
+        match self {
+            OutputMode::Auto if ColorSupport::detect() >= ColorSupport::Colors256 => OutputMode::EightBit,
+            OutputMode::Auto => OutputMode::Normal,
+            mode => mode,
+        }
+    }
 }
 
 
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub enum Color {
     Black,
     Red,
@@ -72,9 +265,79 @@ pub enum Color {
     Cyan,
     White,
     Byte(u16),
+    /// A 24-bit color. termbox can't show these directly, so each is shown as the nearest
+    /// color the output mode has, as `downsample` finds it.
+    Rgb(u8, u8, u8),
     Default,
 }
+
+/// The sets of colors that `Color::downsample` can bring a color down to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Palette {
+    /// The eight named colors, which is what `OutputMode::Normal` has.
+    Ansi8,
+    /// The named colors and their bright variants, `Byte(8)` to `Byte(15)`.
+    Ansi16,
+    /// The palette of 88 color terminals such as rxvt-unicode's: the 16 basic colors, a 4x4x4
+    /// color cube and 8 grays.
+    Xterm88,
+    /// The 256 colors of `OutputMode::EightBit`.
+    Xterm256,
+    /// The 6x6x6 color cube, `Byte(16)` to `Byte(231)`, which is what `OutputMode::WebSafe`
+    /// has.
+    WebSafe,
+    /// The 24 grays, `Byte(232)` to `Byte(255)`, which is what `OutputMode::Grayscale` has.
+    Grayscale,
+}
+
 impl Color {
+    /// What the color looks like in the standard xterm palette, or `None` for `Default`,
+    /// which is up to the terminal.
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        match *self {
+            Color::Default => None,
+            Color::Rgb(r, g, b) => Some((r, g, b)),
+            Color::Byte(b) => Some(palette::rgb(b as u8)),
+            named => Some(palette::rgb(named.as_256color() as u8)),
+        }
+    }
+
+    /// The color in `palette` that looks closest to this one, by a perceptual measure.
+    /// Colors already in the palette are left alone, as is `Default`; the bright basic colors
+    /// become their plain counterparts in `Ansi8`.
+    ///
+    /// RustBox does this itself for the output mode in use, so it's only needed to find out
+    /// what a color will look like, or to draw for a terminal some other way.
+    ///
+    /// ```
+    /// use rustbox::{Color, Palette};
+    ///
+    /// assert_eq!(Color::Rgb(250, 10, 10).downsample(Palette::Ansi8), Color::Red);
+    /// assert_eq!(Color::Rgb(95, 135, 175).downsample(Palette::Xterm256), Color::Byte(67));
+    /// assert_eq!(Color::Byte(9).downsample(Palette::Ansi8), Color::Red);
+    /// ```
+    pub fn downsample(self, palette: Palette) -> Color {
+        let (r, g, b) = match self.rgb() {
+            Some(rgb) => rgb,
+            None => return self,
+        };
+        let index = match (palette, self) {
+            (Palette::Ansi8, Color::Byte(b)) if b < 16 => b as u8 % 8,
+            (Palette::Ansi8, Color::Rgb(..)) | (Palette::Ansi8, Color::Byte(_)) => palette::nearest(r, g, b, 0..=7),
+            (Palette::Ansi16, Color::Byte(b)) if b < 16 => return self,
+            (Palette::Ansi16, Color::Rgb(..)) | (Palette::Ansi16, Color::Byte(_)) => palette::nearest(r, g, b, 0..=15),
+            (Palette::Xterm88, Color::Byte(b)) if b < 16 => return self,
+            (Palette::Xterm88, Color::Rgb(..)) | (Palette::Xterm88, Color::Byte(_)) => palette::nearest88(r, g, b),
+            (Palette::Xterm256, Color::Rgb(..)) => palette::nearest(r, g, b, 16..=255),
+            (Palette::WebSafe, Color::Byte(b)) if (16..=231).contains(&b) => return self,
+            (Palette::WebSafe, _) => palette::nearest(r, g, b, 16..=231),
+            (Palette::Grayscale, Color::Byte(b)) if (232..=255).contains(&b) => return self,
+            (Palette::Grayscale, _) => palette::nearest(r, g, b, 232..=255),
+            _ => return self,
+        };
+        Color::from_256color(index as u16)
+    }
+
     pub fn as_256color(&self) -> u16 {
         match *self {
             Color::Black => 0x00,
@@ -86,6 +349,7 @@ impl Color {
             Color::Cyan => 0x06,
             Color::White => 0x07,
             Color::Byte(b) => b,
+            Color::Rgb(..) => self.downsample(Palette::Xterm256).as_256color(),
             Color::Default => panic!("Attempted to cast default color to byte"),
         }
     }
@@ -101,7 +365,37 @@ impl Color {
             Color::Magenta => 0x06,
             Color::Cyan => 0x07,
             Color::White => 0x08,
-            Color::Byte(b) => panic!("Attempted to cast color byte {} to 16 color mode", b),
+            Color::Byte(_) | Color::Rgb(..) => self.downsample(Palette::Ansi8).as_16color(),
+        }
+    }
+
+    // The inverse of `as_256color`. Bytes 0-7 come back as the named colors.
+    fn from_256color(b: u16) -> Color {
+        match b & 0xFF {
+            0x00 => Color::Black,
+            0x01 => Color::Red,
+            0x02 => Color::Green,
+            0x03 => Color::Yellow,
+            0x04 => Color::Blue,
+            0x05 => Color::Magenta,
+            0x06 => Color::Cyan,
+            0x07 => Color::White,
+            b => Color::Byte(b),
+        }
+    }
+
+    // The inverse of `as_16color`.
+    fn from_16color(b: u16) -> Color {
+        match b & style::TB_NORMAL_COLOR.bits() {
+            0x01 => Color::Black,
+            0x02 => Color::Red,
+            0x03 => Color::Green,
+            0x04 => Color::Yellow,
+            0x05 => Color::Blue,
+            0x06 => Color::Magenta,
+            0x07 => Color::Cyan,
+            0x08 => Color::White,
+            _ => Color::Default,
         }
     }
 }
@@ -134,10 +428,30 @@ mod style {
             Style { bits: color.as_256color() }
         }
     }
+
+    // A style is its bits, which can hold a color as well as the flags.
+    #[cfg(feature = "serde")]
+    impl ::serde::Serialize for Style {
+        fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.bits.serialize(serializer)
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> ::serde::Deserialize<'de> for Style {
+        fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Style, D::Error> {
+            u16::deserialize(deserializer).map(|bits| Style { bits })
+        }
+    }
 }
 
 const NIL_RAW_EVENT: RawEvent = RawEvent { etype: 0, emod: 0, key: 0, ch: 0, w: 0, h: 0, x: 0, y: 0 };
 
+// Synchronized output (mode 2026): the terminal holds back what comes between these until it
+// has all arrived, so a frame is never shown half drawn.
+const SYNCHRONIZED_BEGIN: &[u8] = b"\x1b[?2026h";
+const SYNCHRONIZED_END: &[u8] = b"\x1b[?2026l";
+
 #[derive(Debug)]
 pub enum EventError {
    TermboxError,
@@ -195,12 +509,12 @@ fn unpack_event(ev_type: c_int, ev: &RawEvent, raw: bool) -> EventResult {
                     0 => char::from_u32(ev.ch).map(|c| Key::Char(c)),
                     a => Key::from_code(a),
                 };
-                if let Some(key) = k {
+                let key = k.unwrap_or(Key::Unknown(ev.key));
+                if ev.emod & termbox::TB_MOD_ALT != 0 {
+                    Event::ModifiedKeyEvent(key, MOD_ALT)
+                } else {
                     Event::KeyEvent(key)
                 }
-                else {
-                    Event::KeyEvent(Key::Unknown(ev.key))
-                }
             }),
         2 => Ok(Event::ResizeEvent(ev.w, ev.h)),
         3 => {
@@ -212,9 +526,112 @@ fn unpack_event(ev_type: c_int, ev: &RawEvent, raw: bool) -> EventResult {
     }
 }
 
+// Make room for `lines` lines from the cursor down for `ScreenMode::Inline`, scrolling the
+// terminal up if there aren't that many below it, and return the row they start on.
+fn reserve_lines(tty: &tty::Tty, lines: usize) -> io::Result<usize> {
+    let (_, height) = tty.size()?;
+    let lines = cmp::max(cmp::min(lines, height), 1);
+    let _guard = RawModeGuard::for_fd(tty.as_raw_fd())?;
+    // Newlines scroll the terminal once they reach the bottom, and moving back up doesn't.
+    let mut out = String::from("\r");
+    out.push_str(&"\n".repeat(lines - 1));
+    if lines > 1 {
+        out.push_str(&format!("\x1b[{}A", lines - 1));
+    }
+    tty.write(out.as_bytes())?;
+    match tty.query(b"\x1b[6n", Duration::from_millis(200), |r| parse_cursor_report(r).is_some()) {
+        Ok(reply) => Ok(parse_cursor_report(&reply).unwrap().1),
+        // Without a reply, go by the bottom of the screen instead.
+        Err(_) => {
+            tty.write(format!("\x1b[{};1H{}", height, "\n".repeat(lines)).as_bytes())?;
+            Ok(height.saturating_sub(lines))
+        },
+    }
+}
+
+// Scroll everything on screen into the scrollback for `ScreenMode::Main`, by starting as many
+// new lines at the bottom as there are rows.
+fn scroll_away(tty: &tty::Tty) -> io::Result<()> {
+    let (_, height) = tty.size()?;
+    tty.write(format!("\x1b[{};1H{}\x1b[H", height, "\n".repeat(height)).as_bytes())
+}
+
+// Find a cursor position report (`ESC [ row ; col R`) at the end of `reply`, and return it as
+// zero-based (x, y).
+fn parse_cursor_report(reply: &[u8]) -> Option<(usize, usize)> {
+    if reply.last() != Some(&b'R') {
+        return None;
+    }
+    let start = match reply.windows(2).rposition(|w| w == b"\x1b[") {
+        Some(i) => i + 2,
+        None => return None,
+    };
+    let body = match std::str::from_utf8(&reply[start..reply.len() - 1]) {
+        Ok(body) => body,
+        Err(_) => return None,
+    };
+    let mut parts = body.splitn(2, ';').map(|n| n.parse::<usize>());
+    match (parts.next(), parts.next()) {
+        (Some(Ok(row)), Some(Ok(col))) if row > 0 && col > 0 => Some((col - 1, row - 1)),
+        _ => None,
+    }
+}
+
+// Parse an XTWINOPS report, `ESC [ kind ; height ; width t`, returning `(width, height)`.
+fn parse_window_report(reply: &[u8], kind: usize) -> Option<(usize, usize)> {
+    if reply.last() != Some(&b't') {
+        return None;
+    }
+    let start = reply.windows(2).rposition(|w| w == b"\x1b[")? + 2;
+    let body = std::str::from_utf8(&reply[start..reply.len() - 1]).ok()?;
+    let numbers: Vec<usize> = body.split(';').map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    match numbers[..] {
+        [k, h, w] if k == kind && w > 0 && h > 0 => Some((w, h)),
+        _ => None,
+    }
+}
+
+// Find an OSC reply starting with `prefix` (`ESC ] <prefix> ... ST`) in `reply`, and return what
+// follows the prefix. The reply may be terminated by either BEL or ESC \.
+fn parse_osc_reply<'a>(reply: &'a [u8], prefix: &[u8]) -> Option<&'a [u8]> {
+    let introducer = [b"\x1b]".as_ref(), prefix].concat();
+    let start = match reply.windows(introducer.len()).position(|w| w == &introducer[..]) {
+        Some(i) => i + introducer.len(),
+        None => return None,
+    };
+    let rest = &reply[start..];
+    if let Some(end) = rest.iter().position(|&b| b == b'\x07') {
+        return Some(&rest[..end]);
+    }
+    rest.windows(2).position(|w| w == b"\x1b\\").map(|end| &rest[..end])
+}
+
+// Parse an X11 color spec as found in OSC color replies: `rgb:R/G/B` with one to four hex
+// digits per channel (`rgba:` with a fourth, ignored, channel is sometimes used too), or
+// `#RRGGBB`. Channels are scaled to 8 bits.
+fn parse_color_spec(spec: &[u8]) -> Option<(u8, u8, u8)> {
+    let spec = std::str::from_utf8(spec).ok()?;
+    if spec.starts_with('#') && spec.len() == 7 {
+        let channel = |i: usize| u8::from_str_radix(spec.get(i..i + 2)?, 16).ok();
+        return Some((channel(1)?, channel(3)?, channel(5)?));
+    }
+    let channels = spec.strip_prefix("rgb:").or_else(|| spec.strip_prefix("rgba:"))?;
+    let mut values = channels.split('/').map(|c| {
+        if c.is_empty() || c.len() > 4 {
+            return None;
+        }
+        let max = (1u32 << (4 * c.len())) - 1;
+        u32::from_str_radix(c, 16).ok().map(|v| (v * 255 / max) as u8)
+    });
+    Some((values.next()??, values.next()??, values.next()??))
+}
+
 #[derive(Debug)]
 pub enum InitError {
     BufferStderrFailed(io::Error),
+    TerminalSizeFailed(io::Error),
+    /// `InitOptions::keep_signals` or `keep_output_processing` couldn't be applied.
+    TerminalModeFailed(io::Error),
     AlreadyOpen,
     UnsupportedTerminal,
     FailedToOpenTTy,
@@ -232,6 +649,8 @@ impl Error for InitError {
     fn description(&self) -> &str {
         match *self {
             InitError::BufferStderrFailed(_) => "Could not redirect stderr",
+            InitError::TerminalSizeFailed(_) => "Could not get the terminal size",
+            InitError::TerminalModeFailed(_) => "Could not set the terminal's mode",
             InitError::AlreadyOpen => "RustBox is already open",
             InitError::UnsupportedTerminal => "Unsupported terminal",
             InitError::FailedToOpenTTy => "Failed to open TTY",
@@ -243,6 +662,8 @@ impl Error for InitError {
     fn cause(&self) -> Option<&Error> {
         match *self {
             InitError::BufferStderrFailed(ref e) => Some(e),
+            InitError::TerminalSizeFailed(ref e) => Some(e),
+            InitError::TerminalModeFailed(ref e) => Some(e),
             _ => None
         }
     }
@@ -263,18 +684,180 @@ impl FromPrimitive for InitError {
    }
 }
 
+// Translate a style and colors into termbox's fg/bg attribute words for the current output
+// mode.
+fn attributes(mode: OutputMode, sty: Style, fg: Color, bg: Color) -> (u16, u16) {
+    match mode {
+        // 256 color mode, which has no default color, so white (light gray) on black stands in
+        // for it.
+        OutputMode::EightBit => {
+            let index = |color: Color, default: Color| {
+                Style::from_256color(if color == Color::Default { default } else { color })
+            };
+            ((index(fg, Color::White) | (sty & style::TB_ATTRIB)).bits(), index(bg, Color::Black).bits())
+        },
+
+        // 216 and 24 color modes, where termbox numbers the colors from the start of the cube
+        // or the grays. Neither has a default color either, so light gray on black stands in
+        // for it.
+        OutputMode::WebSafe | OutputMode::Grayscale => {
+            let (palette, first) = match mode {
+                OutputMode::WebSafe => (Palette::WebSafe, 16),
+                _ => (Palette::Grayscale, 232),
+            };
+            let index = |color: Color, default: u8| {
+                let color = if color == Color::Default { Color::Rgb(default, default, default) } else { color };
+                color.downsample(palette).as_256color() - first
+            };
+            (index(fg, 0xe5) | (sty & style::TB_ATTRIB).bits(), index(bg, 0x00))
+        },
+
+        // 16 color mode
+        _ => {
+            ((Style::from_color(fg) | (sty & style::TB_ATTRIB)).bits(),
+             Style::from_color(bg).bits())
+        }
+    }
+}
+
+fn cell_from_raw(mode: OutputMode, raw: &RawCell) -> Cell {
+    let (fg, bg) = match mode {
+        OutputMode::EightBit => (Color::from_256color(raw.fg), Color::from_256color(raw.bg)),
+        OutputMode::WebSafe => (Color::Byte((raw.fg & 0xFF) + 16), Color::Byte((raw.bg & 0xFF) + 16)),
+        OutputMode::Grayscale => (Color::Byte((raw.fg & 0xFF) + 232), Color::Byte((raw.bg & 0xFF) + 232)),
+        _ => (Color::from_16color(raw.fg), Color::from_16color(raw.bg)),
+    };
+    Cell {
+        ch: char::from_u32(raw.ch).unwrap_or(' '),
+        fg,
+        bg,
+        style: Style::from_bits_truncate(raw.fg) & style::TB_ATTRIB,
+    }
+}
+
+// What `restart` keeps of termbox's state while termbox is shut down, to give back to it once
+// it has started again.
+struct Stopped {
+    width: usize,
+    height: usize,
+    cells: Vec<RawCell>,
+    input_mode: c_int,
+    output_mode: c_int,
+}
+
 #[allow(missing_copy_implementations)]
 pub struct RustBox {
     // We only bother to redirect stderr for the moment, since it's used for panic!
     _stderr: Option<Hold>,
+    // Our own handle on the terminal, for escape sequences termbox has no API for. Only written
+    // to with `output_lock` held.
+    tty: tty::Tty,
+    // Becomes readable when the terminal is resized. See `resize_fd`.
+    resize_pipe: winch::Pipe,
     // RAII lock.
     //
     // Note that running *MUST* be the last field in the destructor, since destructors run in
     // top-down order. Otherwise it will not properly protect the above fields.
     _running: running::RunningGuard,
 
-    // Store this so we know which colours to use
-    output_mode: OutputMode,
+    // `InitOptions::keep_signals` and `keep_output_processing`, for when termbox is restarted.
+    keep_modes: (bool, bool),
+
+    // `InitOptions::screen`, and the first row of the screen drawn in.
+    screen: ScreenMode,
+    viewport_top: AtomicUsize,
+
+    // Store this so we know which colours to use. Like the other `Mutex`es below, this is only
+    // touched with `output_lock` held; the `Mutex` just lets it be changed through `&self`.
+    output_mode: Mutex<OutputMode>,
+
+    // The colors and style that cleared cells get, as passed to `set_clear_attributes`. `None`
+    // means termbox's own default.
+    clear_attributes: Mutex<Option<(Color, Color, Style)>>,
+
+    // Whether `set_cursor_style` has been used, in which case the terminal's default cursor is
+    // put back on drop.
+    cursor_style_changed: AtomicBool,
+
+    // Whether the application has saved the cursor with `save_cursor` and not yet restored it,
+    // in which case `present` mustn't save and restore the cursor itself.
+    cursor_saved: AtomicBool,
+
+    // Whether `present` shows box-drawing and block characters in ASCII. See
+    // `set_ascii_fallback`.
+    ascii_fallback: AtomicBool,
+
+    // Whether the terminal's original title has been saved by `set_title`, and so needs to be
+    // restored on drop.
+    title_saved: AtomicBool,
+
+    // Set once the terminal has been handed back, by `shutdown` or on drop.
+    shut_down: bool,
+
+    // Set while termbox is shut down by `restart`, including after it has failed to start
+    // termbox again, in which case nothing may call into termbox until a later `restart`
+    // succeeds. Only changed with both locks held.
+    stopped: Mutex<Option<Stopped>>,
+
+    // The recording started by `start_recording`, if any.
+    recorder: Mutex<Option<record::Recorder>>,
+
+    // The input recording started by `start_input_recording`, and the one being played back by
+    // `play_input`. Only touched with `input_lock` held, apart from on shutdown.
+    input_recorder: Mutex<Option<record::InputRecorder>>,
+    playback: Mutex<Option<record::Playback>>,
+
+    // Set by `set_signal_keys`. Only touched with `input_lock` held.
+    signal_keys: Mutex<SignalKeys>,
+
+    // Set by `set_mouse_tracking`. Only changed with both locks held, so it can be read with
+    // either.
+    mouse_tracking: Mutex<MouseTracking>,
+
+    // How images are drawn, once `image_protocol` has worked it out or been told.
+    image_protocol: Mutex<Option<ImageProtocol>>,
+
+    // The size of a cell in pixels, once `cell_size` has had to ask the terminal for it.
+    cell_size: Mutex<Option<(usize, usize)>>,
+
+    // What the terminal said about itself, once `terminal_info` has asked, and whether that
+    // included support for synchronized output, which `present` then wraps its output in.
+    terminal_info: Mutex<Option<TerminalInfo>>,
+    synchronized_output: AtomicBool,
+    // The images drawn since they were last cleared, and those that were on screen after the
+    // last `present`. Only touched with `output_lock` held.
+    images: Mutex<Vec<image::Placement>>,
+    shown_images: Mutex<Vec<image::Placement>>,
+    // The id to give the next kitty image.
+    next_image_id: AtomicU32,
+
+    // The layers added by `add_layer`, lowest first. Only touched with `output_lock` held.
+    layers: Mutex<layer::Stack>,
+
+    // What termbox has put on screen, and the stats for the last frame and all of them. Only
+    // touched with `output_lock` held.
+    tracker: Mutex<stats::Tracker>,
+    stats: Mutex<(Stats, Stats)>,
+
+    // The overlay turned on by `set_debug_overlay`. Events are added to it from the input side,
+    // so it has to be locked on its own, never while waiting for another lock.
+    debug_overlay: Mutex<Option<debug::Overlay>>,
+
+    // The terminal's background, once `background_kind` has worked it out.
+    background_kind: Mutex<Option<BackgroundKind>>,
+
+    // The input method composition shown by `set_preedit`. Only touched with `output_lock`
+    // held.
+    preedit: Mutex<Option<ime::Preedit>>,
+
+    // What `print` does with control characters. Only touched with `output_lock` held.
+    control_policy: Mutex<ControlPolicy>,
+
+    // The theme set by `set_theme`, locked on its own just long enough to read or replace it.
+    theme: Mutex<Theme>,
+
+    // Where the cursor was last put, so it can be put back after restarting termbox.
+    cursor: Mutex<(c_int, c_int)>,
 
     // Used/obtained by methods that read from the terminal (or termbox's
     // internal state). Termbox is only thread safe to the extent that one
@@ -311,6 +894,32 @@ pub struct InitOptions {
     /// your program, don't use RustBox's default pipe-based redirection; instead, redirect stderr
     /// to a log file or another process that is capable of handling it better.
     pub buffer_stderr: bool,
+
+    /// Leave the terminal's signal keys working, so that Ctrl-C interrupts the process and
+    /// Ctrl-Z stops it rather than arriving as key events. Stopping the process this way leaves
+    /// the terminal as RustBox set it up; `suspend_process` hands it back properly.
+    pub keep_signals: bool,
+
+    /// Leave the terminal's output processing on, so that a `\n` written to it behind
+    /// RustBox's back also returns to the start of the line.
+    pub keep_output_processing: bool,
+
+    /// Put the terminal back as it was if the process is killed by SIGTERM, SIGHUP, SIGINT or
+    /// SIGQUIT while RustBox is running, rather than leaving the user's shell unusable. Signals
+    /// the application already handles itself are left alone.
+    pub restore_on_signal: bool,
+
+    /// Where to draw. Defaults to `ScreenMode::Alternate`.
+    pub screen: ScreenMode,
+
+    /// Which mouse events to report, when the input mode has the mouse on. Defaults to
+    /// `MouseTracking::Clicks`.
+    pub mouse_tracking: MouseTracking,
+
+    /// Ask the terminal what it is and supports straight away, rather than the first time
+    /// `terminal_info` is called, so that `present` uses synchronized output from the start
+    /// where the terminal supports it. May hold up `init` for up to 200ms.
+    pub probe_terminal: bool,
 }
 
 impl Default for InitOptions {
@@ -319,6 +928,12 @@ impl Default for InitOptions {
             input_mode: InputMode::Current,
             output_mode: OutputMode::Current,
             buffer_stderr: false,
+            keep_signals: false,
+            keep_output_processing: false,
+            restore_on_signal: false,
+            screen: ScreenMode::Alternate,
+            mouse_tracking: MouseTracking::Clicks,
+            probe_terminal: false,
         }
     }
 }
@@ -380,6 +995,10 @@ impl RustBox {
     /// use std::default::Default;
     /// let rb = RustBox::init(InitOptions { input_mode: rustbox::InputMode::Esc, ..Default::default() });
     /// ```
+    ///
+    /// Only one RustBox can exist at a time, since termbox itself is global. While one is
+    /// alive, `init` fails with `InitError::AlreadyOpen`, from any thread. Once it has been
+    /// dropped (or `shutdown`), the terminal is fully restored and `init` can be called again.
     pub fn init(opts: InitOptions) -> Result<RustBox, InitError> {
         let running = match running::run() {
             Some(r) => r,
@@ -392,19 +1011,73 @@ impl RustBox {
             None
         };
 
+        let tty = match tty::Tty::open() {
+            Ok(tty) => tty,
+            Err(_) => return Err(InitError::FailedToOpenTTy),
+        };
+        let resize_pipe = winch::Pipe::new().map_err(|_| InitError::PipeTrapError)?;
+
+        let viewport_top = match opts.screen {
+            ScreenMode::Alternate => 0,
+            ScreenMode::Inline(lines) => reserve_lines(&tty, lines).map_err(InitError::TerminalModeFailed)?,
+            ScreenMode::Main => {
+                scroll_away(&tty).map_err(InitError::TerminalModeFailed)?;
+                0
+            },
+        };
+
+        signals::install(tty.as_raw_fd(), opts.restore_on_signal).map_err(InitError::TerminalModeFailed)?;
+
         // Create the RustBox.
-        let mut rb = unsafe { match termbox::tb_init() {
+        let rb = unsafe { match termbox::tb_init() {
             0 => RustBox {
                 _stderr: stderr,
+                tty,
+                resize_pipe,
                 _running: running,
-                output_mode: OutputMode::Current,
+                keep_modes: (opts.keep_signals, opts.keep_output_processing),
+                screen: opts.screen,
+                viewport_top: AtomicUsize::new(viewport_top),
+                output_mode: Mutex::new(OutputMode::Current),
+                clear_attributes: Mutex::new(None),
+                cursor_style_changed: AtomicBool::new(false),
+                cursor_saved: AtomicBool::new(false),
+                ascii_fallback: AtomicBool::new(!capability::unicode_supported()),
+                title_saved: AtomicBool::new(false),
+                shut_down: false,
+                stopped: Mutex::new(None),
+                recorder: Mutex::new(None),
+                input_recorder: Mutex::new(None),
+                signal_keys: Mutex::new(SignalKeys::Keys),
+                mouse_tracking: Mutex::new(opts.mouse_tracking),
+                playback: Mutex::new(None),
+                image_protocol: Mutex::new(None),
+                cell_size: Mutex::new(None),
+                terminal_info: Mutex::new(None),
+                synchronized_output: AtomicBool::new(false),
+                images: Mutex::new(Vec::new()),
+                shown_images: Mutex::new(Vec::new()),
+                next_image_id: AtomicU32::new(1),
+                layers: Mutex::new(Default::default()),
+                tracker: Mutex::new(Default::default()),
+                stats: Mutex::new(Default::default()),
+                debug_overlay: Mutex::new(None),
+                preedit: Mutex::new(None),
+                control_policy: Mutex::new(ControlPolicy::default()),
+                background_kind: Mutex::new(None),
+                theme: Mutex::new(Theme::default()),
+                cursor: Mutex::new((termbox::TB_HIDE_CURSOR, termbox::TB_HIDE_CURSOR)),
                 input_lock: Mutex::new(()),
                 output_lock: Mutex::new(()),
             },
             res => {
+                signals::uninstall();
                 return Err(FromPrimitive::from_isize(res as isize).unwrap())
             }
         }};
+        winch::hook(&rb.resize_pipe);
+        rb.apply_keep_modes().map_err(InitError::TerminalModeFailed)?;
+        rb.enter_main_screen().map_err(InitError::TerminalModeFailed)?;
         match opts.input_mode {
             InputMode::Current => (),
             _ => rb.set_input_mode(opts.input_mode),
@@ -413,6 +1086,10 @@ impl RustBox {
             OutputMode::Current => (),
             _ => rb.set_output_mode(opts.output_mode),
         }
+        rb.apply_mouse_tracking().map_err(InitError::TerminalModeFailed)?;
+        if opts.probe_terminal {
+            rb.terminal_info();
+        }
 
         Ok(rb)
     }
@@ -420,154 +1097,1920 @@ impl RustBox {
     pub fn width(&self) -> usize {
         let _lock = self.output_lock.lock();
 
-        unsafe { termbox::tb_width() as usize }
+        self.raw_width()
     }
 
     pub fn height(&self) -> usize {
         let _lock = self.output_lock.lock();
 
-        unsafe { termbox::tb_height() as usize }
+        self.raw_height()
     }
 
-    pub fn clear(&self) {
-        let _lock = self.output_lock.lock();
+    /// Ask the terminal for its current size and resize the buffers if it has changed.
+    ///
+    /// termbox only notices a resize when SIGWINCH arrives while it is waiting for an event, so
+    /// this is useful when the signal may have been missed. If the size did change, termbox is
+    /// restarted as with `invalidate`, and `Ok(true)` is returned. If that fails, termbox is left
+    /// shut down as described for `invalidate`.
+    pub fn refresh_size(&self) -> Result<bool, InitError> {
+        let _input_lock = self.input_lock.lock();
+        let _output_lock = self.output_lock.lock();
 
-        unsafe { termbox::tb_clear() }
+        let size = self.tty.size().map_err(InitError::TerminalSizeFailed)?;
+        if size == (self.raw_width(), self.raw_height()) {
+            return Ok(false);
+        }
+        self.restart(|| ())?;
+        Ok(true)
     }
 
-    pub fn present(&self) {
+    pub fn clear(&self) {
         let _lock = self.output_lock.lock();
 
-        unsafe { termbox::tb_present() }
+        self.images.lock().unwrap().clear();
+        if !self.termbox_stopped() {
+            unsafe { termbox::tb_clear() }
+        }
     }
 
-    pub fn set_cursor(&self, x: isize, y: isize) {
+    /// Like `clear`, but only resets the `w` by `h` rectangle whose top left corner is at
+    /// `(x, y)`. The parts of the rectangle that lie off screen are ignored. Any image that
+    /// overlaps the rectangle is removed.
+    pub fn clear_region(&self, x: usize, y: usize, w: usize, h: usize) {
         let _lock = self.output_lock.lock();
 
-        unsafe { termbox::tb_set_cursor(x as c_int, y as c_int) }
-    }
+        self.images.lock().unwrap().retain(|image| !image.overlaps(x, y, w, h));
 
-    pub unsafe fn change_cell(&self, x: usize, y: usize, ch: u32, fg: u16, bg: u16) {
-        termbox::tb_change_cell(x as c_int, y as c_int, ch, fg, bg)
+        let (fg_int, bg_int) = self.clear_raw_attributes();
+        let right = cmp::min(x.saturating_add(w), self.raw_width());
+        let bottom = cmp::min(y.saturating_add(h), self.raw_height());
+        for cy in y..bottom {
+            for cx in x..right {
+                unsafe {
+                    self.change_cell(cx, cy, ' ' as u32, fg_int, bg_int);
+                }
+            }
+        }
     }
 
-    pub fn print(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+    /// Bring the screen up to date with the back buffer.
+    ///
+    /// termbox doesn't report failed writes itself, so once it has written the frame the
+    /// terminal is checked for a hang-up. If it has gone away (the terminal emulator was closed,
+    /// or the ssh connection dropped) an error of kind `BrokenPipe` is returned, and every later
+    /// `present` will fail the same way; the application should take that as a sign to exit.
+    /// Errors writing images are returned as they are.
+    ///
+    /// If termbox has failed to start again after `invalidate` or the like, this returns an
+    /// error of kind `NotConnected` without writing anything.
+    pub fn present(&self) -> io::Result<()> {
         let _lock = self.output_lock.lock();
 
-        let fg_int;
-        let bg_int;
-
-        match self.output_mode {
-            // 256 color mode
-            OutputMode::EightBit => {
-                fg_int = Style::from_256color(fg) | (sty & style::TB_ATTRIB);
-                bg_int = Style::from_256color(bg);
-            },
-
-            // 16 color mode
-            _ => {
-                fg_int = Style::from_color(fg) | (sty & style::TB_ATTRIB);
-                bg_int = Style::from_color(bg);
+        if self.termbox_stopped() {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "termbox is not running"));
+        }
+        let start = Instant::now();
+        let mut base = self.draw_overlays();
+        if self.ascii_fallback() {
+            if base.is_none() {
+                base = Some(self.raw_cells().to_vec());
             }
+            self.replace_unicode();
         }
-
-        for (i, ch) in s.chars().enumerate() {
+        if let ScreenMode::Inline(_) = self.screen {
+            if base.is_none() {
+                base = Some(self.raw_cells().to_vec());
+            }
+            self.blank_outside_viewport();
+        }
+        let frame = self.raw_buffer();
+        if let Some(ref mut recorder) = *self.recorder.lock().unwrap() {
+            recorder.frame(&frame);
+        }
+        let (fg_int, bg_int) = self.clear_raw_attributes();
+        let blank = cell_from_raw(self.output_mode(), &RawCell { ch: ' ' as u32, fg: fg_int, bg: bg_int });
+        let mut stats = self.tracker.lock().unwrap().frame(frame.width(), frame.cells(), blank);
+        // termbox would lay out characters the width policy measures differently by their usual
+        // width, so it gets a blank instead and they are written afterwards.
+        let misfits: Vec<(usize, usize, Cell)> = frame.cells().iter().enumerate()
+            .filter(|&(_, cell)| text::width_differs(cell.ch))
+            .map(|(i, &cell)| (i % frame.width(), i / frame.width(), cell))
+            .collect();
+        if !misfits.is_empty() && base.is_none() {
+            base = Some(self.raw_cells().to_vec());
+        }
+        for &(x, y, _) in &misfits {
+            let raw = self.raw_cells()[y * frame.width() + x];
+            unsafe {
+                self.change_cell(x, y, ' ' as u32, raw.fg, raw.bg);
+            }
+        }
+        // While there's a composition, the cursor goes where the input method wants it.
+        let preedit_cursor = self.preedit.lock().unwrap().as_ref().map(|preedit| preedit.cursor_position());
+        // A failure to write these shows up as a hang-up at the end.
+        let synchronized = self.synchronized_output.load(Ordering::SeqCst);
+        unsafe {
+            if let Some((x, y)) = preedit_cursor {
+                let x = cmp::min(x, self.raw_width().saturating_sub(1));
+                termbox::tb_set_cursor(x as c_int, y as c_int);
+            }
+            if synchronized {
+                let _ = self.tty.write(SYNCHRONIZED_BEGIN);
+            }
+            termbox::tb_present();
+            if preedit_cursor.is_some() {
+                let (x, y) = *self.cursor.lock().unwrap();
+                termbox::tb_set_cursor(x, y);
+            }
+        }
+        let shown_cursor = match preedit_cursor {
+            Some((x, y)) => Some((cmp::min(x, self.raw_width().saturating_sub(1)), y)),
+            None => match *self.cursor.lock().unwrap() {
+                (x, y) if x >= 0 && y >= 0 => Some((x as usize, y as usize)),
+                _ => None,
+            },
+        };
+        let (save, restore) = self.cursor_return(shown_cursor);
+        let images = self.present_images(&save, &restore);
+        let misfits = self.present_misfits(&misfits, &save, &restore);
+        if synchronized {
+            let _ = self.tty.write(SYNCHRONIZED_END);
+        }
+        if let Some(cells) = base {
             unsafe {
-                self.change_cell(x+i, y, ch as u32, fg_int.bits(), bg_int.bits());
+                termbox::tb_blit(0, 0, self.raw_width() as c_int, self.raw_height() as c_int,
+                                 cells.as_ptr());
             }
         }
+        stats.bytes += *images.as_ref().unwrap_or(&0) as u64 + *misfits.as_ref().unwrap_or(&0) as u64;
+        stats.duration = start.elapsed();
+        let mut all_stats = self.stats.lock().unwrap();
+        all_stats.0 = stats;
+        all_stats.1.add(&stats);
+        images?;
+        misfits?;
+        self.tty.check_open()
     }
 
-    pub fn print_char(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, ch: char) {
+    /// Show `text`, the composition an input method is building up, underlined at `(x, y)`,
+    /// with the cursor `cursor` characters into it, until `clear_preedit` is called. `present`
+    /// draws it over whatever is there, which stays in the back buffer underneath.
+    ///
+    /// Terminals draw their input method's own candidate window at the cursor, so while there
+    /// is no composition the cursor should be kept at the insertion point with `set_cursor`
+    /// rather than hidden, e.g. at `LineEdit::cursor_position`, for the window to show up in
+    /// the right place.
+    pub fn set_preedit(&self, x: usize, y: usize, text: &str, cursor: usize) {
         let _lock = self.output_lock.lock();
 
-        let fg_int;
-        let bg_int;
+        *self.preedit.lock().unwrap() = Some(ime::Preedit { x, y, text: text.to_string(), cursor });
+    }
 
-        match self.output_mode {
-            // 256 color mode
-            OutputMode::EightBit => {
-                fg_int = Style::from_256color(fg) | (sty & style::TB_ATTRIB);
-                bg_int = Style::from_256color(bg);
-            },
+    pub fn clear_preedit(&self) {
+        let _lock = self.output_lock.lock();
+
+        *self.preedit.lock().unwrap() = None;
+    }
+
+    /// Show box-drawing, block and braille characters as the nearest ASCII, e.g. `+`, `-` and
+    /// `|` for borders, for terminals that would garble them. Only what `present` sends to the
+    /// terminal changes; the back buffer keeps the original characters.
+    ///
+    /// termbox writes every frame itself, so the VT100 line-drawing character set can't be
+    /// switched to. This starts out on if the locale's character set isn't UTF-8, or on the
+    /// Linux console.
+    pub fn set_ascii_fallback(&self, on: bool) {
+        self.ascii_fallback.store(on, Ordering::SeqCst);
+    }
 
-            // 16 color mode
-            _ => {
-                fg_int = Style::from_color(fg) | (sty & style::TB_ATTRIB);
-                bg_int = Style::from_color(bg);
+    pub fn ascii_fallback(&self) -> bool {
+        self.ascii_fallback.load(Ordering::SeqCst)
+    }
+
+    // Put back the blank cells termbox started with outside the viewport, which it then leaves
+    // alone, so that nothing drawn there reaches the screen. Must be called with
+    // `output_lock` held.
+    fn blank_outside_viewport(&self) {
+        let (width, height) = (self.raw_width(), self.raw_height());
+        let viewport = self.raw_viewport();
+        for y in (0..viewport.y).chain(viewport.bottom()..height) {
+            for x in 0..width {
+                unsafe {
+                    self.change_cell(x, y, ' ' as u32, termbox::TB_DEFAULT, termbox::TB_DEFAULT);
+                }
             }
         }
-        unsafe {
-            self.change_cell(x, y, ch as u32, fg_int.bits(), bg_int.bits());
+    }
+
+    // Swap the characters in the back buffer that `border::ascii_fallback` has stand-ins for.
+    // Must be called with `output_lock` held.
+    fn replace_unicode(&self) {
+        let width = self.raw_width();
+        for i in 0..self.raw_cells().len() {
+            let raw = self.raw_cells()[i];
+            let ch = char::from_u32(raw.ch).unwrap_or(' ');
+            let ascii = border::ascii_fallback(ch);
+            if ascii != ch {
+                unsafe {
+                    self.change_cell(i % width, i / width, ascii as u32, raw.fg, raw.bg);
+                }
+            }
         }
     }
 
-    pub fn poll_event(&self, raw: bool) -> EventResult {
-        let _lock = self.input_lock.lock();
-        let mut ev = NIL_RAW_EVENT;
-        let rc = unsafe {
-            termbox::tb_poll_event(&mut ev)
-        };
-        unpack_event(rc, &ev, raw)
+    /// Show or hide an overlay in the top right corner of the screen with the frame rate, what
+    /// the last `present` wrote, the screen size and the last few events, for finding out why
+    /// an application is slow or flickers. Like a layer, it is drawn by `present` without
+    /// changing the back buffer.
+    pub fn set_debug_overlay(&self, on: bool) {
+        let mut overlay = self.debug_overlay.lock().unwrap();
+        if on != overlay.is_some() {
+            *overlay = if on { Some(Default::default()) } else { None };
+        }
     }
 
-    pub fn peek_event(&self, timeout: Duration, raw: bool) -> EventResult {
-        let _lock = self.input_lock.lock();
-        let mut ev = NIL_RAW_EVENT;
-        let rc = unsafe {
-            termbox::tb_peek_event(&mut ev, (timeout.as_secs() * 1000 + timeout.subsec_nanos() as u64 / 1000000) as c_int)
-        };
-        unpack_event(rc, &ev, raw)
+    pub fn debug_overlay(&self) -> bool {
+        self.debug_overlay.lock().unwrap().is_some()
     }
 
-    pub fn set_input_mode(&self, mode: InputMode) {
+    /// What the last `present` did. See `Stats`.
+    pub fn frame_stats(&self) -> Stats {
         let _lock = self.output_lock.lock();
 
-        unsafe {
-            termbox::tb_select_input_mode(mode as c_int);
-        }
+        self.stats.lock().unwrap().0
     }
 
-    pub fn set_output_mode(&mut self, mode: OutputMode) {
+    /// What every `present` since the start, or since `reset_stats`, did added together.
+    pub fn total_stats(&self) -> Stats {
         let _lock = self.output_lock.lock();
 
-        self.output_mode = mode;
+        self.stats.lock().unwrap().1
+    }
+
+    pub fn reset_stats(&self) {
+        let _lock = self.output_lock.lock();
 
-        unsafe {
-            termbox::tb_select_output_mode(mode as c_int);
+        *self.stats.lock().unwrap() = Default::default();
+    }
+
+    // Draw the layers, the preedit and the debug overlay over the back buffer, returning what
+    // was there before so that `present` can put it back afterwards. Must be called with
+    // `output_lock` held.
+    fn draw_overlays(&self) -> Option<Vec<RawCell>> {
+        let (width, height) = (self.raw_width(), self.raw_height());
+        let last = self.stats.lock().unwrap().0;
+        let overlay = self.debug_overlay.lock().unwrap().as_mut().map(|overlay| {
+            overlay.present(Instant::now());
+            overlay.lines(&last, width, height)
+        });
+        let mut layers = self.layers.lock().unwrap();
+        let preedit = self.preedit.lock().unwrap();
+        if layers.is_empty() && preedit.is_none() && overlay.is_none() {
+            return None;
+        }
+        let base = self.raw_cells().to_vec();
+        let mode = self.output_mode();
+        for layer in layers.resized(width, height) {
+            for (i, cell) in layer.cells().iter().enumerate() {
+                if !cell.is_transparent() {
+                    let below = cell_from_raw(mode, &self.raw_cells()[i]);
+                    let cell = cell.over(below);
+                    let (fg_int, bg_int) = attributes(mode, cell.style, cell.fg, cell.bg);
+                    unsafe {
+                        self.change_cell(i % width, i / width, cell.ch as u32, fg_int, bg_int);
+                    }
+                }
+            }
+        }
+        // The composition is underlined, in the colors of what it covers.
+        for (x, y, ch) in preedit.iter().flat_map(|preedit| preedit.cells()) {
+            if x < width && y < height {
+                let below = cell_from_raw(mode, &self.raw_cells()[y * width + x]);
+                let (fg_int, bg_int) = attributes(mode, below.style | RB_UNDERLINE, below.fg, below.bg);
+                unsafe {
+                    self.change_cell(x, y, ch as u32, fg_int, bg_int);
+                }
+            }
         }
+        // The overlay goes in the top right corner, above everything else.
+        if let Some(lines) = overlay {
+            let (fg_int, bg_int) = attributes(mode, RB_REVERSE, Color::White, Color::Black);
+            let x = width.saturating_sub(debug::WIDTH);
+            for (y, line) in lines.iter().enumerate().take(height) {
+                for (dx, ch) in text::columns(line) {
+                    if x + dx < width {
+                        unsafe {
+                            self.change_cell(x + dx, y, ch as u32, fg_int, bg_int);
+                        }
+                    }
+                }
+            }
+        }
+        Some(base)
     }
 
-    /// Convenience method to lock all (both input/output) access to
-    /// Rustbox, shutdown termbox itself, and then defer to the caller (via F,
-    /// while access is still locked). Once F completes, termbox is started and
-    /// the locks are released.
-    pub fn suspend<F>(&self, func: F)
-        where F: FnOnce() -> ()
+    // Send the images that are new, or have had something drawn over them, since the last
+    // `present`, and delete kitty images that have been cleared, returning the number of bytes
+    // written. `save` and `restore` go around each image, as from `cursor_return`. Must be
+    // called with `output_lock` held.
+    fn present_images(&self, save: &str, restore: &str) -> io::Result<usize> {
+        let mut images = self.images.lock().unwrap();
+        let mut shown = self.shown_images.lock().unwrap();
+        let mut out = Vec::new();
+        for image in images.iter_mut() {
+            image.cells = self.raw_region(image.x, image.y, image.w, image.h);
+            let previous = shown.iter().find(|s| s.same_image(image));
+            let unchanged = match previous {
+                Some(previous) => {
+                    image.id = previous.id;
+                    // Kitty images aren't affected by what is drawn over them.
+                    image.is_kitty() || previous.cells == image.cells
+                },
+                None => false,
+            };
+            if !unchanged {
+                if image.is_kitty() && image.id == 0 {
+                    image.id = self.next_image_id.fetch_add(1, Ordering::SeqCst);
+                }
+                // Put the cursor back after the image, which moves it.
+                out.extend_from_slice(format!("{}\x1b[{};{}H", save, image.y + 1, image.x + 1).as_bytes());
+                out.extend_from_slice(&image.escape());
+                out.extend_from_slice(restore.as_bytes());
+            }
+        }
+        for old in shown.iter().filter(|s| s.id != 0) {
+            if !images.iter().any(|image| image.id == old.id) {
+                out.extend_from_slice(&image::kitty_delete(old.id));
+            }
+        }
+        *shown = images.clone();
+        if out.is_empty() {
+            return Ok(0);
+        }
+        self.tty.write(&out)?;
+        Ok(out.len())
+    }
+
+    // Write the characters `present` held back from termbox because the width policy measures
+    // them differently. termbox may have written over them since they were last written, so
+    // they are written every time, with `save` and `restore` around each, as for images.
+    fn present_misfits(&self, misfits: &[(usize, usize, Cell)], save: &str, restore: &str) -> io::Result<usize> {
+        if misfits.is_empty() {
+            return Ok(0);
+        }
+        let mut out = String::new();
+        for &(x, y, ref cell) in misfits {
+            out.push_str(&format!("{}\x1b[{};{}H{}{}{}", save, y + 1, x + 1, export::sgr(cell), cell.ch, restore));
+        }
+        self.tty.write(out.as_bytes())?;
+        Ok(out.len())
+    }
+
+    // The cells in a rectangle of the back buffer, which must lie on screen. Must be called with
+    // `output_lock` held.
+    fn raw_region(&self, x: usize, y: usize, w: usize, h: usize) -> Vec<(u32, u16, u16)> {
+        let (cells, width) = (self.raw_cells(), self.raw_width());
+        (y..y + h)
+            .flat_map(|cy| cells[cy * width + x..cy * width + x + w].iter())
+            .map(|cell| (cell.ch, cell.fg, cell.bg))
+            .collect()
+    }
+
+    /// Draw an image over the `w` by `h` cells whose top left corner is at `(x, y)`, scaled to
+    /// fit while keeping its aspect ratio. Anything that doesn't fit on screen is clipped.
+    ///
+    /// The image is sent to the terminal by the next `present`, and stays until it is cleared
+    /// by `clear` or `clear_region`, just like text. Drawing over the cells it covers draws
+    /// over the image. How the image is shown depends on `image_protocol`; without any
+    /// graphics support the cells are filled with the image's colors.
+    pub fn draw_image(&self, x: usize, y: usize, w: usize, h: usize, image: ImageSource)
+                      -> Result<(), ImageError>
     {
-        // Lock I/O until we've resumed.
-        let _input_lock = self.input_lock.lock();
-        let _output_lock = self.output_lock.lock();
+        let pixels = image::Pixels::from_source(&image)?;
+        // There is nothing to show, and the encoders can't cope with it.
+        if pixels.width == 0 || pixels.height == 0 {
+            return Ok(());
+        }
+        let protocol = self.image_protocol();
 
-        unsafe {
-            termbox::tb_shutdown();
+        let _lock = self.output_lock.lock();
+
+        let w = cmp::min(w, self.raw_width().saturating_sub(x));
+        let h = cmp::min(h, self.raw_height().saturating_sub(y));
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+        let (cell_width, cell_height) = self.cell_pixel_size();
+        let encoded = match protocol {
+            ImageProtocol::Kitty | ImageProtocol::Iterm2 => {
+                // The terminal does the scaling, so only shrink images to save sending pixels
+                // that won't be seen.
+                let fitted = pixels.fit(w * cell_width, h * cell_height);
+                let cols = cmp::min(fitted.width.div_ceil(cell_width), w);
+                let rows = cmp::min(fitted.height.div_ceil(cell_height), h);
+                let sent = if fitted.width < pixels.width { &fitted } else { &pixels };
+                if protocol == ImageProtocol::Iterm2 {
+                    image::Encoded::Cells(image::iterm2(sent, cols, rows))
+                } else {
+                    image::Encoded::Kitty {
+                        width: sent.width,
+                        height: sent.height,
+                        cols,
+                        rows,
+                        rgba: sent.rgba(),
+                    }
+                }
+            },
+            ImageProtocol::Sixel => {
+                image::Encoded::Cells(image::sixel(&pixels.fit(w * cell_width, h * cell_height)))
+            },
+            ImageProtocol::Placeholder => {
+                self.draw_placeholder(x, y, w, h, &pixels);
+                return Ok(());
+            },
+        };
+        for cy in y..y + h {
+            for cx in x..x + w {
+                unsafe {
+                    self.change_cell(cx, cy, image::FILL as u32, termbox::TB_DEFAULT,
+                                     termbox::TB_DEFAULT);
+                }
+            }
         }
+        let mut images = self.images.lock().unwrap();
+        images.retain(|image| !image.overlaps(x, y, w, h));
+        images.push(image::Placement::new(x, y, w, h, encoded));
+        Ok(())
+    }
 
-        func();
+    // Fill each cell with the average color of the part of the image it covers, taking a cell
+    // to be about twice as tall as it is wide. Must be called with `output_lock` held.
+    fn draw_placeholder(&self, x: usize, y: usize, w: usize, h: usize, pixels: &image::Pixels) {
+        let mode = self.output_mode();
+        let fitted = pixels.fit(w, h * 2);
+        for cy in 0..fitted.height.div_ceil(2) {
+            for cx in 0..fitted.width {
+                let (r, g, b) = match fitted.average(cx, cy * 2, cx + 1, cy * 2 + 2) {
+                    Some(rgb) => rgb,
+                    None => continue,
+                };
+                // The cell is blank, so its foreground is never seen.
+                let (fg_int, bg_int) = attributes(mode, RB_NORMAL, Color::Rgb(r, g, b), Color::Rgb(r, g, b));
+                unsafe {
+                    self.change_cell(x + cx, y + cy, ' ' as u32, fg_int, bg_int);
+                }
+            }
+        }
+    }
 
-        unsafe {
-            termbox::tb_init();
+    // The size of a cell in pixels, from the kernel, or else from the last time `cell_size`
+    // asked the terminal, or else a guess. Must be called with `output_lock` held.
+    fn cell_pixel_size(&self) -> (usize, usize) {
+        match self.tty.pixel_size() {
+            Ok((pw, ph)) if pw > 0 && ph > 0 =>
+                (cmp::max(pw / self.raw_width().max(1), 1), cmp::max(ph / self.raw_height().max(1), 1)),
+            _ => self.cell_size.lock().unwrap().unwrap_or((10, 20)),
         }
     }
-}
 
-impl Drop for RustBox {
-    fn drop(&mut self) {
+    /// How `draw_image` shows images. Unless set with `set_image_protocol`, this is worked out
+    /// the first time it's needed. iTerm2 is recognised from the environment; otherwise it
+    /// comes from `terminal_info`, which may take the input lock. When another thread is
+    /// waiting for events, call this beforehand to avoid `draw_image` waiting for it.
+    pub fn image_protocol(&self) -> ImageProtocol {
+        if let Some(protocol) = *self.image_protocol.lock().unwrap() {
+            return protocol;
+        }
+        // LC_TERMINAL is passed on by ssh, unlike TERM_PROGRAM.
+        if env::var("TERM_PROGRAM").ok().as_deref() == Some("iTerm.app") ||
+            env::var("LC_TERMINAL").ok().as_deref() == Some("iTerm2")
+        {
+            self.set_image_protocol(ImageProtocol::Iterm2);
+            return ImageProtocol::Iterm2;
+        }
+
+        let info = self.terminal_info();
+        let protocol = if info.kitty_graphics {
+            ImageProtocol::Kitty
+        } else if info.sixel() {
+            ImageProtocol::Sixel
+        } else {
+            ImageProtocol::Placeholder
+        };
+        *self.image_protocol.lock().unwrap() = Some(protocol);
+        protocol
+    }
+
+    // Kitty images stay on screen after termbox goes, unlike everything else it draws, so they
+    // have to be deleted before shutting it down. Must be called with `output_lock` held.
+    fn delete_kitty_images(&self) -> io::Result<()> {
+        if self.shown_images.lock().unwrap().iter().any(|image| image.id != 0) {
+            self.tty.write(image::KITTY_DELETE_ALL)?;
+        }
+        Ok(())
+    }
+
+    /// What the terminal says it is and supports. It is asked the first time this is called,
+    /// or by `init` with `InitOptions::probe_terminal`, and the answer is kept from then on.
+    ///
+    /// Asking takes the input lock and drops any keys pressed while waiting for the reply.
+    /// A terminal that doesn't answer within 200ms, or isn't asked at all (see
+    /// `query_cursor_position`), is taken to support nothing.
+    pub fn terminal_info(&self) -> TerminalInfo {
+        if let Some(ref info) = *self.terminal_info.lock().unwrap() {
+            return info.clone();
+        }
+
+        let _input_lock = self.input_lock.lock();
+        let _output_lock = self.output_lock.lock();
+
+        let reply = self.tty.query(&probe::request(), Duration::from_millis(200), probe::done)
+            .unwrap_or_default();
+        let info = probe::parse(&reply);
+        self.synchronized_output.store(info.synchronized_output, Ordering::SeqCst);
+        *self.terminal_info.lock().unwrap() = Some(info.clone());
+        info
+    }
+
+    /// Choose how `draw_image` shows images, rather than asking the terminal.
+    pub fn set_image_protocol(&self, protocol: ImageProtocol) {
+        *self.image_protocol.lock().unwrap() = Some(protocol);
+    }
+
+    /// Start recording every presented frame to an asciicast v2 file at `path`, which can be
+    /// played back with asciinema. Any recording already in progress is stopped first.
+    ///
+    /// Each frame is recorded as a full repaint of the screen rather than the exact bytes
+    /// termbox sends. The recording is finished by `stop_recording`, or by `shutdown` and
+    /// dropping the RustBox.
+    pub fn start_recording<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let _lock = self.output_lock.lock();
+
+        let recorder = record::Recorder::create(path.as_ref(), self.raw_width(), self.raw_height())?;
+        match self.recorder.lock().unwrap().replace(recorder) {
+            Some(old) => old.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Stop recording and flush the file, reporting any error that happened while writing it.
+    /// Does nothing if no recording is in progress.
+    pub fn stop_recording(&self) -> io::Result<()> {
+        let _lock = self.output_lock.lock();
+
+        match self.recorder.lock().unwrap().take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Show the terminal's cursor at `(x, y)` once the next `present` runs.
+    pub fn set_cursor(&self, x: isize, y: isize) {
+        let _lock = self.output_lock.lock();
+
+        self.set_raw_cursor(x as c_int, y as c_int);
+    }
+
+    /// Change the shape of the terminal's cursor. The terminal's default shape is restored when
+    /// RustBox is dropped.
+    ///
+    /// Terminals that don't support DECSCUSR ignore this.
+    pub fn set_cursor_style(&self, style: CursorStyle, blinking: bool) -> io::Result<()> {
+        let _lock = self.output_lock.lock();
+
+        let n = match (style, blinking) {
+            (CursorStyle::Block, true) => 1,
+            (CursorStyle::Block, false) => 2,
+            (CursorStyle::Underline, true) => 3,
+            (CursorStyle::Underline, false) => 4,
+            (CursorStyle::Bar, true) => 5,
+            (CursorStyle::Bar, false) => 6,
+        };
+        self.cursor_style_changed.store(true, Ordering::SeqCst);
+        self.tty.write(format!("\x1b[{} q", n).as_bytes())
+    }
+
+    /// Save the terminal's cursor position and attributes (DECSC), for `restore_cursor` to put
+    /// back, so that the application can write to the terminal itself and then return the
+    /// cursor to where RustBox left it. This is mostly of use with `ScreenMode::Inline`, to
+    /// write to the rows outside the viewport, which RustBox leaves alone.
+    ///
+    /// ```no_run
+    /// use std::io::Write;
+    /// use rustbox::{InitOptions, RustBox, ScreenMode};
+    ///
+    /// let rb = RustBox::init(InitOptions { screen: ScreenMode::Inline(3), ..Default::default() }).unwrap();
+    /// let viewport = rb.viewport();
+    /// if viewport.y > 0 {
+    ///     rb.save_cursor().unwrap();
+    ///     // The row just above the viewport.
+    ///     print!("\x1b[{};1H\x1b[2KDownloading...", viewport.y);
+    ///     std::io::stdout().flush().unwrap();
+    ///     rb.restore_cursor().unwrap();
+    /// }
+    /// ```
+    ///
+    /// Terminals only keep one saved cursor. Until it is restored, `present` leaves it alone,
+    /// but restarting termbox, as `invalidate` and `refresh_size` do, overwrites it.
+    pub fn save_cursor(&self) -> io::Result<()> {
+        let _lock = self.output_lock.lock();
+
+        self.cursor_saved.store(true, Ordering::SeqCst);
+        self.tty.write(b"\x1b7")
+    }
+
+    /// Put the cursor back as `save_cursor` saved it (DECRC).
+    pub fn restore_cursor(&self) -> io::Result<()> {
+        let _lock = self.output_lock.lock();
+
+        self.cursor_saved.store(false, Ordering::SeqCst);
+        self.tty.write(b"\x1b8")
+    }
+
+    // What to write before and after moving the cursor behind termbox's back in `present`, to
+    // put it back at `shown`, where termbox left it, if it is showing. Usually that's DECSC and
+    // DECRC, but they would lose the position saved by `save_cursor`, so while there is one the
+    // cursor is moved back directly instead.
+    fn cursor_return(&self, shown: Option<(usize, usize)>) -> (String, String) {
+        if !self.cursor_saved.load(Ordering::SeqCst) {
+            return ("\x1b7".to_string(), "\x1b8".to_string());
+        }
+        match shown {
+            Some((x, y)) => (String::new(), format!("\x1b[{};{}H", y + 1, x + 1)),
+            None => (String::new(), String::new()),
+        }
+    }
+
+    /// Ask the terminal where its cursor actually is, using a DSR (CSI 6n) query. The result is
+    /// zero-based, like `set_cursor`.
+    ///
+    /// The reply is read straight from the terminal, so any keys pressed while waiting for it
+    /// are dropped. Fails with `io::ErrorKind::TimedOut` if the terminal doesn't answer within
+    /// `timeout`.
+    ///
+    /// Like every query, this is only best-effort. With `TERM=dumb` nothing is sent, and it
+    /// fails with `io::ErrorKind::Unsupported`. Once queries have gone unanswered for half a
+    /// second in all, RustBox stops asking, so that a terminal (or script) that never answers
+    /// can't hold the application up for long, and it fails with `TimedOut` straight away.
+    pub fn query_cursor_position(&self, timeout: Duration) -> io::Result<(usize, usize)> {
+        let _input_lock = self.input_lock.lock();
+        let _output_lock = self.output_lock.lock();
+
+        let reply = self.tty.query(b"\x1b[6n", timeout, |r| parse_cursor_report(r).is_some())?;
+        Ok(parse_cursor_report(&reply).unwrap())
+    }
+
+    /// The size of the terminal's window in pixels, as `(width, height)`, for scaling images
+    /// to fit. This comes from the kernel where the terminal tells it; otherwise the terminal
+    /// is asked with CSI 14t, which takes the input lock and drops any keys pressed while
+    /// waiting for the reply. Fails with `io::ErrorKind::TimedOut` if the terminal doesn't
+    /// answer in time, as many don't.
+    pub fn pixel_size(&self) -> io::Result<(usize, usize)> {
+        match self.tty.pixel_size()? {
+            (w, h) if w > 0 && h > 0 => Ok((w, h)),
+            _ => self.query_window_report(b"\x1b[14t", 4),
+        }
+    }
+
+    /// The size of a cell in pixels, as `(width, height)`. Like `pixel_size`, this comes from
+    /// the kernel if it knows, and otherwise from asking the terminal, with CSI 16t. The
+    /// answer is kept for `draw_image` to scale images with.
+    pub fn cell_size(&self) -> io::Result<(usize, usize)> {
+        let (w, h) = self.tty.pixel_size()?;
+        if w > 0 && h > 0 {
+            let (cols, rows) = (self.width().max(1), self.height().max(1));
+            return Ok((cmp::max(w / cols, 1), cmp::max(h / rows, 1)));
+        }
+        let size = self.query_window_report(b"\x1b[16t", 6)?;
+        *self.cell_size.lock().unwrap() = Some(size);
+        Ok(size)
+    }
+
+    // Send an XTWINOPS query and wait for its `kind` of reply.
+    fn query_window_report(&self, request: &[u8], kind: usize) -> io::Result<(usize, usize)> {
+        let _input_lock = self.input_lock.lock();
+        let _output_lock = self.output_lock.lock();
+
+        let reply = self.tty.query(request, Duration::from_millis(200),
+                                   |r| parse_window_report(r, kind).is_some())?;
+        Ok(parse_window_report(&reply, kind).unwrap())
+    }
+
+    /// Ring the terminal's bell.
+    pub fn bell(&self) -> io::Result<()> {
+        let _lock = self.output_lock.lock();
+
+        self.tty.write(b"\x07")
+    }
+
+    /// Flash the screen by switching the terminal to reverse video (DECSCNM) for `duration`.
+    ///
+    /// Blocks the calling thread, and any other output, until the flash is over.
+    pub fn visual_bell(&self, duration: Duration) -> io::Result<()> {
+        let _lock = self.output_lock.lock();
+
+        self.tty.write(b"\x1b[?5h")?;
+        thread::sleep(duration);
+        self.tty.write(b"\x1b[?5l")
+    }
+
+    /// Copy `text` to the system clipboard using OSC 52. This works over SSH, but many
+    /// terminals disable it or limit the size of the payload.
+    pub fn set_clipboard(&self, text: &str) -> io::Result<()> {
+        let _lock = self.output_lock.lock();
+
+        let mut seq = String::from("\x1b]52;c;");
+        seq.push_str(&base64::encode(text.as_bytes()));
+        seq.push('\x07');
+        // Some terminals and multiplexers choke on very long single writes.
+        for chunk in seq.as_bytes().chunks(4096) {
+            self.tty.write(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Ask the terminal for the contents of the system clipboard using OSC 52.
+    ///
+    /// Most terminals refuse to answer unless the user has allowed it, in which case this
+    /// fails with `io::ErrorKind::TimedOut` after `timeout`. As with `query_cursor_position`,
+    /// keys pressed while waiting are dropped.
+    pub fn request_clipboard(&self, timeout: Duration) -> io::Result<String> {
+        let _input_lock = self.input_lock.lock();
+        let _output_lock = self.output_lock.lock();
+
+        let reply = self.tty.query(b"\x1b]52;c;?\x07", timeout, |r| parse_osc_reply(r, b"52;").is_some())?;
+        let payload = parse_osc_reply(&reply, b"52;").unwrap();
+        // The payload is `<selection>;<base64 data>`.
+        let data = payload.splitn(2, |&b| b == b';').nth(1).unwrap_or(&[]);
+        let decoded = std::str::from_utf8(data).ok().and_then(base64::decode);
+        match decoded {
+            Some(bytes) => Ok(String::from_utf8_lossy(&bytes).into_owned()),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, "malformed clipboard reply")),
+        }
+    }
+
+    /// Ask the terminal for its default foreground and background colors, using OSC 10 and
+    /// 11 queries, so that colors can be picked which are readable against what the user
+    /// actually has.
+    ///
+    /// As with `query_cursor_position`, keys pressed while waiting are dropped. Fails with
+    /// `io::ErrorKind::Unsupported` if the terminal answers without reporting the colors, and
+    /// with `io::ErrorKind::TimedOut` if it doesn't answer within `timeout`.
+    pub fn query_default_colors(&self, timeout: Duration) -> io::Result<DefaultColors> {
+        match self.query_colors(&["10", "11"], timeout)?[..] {
+            [Some(foreground), Some(background)] => Ok(DefaultColors { foreground, background }),
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "terminal did not report its colors")),
+        }
+    }
+
+    /// Whether the terminal has a light or dark background, so that a theme to suit it can be
+    /// picked with `Theme::for_background`.
+    ///
+    /// The first call asks the terminal for its background color, as `query_default_colors`
+    /// does, so keys pressed meanwhile are dropped; it's best called straight after `init`.
+    /// If the terminal doesn't say, `COLORFGBG` is used if it's set. The answer is remembered.
+    pub fn background_kind(&self) -> BackgroundKind {
+        if let Some(kind) = *self.background_kind.lock().unwrap() {
+            return kind;
+        }
+        let kind = match self.query_colors(&["11"], Duration::from_millis(200)).ok().as_deref() {
+            Some(&[Some(background)]) => BackgroundKind::from_rgb(background),
+            _ => match env::var("COLORFGBG") {
+                Ok(value) => BackgroundKind::from_colorfgbg(&value),
+                Err(_) => BackgroundKind::Unknown,
+            },
+        };
+        *self.background_kind.lock().unwrap() = Some(kind);
+        kind
+    }
+
+    /// Ask the terminal what color number `index` of its palette looks like, using an OSC 4
+    /// query. Fails in the same way as `query_default_colors`.
+    pub fn query_palette_color(&self, index: u8, timeout: Duration) -> io::Result<(u8, u8, u8)> {
+        match self.query_colors(&[&format!("4;{}", index)], timeout)?[..] {
+            [Some(rgb)] => Ok(rgb),
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "terminal did not report the color")),
+        }
+    }
+
+    // Ask for each of the colors named by `params`, e.g. `11` for the background, followed by
+    // a device attributes query, which every terminal answers, so that one which ignores color
+    // queries doesn't leave us waiting. Colors the terminal didn't report are `None`.
+    fn query_colors(&self, params: &[&str], timeout: Duration) -> io::Result<Vec<Option<(u8, u8, u8)>>> {
+        let _input_lock = self.input_lock.lock();
+        let _output_lock = self.output_lock.lock();
+
+        let mut request = Vec::new();
+        for param in params {
+            request.extend_from_slice(format!("\x1b]{};?\x07", param).as_bytes());
+        }
+        request.extend_from_slice(b"\x1b[c");
+        let reply = self.tty.query(&request, timeout, probe::done)?;
+        Ok(params.iter()
+           .map(|param| parse_osc_reply(&reply, format!("{};", param).as_bytes()).and_then(parse_color_spec))
+           .collect())
+    }
+
+    /// Set the terminal's window (and tab) title. The original title is saved the first time
+    /// this is called and restored when RustBox is dropped, on terminals that support it.
+    ///
+    /// Control characters in `title` are dropped.
+    pub fn set_title(&self, title: &str) -> io::Result<()> {
+        let _lock = self.output_lock.lock();
+
+        if !self.title_saved.swap(true, Ordering::SeqCst) {
+            // XTWINOPS: push the icon and window titles onto the terminal's title stack.
+            self.tty.write(b"\x1b[22;0t")?;
+        }
+        let title: String = title.chars().filter(|c| !c.is_control()).collect();
+        self.tty.write(format!("\x1b]0;{}\x07", title).as_bytes())
+    }
+
+    /// Hide the terminal's cursor (the default). `set_cursor` shows it again.
+    pub fn hide_cursor(&self) {
+        let _lock = self.output_lock.lock();
+
+        self.set_raw_cursor(termbox::TB_HIDE_CURSOR, termbox::TB_HIDE_CURSOR);
+    }
+
+    // Must be called with `output_lock` held.
+    fn set_raw_cursor(&self, x: c_int, y: c_int) {
+        *self.cursor.lock().unwrap() = (x, y);
+        if !self.termbox_stopped() {
+            unsafe { termbox::tb_set_cursor(x, y) }
+        }
+    }
+
+    pub unsafe fn change_cell(&self, x: usize, y: usize, ch: u32, fg: u16, bg: u16) {
+        if !self.termbox_stopped() {
+            termbox::tb_change_cell(x as c_int, y as c_int, ch, fg, bg)
+        }
+    }
+
+    /// Choose what `print` does with tabs, newlines and other control characters. See
+    /// `ControlPolicy`.
+    pub fn set_control_policy(&self, policy: ControlPolicy) {
+        let _lock = self.output_lock.lock();
+
+        *self.control_policy.lock().unwrap() = policy;
+    }
+
+    pub fn control_policy(&self) -> ControlPolicy {
+        let _lock = self.output_lock.lock();
+
+        *self.control_policy.lock().unwrap()
+    }
+
+    /// Print `s` starting at `(x, y)`, a character to a cell. Tabs, newlines and other control
+    /// characters are dealt with as `set_control_policy` says. Anything that falls outside the
+    /// screen is clipped.
+    pub fn print(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+        let _lock = self.output_lock.lock();
+
+        let (fg_int, bg_int) = attributes(self.output_mode(), sty, fg, bg);
+        // Clip here rather than handing termbox coordinates that may not fit in a c_int.
+        let (width, height) = (self.raw_width(), self.raw_height());
+        for (dx, dy, ch) in self.control_policy.lock().unwrap().place(s) {
+            let (cx, cy) = (x.saturating_add(dx), y.saturating_add(dy));
+            if cx < width && cy < height {
+                unsafe {
+                    self.change_cell(cx, cy, ch as u32, fg_int, bg_int);
+                }
+            }
+        }
+    }
+
+    pub fn print_char(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, ch: char) {
+        self.set_cell(x, y, Cell { ch, fg, bg, style: sty });
+    }
+
+    /// Print `s` at `(x, y)` with the attributes the current theme gives `role`. See
+    /// `set_theme`.
+    pub fn print_themed(&self, x: usize, y: usize, role: Role, s: &str) {
+        let attr = self.theme().get(role);
+        self.print(x, y, attr.style, attr.fg, attr.bg, s);
+    }
+
+    /// Use `theme` for `print_themed`. Its `Background` role becomes the clear attributes,
+    /// as though passed to `set_clear_attributes`, so call `clear` afterwards to repaint the
+    /// whole screen in it.
+    pub fn set_theme(&self, theme: Theme) {
+        *self.theme.lock().unwrap() = theme;
+        let background = theme.get(Role::Background);
+        self.set_clear_attributes(background.fg, background.bg, background.style);
+    }
+
+    /// The theme used by `print_themed`, which until `set_theme` is called is
+    /// `Theme::default()`.
+    pub fn theme(&self) -> Theme {
+        *self.theme.lock().unwrap()
+    }
+
+    /// Print each span of `text` with its own attributes, starting at `(x, y)`. Anything that
+    /// doesn't fit on the line is clipped. Returns the number of columns the text takes up.
+    pub fn print_text(&self, x: usize, y: usize, text: &Text) -> usize {
+        let _lock = self.output_lock.lock();
+
+        let (width, height) = (self.raw_width(), self.raw_height());
+        let mode = self.output_mode();
+        if y < height {
+            for (dx, ch, attr) in text.columns() {
+                if x + dx >= width {
+                    break;
+                }
+                let (fg_int, bg_int) = attributes(mode, attr.style, attr.fg, attr.bg);
+                unsafe {
+                    self.change_cell(x + dx, y, ch as u32, fg_int, bg_int);
+                }
+            }
+        }
+        text.width()
+    }
+
+    /// Print `s` inside `rect`, wrapped at word boundaries and with each line aligned as given.
+    /// Wide characters take up two cells. If the text needs more lines than `rect` has, the
+    /// rest is cut off, and with `ellipsis` set the last line ends with `…` to show it.
+    ///
+    /// Returns the number of lines used.
+    #[allow(clippy::too_many_arguments)]
+    pub fn print_wrapped(&self, rect: Rect, align: Alignment, ellipsis: bool, sty: Style,
+                         fg: Color, bg: Color, s: &str) -> usize {
+        let lines = text::layout(s, rect.w, rect.h, align, ellipsis);
+
+        let _lock = self.output_lock.lock();
+
+        let (width, height) = (self.raw_width(), self.raw_height());
+        let (fg_int, bg_int) = attributes(self.output_mode(), sty, fg, bg);
+        for (i, &(offset, ref line)) in lines.iter().enumerate() {
+            let y = rect.y + i;
+            for (dx, ch) in text::columns(line) {
+                let x = rect.x + offset + dx;
+                if x < width && y < height {
+                    unsafe {
+                        self.change_cell(x, y, ch as u32, fg_int, bg_int);
+                    }
+                }
+            }
+        }
+        lines.len()
+    }
+
+    /// Add a layer called `name`, drawn over the back buffer by every `present` until it is
+    /// removed. Layers with a higher `z` are drawn over those with a lower one, and layers with
+    /// the same `z` in the order they were added. If there already is a layer called `name`, it
+    /// is just moved to `z`.
+    ///
+    /// Whatever is drawn on a layer covers the back buffer without changing it, so a popup can
+    /// be shown on its own layer and taken away with `remove_layer`, leaving the screen as it
+    /// was underneath. Layers are as big as the screen, and follow it when it is resized.
+    ///
+    /// ```no_run
+    /// use rustbox::{Color, RustBox, RB_BOLD};
+    ///
+    /// let rb = RustBox::init(Default::default()).unwrap();
+    /// rb.add_layer("dialog", 10);
+    /// rb.with_layer("dialog", |layer| {
+    ///     layer.print(2, 2, RB_BOLD, Color::White, Color::Red, " Really quit? ");
+    /// });
+    /// rb.present().unwrap();
+    /// rb.remove_layer("dialog");
+    /// rb.present().unwrap();
+    /// ```
+    pub fn add_layer(&self, name: &str, z: i32) {
+        let _lock = self.output_lock.lock();
+
+        self.layers.lock().unwrap().add(name, z, self.raw_width(), self.raw_height());
+    }
+
+    /// Remove the layer called `name`, returning whether there was one.
+    pub fn remove_layer(&self, name: &str) -> bool {
+        let _lock = self.output_lock.lock();
+
+        self.layers.lock().unwrap().remove(name)
+    }
+
+    /// Run `func` on the layer called `name`, returning what it returns, or `None` if there is
+    /// no such layer.
+    ///
+    /// `func` runs with the output lock held, so it must not call back into this `RustBox`.
+    pub fn with_layer<F, R>(&self, name: &str, func: F) -> Option<R>
+        where F: FnOnce(&mut Layer) -> R
+    {
+        let _lock = self.output_lock.lock();
+
+        let (width, height) = (self.raw_width(), self.raw_height());
+        let policy = *self.control_policy.lock().unwrap();
+        self.layers.lock().unwrap().get_mut(name, width, height).map(|layer| {
+            layer.set_control_policy(policy);
+            func(layer)
+        })
+    }
+
+    /// A window onto `rect`, for drawing in coordinates relative to its top left corner. See
+    /// `Window`.
+    pub fn window(&self, rect: Rect) -> Window<'_> {
+        Window::new(self, rect)
+    }
+
+    /// Fill every cell of `rect` with `cell`. The parts of `rect` that lie off screen are
+    /// ignored.
+    pub fn fill_rect(&self, rect: Rect, cell: Cell) {
+        let _lock = self.output_lock.lock();
+
+        let rect = rect.intersection(Rect::new(0, 0, self.raw_width(), self.raw_height()));
+        let (fg_int, bg_int) = attributes(self.output_mode(), cell.style, cell.fg, cell.bg);
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                unsafe {
+                    self.change_cell(x, y, cell.ch as u32, fg_int, bg_int);
+                }
+            }
+        }
+    }
+
+    /// Move everything in `rect` up by `n` rows, clearing the rows left at the bottom as
+    /// `clear` would. Anything moved above the top of `rect` is lost, as is any image that
+    /// overlaps it.
+    pub fn scroll_up(&self, rect: Rect, n: usize) {
+        self.scroll(rect, n, true);
+    }
+
+    /// Like `scroll_up`, but moves things down, clearing the rows left at the top.
+    pub fn scroll_down(&self, rect: Rect, n: usize) {
+        self.scroll(rect, n, false);
+    }
+
+    fn scroll(&self, rect: Rect, n: usize, up: bool) {
+        let _lock = self.output_lock.lock();
+
+        let width = self.raw_width();
+        let rect = rect.intersection(Rect::new(0, 0, width, self.raw_height()));
+        self.images.lock().unwrap().retain(|image| !image.overlaps(rect.x, rect.y, rect.w, rect.h));
+
+        let old = self.raw_cells().to_vec();
+        let (fg_int, bg_int) = self.clear_raw_attributes();
+        for dy in 0..rect.h {
+            let from = buffer::scrolled_from(dy, rect.h, n, up);
+            for x in rect.x..rect.right() {
+                let raw = match from {
+                    Some(sy) => old[(rect.y + sy) * width + x],
+                    None => RawCell { ch: ' ' as u32, fg: fg_int, bg: bg_int },
+                };
+                unsafe {
+                    self.change_cell(x, rect.y + dy, raw.ch, raw.fg, raw.bg);
+                }
+            }
+        }
+    }
+
+    /// Change the colors and style of every cell in `rect`, keeping the characters. This is
+    /// what selection highlighting and focus indication want.
+    pub fn paint_attrs(&self, rect: Rect, fg: Color, bg: Color, sty: Style) {
+        let _lock = self.output_lock.lock();
+
+        let width = self.raw_width();
+        let rect = rect.intersection(Rect::new(0, 0, width, self.raw_height()));
+        let (fg_int, bg_int) = attributes(self.output_mode(), sty, fg, bg);
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                let ch = self.raw_cells()[y * width + x].ch;
+                unsafe {
+                    self.change_cell(x, y, ch, fg_int, bg_int);
+                }
+            }
+        }
+    }
+
+    /// Draw a horizontal line `len` cells long, starting at `(x, y)` and going right. Where it
+    /// meets lines of the same weight already on screen, they are joined up.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_hline(&self, x: usize, y: usize, len: usize, border: BorderStyle, sty: Style,
+                      fg: Color, bg: Color) {
+        self.draw_border(&border::hline(x, y, len), border, sty, fg, bg);
+    }
+
+    /// Like `draw_hline`, but going down from `(x, y)`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_vline(&self, x: usize, y: usize, len: usize, border: BorderStyle, sty: Style,
+                      fg: Color, bg: Color) {
+        self.draw_border(&border::vline(x, y, len), border, sty, fg, bg);
+    }
+
+    /// Draw the outline of the `w` by `h` box whose top left corner is at `(x, y)`, leaving the
+    /// inside alone. As with `draw_hline`, it joins up with lines already on screen.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_box(&self, x: usize, y: usize, w: usize, h: usize, border: BorderStyle,
+                    sty: Style, fg: Color, bg: Color) {
+        self.draw_border(&border::rect(x, y, w, h), border, sty, fg, bg);
+    }
+
+    fn draw_border(&self, cells: &[(usize, usize, u8)], border: BorderStyle, sty: Style,
+                   fg: Color, bg: Color) {
+        let _lock = self.output_lock.lock();
+
+        let (width, height) = (self.raw_width(), self.raw_height());
+        let (fg_int, bg_int) = attributes(self.output_mode(), sty, fg, bg);
+        for &(x, y, segments) in cells {
+            if x >= width || y >= height {
+                continue;
+            }
+            let existing = char::from_u32(self.raw_cells()[y * width + x].ch).unwrap_or(' ');
+            unsafe {
+                self.change_cell(x, y, border::join(existing, segments, border) as u32, fg_int,
+                                 bg_int);
+            }
+        }
+    }
+
+    /// Returns the cell at `(x, y)` in the back buffer, or `None` if it is off screen.
+    pub fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        let _lock = self.output_lock.lock();
+
+        if x < self.raw_width() && y < self.raw_height() {
+            Some(cell_from_raw(self.output_mode(), &self.raw_cells()[y * self.raw_width() + x]))
+        } else {
+            None
+        }
+    }
+
+    /// Replace the cell at `(x, y)` in the back buffer. Off screen positions are ignored; use
+    /// `try_set_cell` to find out about them.
+    pub fn set_cell(&self, x: usize, y: usize, cell: Cell) {
+        let _ = self.try_set_cell(x, y, cell);
+    }
+
+    pub fn try_set_cell(&self, x: usize, y: usize, cell: Cell) -> Result<(), CellError> {
+        let _lock = self.output_lock.lock();
+
+        if x >= self.raw_width() || y >= self.raw_height() {
+            return Err(CellError::OutOfBounds(x, y));
+        }
+        let (fg_int, bg_int) = attributes(self.output_mode(), cell.style, cell.fg, cell.bg);
+        unsafe {
+            self.change_cell(x, y, cell.ch as u32, fg_int, bg_int);
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the back buffer, i.e. everything drawn since the last `clear`.
+    pub fn buffer(&self) -> Buffer {
+        let _lock = self.output_lock.lock();
+
+        self.raw_buffer()
+    }
+
+    /// The text currently in the back buffer, one line per row. See `Buffer::text`.
+    pub fn snapshot(&self) -> String {
+        self.buffer().text()
+    }
+
+    /// The back buffer with color and style markup. See `Buffer::styled_text`.
+    pub fn snapshot_styled(&self) -> String {
+        self.buffer().styled_text()
+    }
+
+    /// The back buffer as a standalone stream of text and escape sequences. See
+    /// `export::ansi`.
+    pub fn render_to_ansi(&self) -> Vec<u8> {
+        export::ansi(&self.buffer())
+    }
+
+    /// Copy `buffer` into the back buffer with its top left corner at `(x, y)`. Anything that
+    /// doesn't fit on screen is clipped.
+    pub fn blit(&self, x: usize, y: usize, buffer: &Buffer) {
+        let _lock = self.output_lock.lock();
+
+        let mode = self.output_mode();
+        let raw: Vec<RawCell> = buffer.cells().iter().map(|cell| {
+            let (fg_int, bg_int) = attributes(mode, cell.style, cell.fg, cell.bg);
+            RawCell { ch: cell.ch as u32, fg: fg_int, bg: bg_int }
+        }).collect();
+        if self.termbox_stopped() {
+            return;
+        }
+        unsafe {
+            termbox::tb_blit(x as c_int, y as c_int, buffer.width() as c_int,
+                             buffer.height() as c_int, raw.as_ptr());
+        }
+    }
+
+    /// Like `blit`, but only copies the `src_rect` part of `buffer`.
+    pub fn blit_region(&self, x: usize, y: usize, buffer: &Buffer, src_rect: Rect) {
+        let src_rect = src_rect.intersection(Rect::new(0, 0, buffer.width(), buffer.height()));
+        let mut part = Buffer::new(src_rect.w, src_rect.h);
+        part.blit(0, 0, buffer, src_rect);
+        self.blit(x, y, &part);
+    }
+
+    // Must be called with `output_lock` held.
+    fn output_mode(&self) -> OutputMode {
+        *self.output_mode.lock().unwrap()
+    }
+
+    // Whether termbox is shut down, so that it mustn't be called. See `restart`.
+    fn termbox_stopped(&self) -> bool {
+        self.stopped.lock().unwrap().is_some()
+    }
+
+    // The following must only be called with `output_lock` held. While termbox is shut down
+    // the screen is taken to be empty.
+
+    fn raw_width(&self) -> usize {
+        if self.termbox_stopped() {
+            return 0;
+        }
+        unsafe { termbox::tb_width() as usize }
+    }
+
+    fn raw_height(&self) -> usize {
+        if self.termbox_stopped() {
+            return 0;
+        }
+        unsafe { termbox::tb_height() as usize }
+    }
+
+    fn raw_buffer(&self) -> Buffer {
+        let mode = self.output_mode();
+        let cells = self.raw_cells().iter().map(|raw| cell_from_raw(mode, raw)).collect();
+        Buffer::from_cells(self.raw_width(), self.raw_height(), cells)
+    }
+
+    fn raw_cells(&self) -> &[RawCell] {
+        if self.termbox_stopped() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(termbox::tb_cell_buffer(), self.raw_width() * self.raw_height()) }
+    }
+
+    pub fn poll_event(&self, raw: bool) -> EventResult {
+        let _lock = self.input_lock.lock();
+        let (rc, ev) = match self.next_played_event(None) {
+            Some(played) => played,
+            None if self.termbox_stopped() => (-1, NIL_RAW_EVENT),
+            None => {
+                let mut ev = NIL_RAW_EVENT;
+                let rc = unsafe {
+                    termbox::tb_poll_event(&mut ev)
+                };
+                (rc, ev)
+            }
+        };
+        self.record_input(rc, &ev);
+        self.debug_event(rc, &ev);
+        self.translate_event(rc, &ev, raw)
+    }
+
+    pub fn peek_event(&self, timeout: Duration, raw: bool) -> EventResult {
+        let _lock = self.input_lock.lock();
+        self.peek_event_locked(timeout, raw)
+    }
+
+    /// Like `peek_event`, but waits until `deadline` rather than for a length of time, returning
+    /// `Event::NoEvent` if it passes first, or has already passed. A loop drawing frames at a
+    /// steady rate can wait until the next frame is due without its timing drifting as the
+    /// time spent on each frame varies.
+    ///
+    /// ```no_run
+    /// use std::time::{Duration, Instant};
+    /// use rustbox::{Event, RustBox};
+    ///
+    /// let rb = RustBox::init(Default::default()).unwrap();
+    /// let frame = Duration::from_millis(1000 / 30);
+    /// let mut next_frame = Instant::now() + frame;
+    /// loop {
+    ///     match rb.poll_event_deadline(next_frame, false).unwrap() {
+    ///         Event::NoEvent => {
+    ///             // Draw the next frame.
+    ///             rb.present().unwrap();
+    ///             next_frame += frame;
+    ///         },
+    ///         Event::KeyEvent(_) => break,
+    ///         _ => {},
+    ///     }
+    /// }
+    /// ```
+    pub fn poll_event_deadline(&self, deadline: Instant, raw: bool) -> EventResult {
+        let _lock = self.input_lock.lock();
+        loop {
+            let now = Instant::now();
+            let timeout = if deadline > now { deadline - now } else { Duration::from_secs(0) };
+            // termbox waits in whole milliseconds, rounding down, so it can return just before
+            // the deadline.
+            match self.peek_event_locked(timeout, raw)? {
+                Event::NoEvent if Instant::now() < deadline => continue,
+                event => return Ok(event),
+            }
+        }
+    }
+
+    /// Returns all of the events that have already arrived, without waiting for more, or an
+    /// empty `Vec` if there are none. After a burst of input, such as a large paste or fast
+    /// mouse motion, this lets the application deal with all of it and then draw one frame,
+    /// rather than drawing after every event.
+    ///
+    /// If the terminal was resized more than once, only the last `ResizeEvent` is kept, since
+    /// the sizes before it no longer matter.
+    ///
+    /// An error stops the draining. It is only returned if no events came before it, so none
+    /// are lost; otherwise the events are returned, and the next call is likely to run into
+    /// the error again.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use rustbox::{Event, RustBox};
+    ///
+    /// let rb = RustBox::init(Default::default()).unwrap();
+    /// loop {
+    ///     let mut events = vec![rb.poll_event(false).unwrap()];
+    ///     events.extend(rb.drain_events(false).unwrap());
+    ///     for event in events {
+    ///         // Update the application's state.
+    ///     }
+    ///     // Draw, then present once.
+    ///     rb.present().unwrap();
+    /// }
+    /// ```
+    pub fn drain_events(&self, raw: bool) -> Result<Vec<Event>, EventError> {
+        let _lock = self.input_lock.lock();
+        // Before looking for events, so that a resize after this wakes up `resize_fd` again.
+        self.resize_pipe.empty();
+        let mut events = Vec::new();
+        loop {
+            match self.peek_event_locked(Duration::from_secs(0), raw) {
+                Ok(Event::NoEvent) => return Ok(events),
+                Ok(event @ Event::ResizeEvent(..)) => {
+                    events.retain(|e| !matches!(*e, Event::ResizeEvent(..)));
+                    events.push(event);
+                },
+                Ok(event) => events.push(event),
+                Err(_) if !events.is_empty() => return Ok(events),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// A descriptor that becomes readable when the terminal is resized, for waiting on along
+    /// with the terminal itself (see `AsRawFd`). It stays readable until `drain_events` takes
+    /// the `ResizeEvent`. Don't read from it or close it.
+    ///
+    /// termbox hears of resizes through `SIGWINCH`, which only reaches it by way of a pipe of
+    /// its own, so RustBox passes the signal through a pipe of its own too.
+    pub fn resize_fd(&self) -> RawFd {
+        self.resize_pipe.as_raw_fd()
+    }
+
+    // Must be called with `input_lock` held.
+    fn peek_event_locked(&self, timeout: Duration, raw: bool) -> EventResult {
+        let (rc, ev) = match self.next_played_event(Some(timeout)) {
+            Some(played) => played,
+            None if self.termbox_stopped() => (-1, NIL_RAW_EVENT),
+            None => {
+                let mut ev = NIL_RAW_EVENT;
+                let rc = unsafe {
+                    termbox::tb_peek_event(&mut ev, (timeout.as_secs() * 1000 + timeout.subsec_nanos() as u64 / 1000000) as c_int)
+                };
+                (rc, ev)
+            }
+        };
+        self.record_input(rc, &ev);
+        self.debug_event(rc, &ev);
+        self.translate_event(rc, &ev, raw)
+    }
+
+    /// Choose what Ctrl-C and Ctrl-\\ do. By default they are `SignalKeys::Keys`, which leaves
+    /// it to the application to notice them among other keys; with `SignalKeys::Events` they
+    /// come as events of their own, which are hard to overlook in a `match`. Raw events are
+    /// left alone.
+    ///
+    /// With `InitOptions::keep_signals` set, the terminal sends the signals itself and the keys
+    /// never arrive, so this makes no difference.
+    pub fn set_signal_keys(&self, keys: SignalKeys) {
+        let _lock = self.input_lock.lock();
+
+        *self.signal_keys.lock().unwrap() = keys;
+    }
+
+    pub fn signal_keys(&self) -> SignalKeys {
+        let _lock = self.input_lock.lock();
+
+        *self.signal_keys.lock().unwrap()
+    }
+
+    // Unpack an event from termbox, giving mouse events the form asked for with
+    // `set_mouse_tracking` and applying `set_signal_keys`. Must be called with `input_lock`
+    // held.
+    fn translate_event(&self, rc: c_int, ev: &RawEvent, raw: bool) -> EventResult {
+        let event = match unpack_event(rc, ev, raw)? {
+            Event::MouseEvent(button, x, y) if *self.mouse_tracking.lock().unwrap() != MouseTracking::Clicks =>
+                Event::Mouse(mouse::kind(button, ev.emod), button, x, y),
+            event => event,
+        };
+        Ok(self.signal_keys.lock().unwrap().apply(event))
+    }
+
+    /// Choose which mouse events are reported. See `MouseTracking`.
+    pub fn set_mouse_tracking(&self, tracking: MouseTracking) -> io::Result<()> {
+        let _input_lock = self.input_lock.lock();
+        let _output_lock = self.output_lock.lock();
+
+        let was = mem::replace(&mut *self.mouse_tracking.lock().unwrap(), tracking);
+        if was == MouseTracking::Motion && tracking != MouseTracking::Motion {
+            return self.tty.write(b"\x1b[?1003l");
+        }
+        self.apply_mouse_tracking()
+    }
+
+    pub fn mouse_tracking(&self) -> MouseTracking {
+        let _lock = self.input_lock.lock();
+
+        *self.mouse_tracking.lock().unwrap()
+    }
+
+    // termbox asks for button presses and drags whenever the mouse is on, so all there is to
+    // do is to ask for all motion on top for `MouseTracking::Motion`, and to stop asking once
+    // the mouse is off. Must be called with `output_lock` held.
+    fn apply_mouse_tracking(&self) -> io::Result<()> {
+        if *self.mouse_tracking.lock().unwrap() != MouseTracking::Motion || self.termbox_stopped() {
+            return Ok(());
+        }
+        let mode = unsafe { termbox::tb_select_input_mode(InputMode::Current as c_int) };
+        if mode & termbox::TB_INPUT_MOUSE != 0 {
+            self.tty.write(b"\x1b[?1003h")
+        } else {
+            self.tty.write(b"\x1b[?1003l")
+        }
+    }
+
+    // Add an event to the debug overlay, if it is on.
+    fn debug_event(&self, rc: c_int, ev: &RawEvent) {
+        if let Some(ref mut overlay) = *self.debug_overlay.lock().unwrap() {
+            if let Ok(event) = unpack_event(rc, ev, false) {
+                overlay.event(&event);
+            }
+        }
+    }
+
+    /// Start recording every event returned by `poll_event` and `peek_event` to a file at
+    /// `path`, along with when it arrived, for later use with `play_input` or
+    /// `TestBackend::play_input`. Any input recording already in progress is stopped first.
+    pub fn start_input_recording<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let _lock = self.input_lock.lock();
+
+        let recorder = record::InputRecorder::create(path.as_ref())?;
+        match self.input_recorder.lock().unwrap().replace(recorder) {
+            Some(old) => old.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Stop recording input and flush the file, reporting any error that happened while
+    /// writing it. Does nothing if no input recording is in progress.
+    pub fn stop_input_recording(&self) -> io::Result<()> {
+        let _lock = self.input_lock.lock();
+
+        match self.input_recorder.lock().unwrap().take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Feed the events from a recording made with `start_input_recording` to `poll_event` and
+    /// `peek_event`, with their original timing, instead of reading from the terminal. Once
+    /// they have all been delivered, input comes from the terminal again.
+    pub fn play_input<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let _lock = self.input_lock.lock();
+
+        *self.playback.lock().unwrap() = Some(record::Playback::open(path.as_ref())?);
+        Ok(())
+    }
+
+    // Must be called with `input_lock` held.
+    fn next_played_event(&self, timeout: Option<Duration>) -> Option<(c_int, RawEvent)> {
+        let mut playback = self.playback.lock().unwrap();
+        let next = playback.as_mut().and_then(|p| p.next(timeout));
+        if next.is_none() {
+            *playback = None;
+        }
+        next
+    }
+
+    fn record_input(&self, rc: c_int, ev: &RawEvent) {
+        if let Some(ref mut recorder) = *self.input_recorder.lock().unwrap() {
+            recorder.event(rc, ev);
+        }
+    }
+
+    pub fn set_input_mode(&self, mode: InputMode) {
+        let _lock = self.output_lock.lock();
+
+        if let Some(ref mut stopped) = *self.stopped.lock().unwrap() {
+            if mode as c_int != InputMode::Current as c_int {
+                stopped.input_mode = mode as c_int;
+            }
+            return;
+        }
+        unsafe {
+            termbox::tb_select_input_mode(mode as c_int);
+        }
+        // There's nowhere to report a failure to, and the mode itself has been set.
+        let _ = self.apply_mouse_tracking();
+    }
+
+    pub fn set_output_mode(&self, mode: OutputMode) {
+        let mode = mode.resolve();
+
+        let _lock = self.output_lock.lock();
+
+        *self.output_mode.lock().unwrap() = mode;
+
+        if let Some(ref mut stopped) = *self.stopped.lock().unwrap() {
+            if mode as c_int != OutputMode::Current as c_int {
+                stopped.output_mode = mode as c_int;
+            }
+            return;
+        }
+        unsafe {
+            termbox::tb_select_output_mode(mode as c_int);
+        }
+        // termbox stores the clear attributes in its own encoding, which depends on the mode.
+        self.apply_clear_attributes();
+    }
+
+    /// Set the colors and style used for blank cells by `clear`, `clear_region`, and by termbox
+    /// when it fills in new space after a resize.
+    ///
+    /// Call `clear` afterwards to apply them to the whole screen straight away.
+    pub fn set_clear_attributes(&self, fg: Color, bg: Color, sty: Style) {
+        let _lock = self.output_lock.lock();
+
+        *self.clear_attributes.lock().unwrap() = Some((fg, bg, sty));
+        self.apply_clear_attributes();
+    }
+
+    // Must be called with `output_lock` held.
+    fn apply_clear_attributes(&self) {
+        if self.termbox_stopped() {
+            return;
+        }
+        let (fg_int, bg_int) = self.clear_raw_attributes();
+        unsafe {
+            termbox::tb_set_clear_attributes(fg_int, bg_int);
+        }
+    }
+
+    fn clear_raw_attributes(&self) -> (u16, u16) {
+        match *self.clear_attributes.lock().unwrap() {
+            Some((fg, bg, sty)) => attributes(self.output_mode(), sty, fg, bg),
+            None => (termbox::TB_DEFAULT, termbox::TB_DEFAULT),
+        }
+    }
+
+    /// Put the terminal back the way it was before `init`, as far as that can be done without
+    /// termbox's help: reset the colors, show the cursor, turn off mouse reporting, leave the
+    /// alternate screen, and restore the terminal's settings. This is for when the terminal
+    /// seems to have got into a muddle, e.g. because the process was stopped the hard way.
+    ///
+    /// RustBox keeps running, but termbox no longer has the terminal set up; call `invalidate`
+    /// to set it up again, or drop the RustBox. See also the free function `reset_terminal`,
+    /// which is safe to call from a signal handler.
+    pub fn reset_terminal(&self) -> io::Result<()> {
+        let _lock = self.output_lock.lock();
+
+        signals::reset()
+    }
+
+    /// Force the next `present` to repaint every cell, e.g. after another process has written
+    /// to the terminal behind RustBox's back.
+    ///
+    /// termbox has no way to reset its record of what is on screen, so this restarts it,
+    /// carrying over the input/output modes and the contents of the back buffer. The screen is
+    /// cleared straight away and repainted by the next `present`.
+    ///
+    /// If termbox can't be started again, the error is returned and the terminal is left in its
+    /// normal state. Drawing then does nothing, `present` fails, and `poll_event` and
+    /// `peek_event` return `EventError::TermboxError`, but the RustBox can still be dropped, and
+    /// calling this again tries to start termbox once more, with the back buffer and modes
+    /// carried over as before.
+    pub fn invalidate(&self) -> Result<(), InitError> {
+        let _input_lock = self.input_lock.lock();
+        let _output_lock = self.output_lock.lock();
+
+        self.restart(|| ())
+    }
+
+    /// Stop the process as if the user had pressed Ctrl-Z at a shell prompt, handing the
+    /// terminal back in its original state. Once the process is continued (e.g. with `fg`),
+    /// termbox is restarted and the next `present` repaints the whole screen.
+    ///
+    /// termbox turns off the terminal's signal keys, unless `InitOptions::keep_signals` is set,
+    /// so Ctrl-Z arrives as `Key::Ctrl('z')`; call this in response to it. A SIGTSTP sent by
+    /// another process is not intercepted.
+    ///
+    /// If termbox fails to start again, it is left shut down as described for `invalidate`.
+    pub fn suspend_process(&self) -> Result<(), InitError> {
+        let _input_lock = self.input_lock.lock();
+        let _output_lock = self.output_lock.lock();
+
+        self.restart(|| unsafe {
+            libc::raise(libc::SIGTSTP);
+        })
+    }
+
+    /// The part of the screen being drawn in: all of it, unless `InitOptions::screen` is
+    /// `ScreenMode::Inline`. Draw through `window(rb.viewport())` to have coordinates start
+    /// from its corner.
+    pub fn viewport(&self) -> Rect {
+        let _lock = self.output_lock.lock();
+
+        self.raw_viewport()
+    }
+
+    // Must be called with `output_lock` held.
+    fn raw_viewport(&self) -> Rect {
+        let (width, height) = (self.raw_width(), self.raw_height());
+        match self.screen {
+            ScreenMode::Alternate | ScreenMode::Main => Rect::new(0, 0, width, height),
+            ScreenMode::Inline(lines) => {
+                let lines = cmp::min(lines, height);
+                let top = cmp::min(self.viewport_top.load(Ordering::SeqCst), height - lines);
+                Rect::new(0, top, width, lines)
+            },
+        }
+    }
+
+    // termbox always starts on the alternate screen, so for the other screen modes switch
+    // straight back to the main one, clearing the lines to be drawn in, since termbox takes
+    // them to be blank.
+    fn enter_main_screen(&self) -> io::Result<()> {
+        if self.screen == ScreenMode::Alternate {
+            return Ok(());
+        }
+        let viewport = self.raw_viewport();
+        let mut out = String::from("\x1b[?1049l");
+        for y in viewport.y..viewport.bottom() {
+            out.push_str(&format!("\x1b[{};1H\x1b[2K", y + 1));
+        }
+        self.tty.write(out.as_bytes())
+    }
+
+    // termbox clears the screen when it shuts down, and then leaves the alternate screen, so
+    // switch to the alternate screen first to keep what's on the main one. Leaving it puts the
+    // cursor back where it was when it was entered, which is set to the last line drawn in.
+    fn leave_main_screen(&self) -> io::Result<()> {
+        if self.screen == ScreenMode::Alternate {
+            return Ok(());
+        }
+        let viewport = self.raw_viewport();
+        self.tty.write(format!("\x1b[{};1H\x1b[?1049h", cmp::max(viewport.bottom(), 1)).as_bytes())
+    }
+
+    // Turn back on what `InitOptions` asked to keep of the terminal modes termbox turns off.
+    fn apply_keep_modes(&self) -> io::Result<()> {
+        match self.keep_modes {
+            (false, false) => Ok(()),
+            (signals, output_processing) => raw_mode::keep(self.tty.as_raw_fd(), signals, output_processing),
+        }
+    }
+
+    // Shut termbox down, run `func`, then start it again with the modes, cursor and back buffer
+    // contents carried over. Must be called with both locks held.
+    fn restart<F, R>(&self, func: F) -> Result<R, InitError>
+        where F: FnOnce() -> R
+    {
+        // After a failed `tb_init` termbox is already shut down, and only needs starting.
+        if !self.termbox_stopped() {
+            let (input_mode, output_mode) = unsafe {
+                (termbox::tb_select_input_mode(InputMode::Current as c_int),
+                 termbox::tb_select_output_mode(OutputMode::Current as c_int))
+            };
+            let stopped = Stopped {
+                width: self.raw_width(),
+                height: self.raw_height(),
+                cells: self.raw_cells().to_vec(),
+                input_mode,
+                output_mode,
+            };
+
+            // They are sent again by the next `present`, so failing to delete them isn't worth
+            // giving up over.
+            let _ = self.delete_kitty_images();
+            let _ = self.leave_main_screen();
+            winch::unhook();
+            unsafe {
+                termbox::tb_shutdown();
+            }
+            *self.stopped.lock().unwrap() = Some(stopped);
+        }
+        let result = func();
+        unsafe {
+            match termbox::tb_init() {
+                0 => (),
+                res => return Err(FromPrimitive::from_isize(res as isize).unwrap()),
+            }
+        }
+        let stopped = self.stopped.lock().unwrap().take().unwrap();
+        unsafe {
+            termbox::tb_select_input_mode(stopped.input_mode);
+            termbox::tb_select_output_mode(stopped.output_mode);
+        }
+        winch::hook(&self.resize_pipe);
+        let _ = self.apply_mouse_tracking();
+        self.apply_keep_modes().map_err(InitError::TerminalModeFailed)?;
+        self.enter_main_screen().map_err(InitError::TerminalModeFailed)?;
+        self.apply_clear_attributes();
+        // The screen has been wiped, so every image needs sending again.
+        self.shown_images.lock().unwrap().clear();
+        self.tracker.lock().unwrap().reset();
+        let (cx, cy) = *self.cursor.lock().unwrap();
+        unsafe {
+            termbox::tb_set_cursor(cx, cy);
+            termbox::tb_blit(0, 0, stopped.width as c_int, stopped.height as c_int, stopped.cells.as_ptr());
+        }
+        Ok(result)
+    }
+
+    /// Split into a handle for reading events and a handle for drawing, so that each can live
+    /// on its own thread. Both are `Send`; termbox is shut down once both have been dropped.
+    ///
+    /// Every `RustBox` method already takes `&self` and does its own locking, so the two
+    /// halves never need any extra synchronization.
+    pub fn split(self) -> (InputHandle, RenderHandle) {
+        handle::split(self)
+    }
+
+    /// Start a thread that reads events and sends them down a channel with room for `capacity`
+    /// of them, so that the application can take input when it suits it, with `try_recv` or
+    /// `recv_timeout`, without running a thread of its own. `raw` is as for `poll_event`.
+    ///
+    /// The thread stops after sending an error, or once the receiver is dropped. While the
+    /// channel is full, it waits for room, leaving further input with the terminal. Dropping
+    /// the `RenderHandle` stops the thread and then shuts termbox down.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use rustbox::{Event, Key, RustBox};
+    ///
+    /// let (events, rb) = RustBox::init(Default::default()).unwrap().spawn_input_thread(64, false);
+    /// loop {
+    ///     // Draw the next frame.
+    ///     rb.present().unwrap();
+    ///     match events.recv_timeout(Duration::from_millis(16)) {
+    ///         Ok(Ok(Event::KeyEvent(Key::Char('q')))) => break,
+    ///         Ok(Err(e)) => panic!("{}", e),
+    ///         _ => {},
+    ///     }
+    /// }
+    /// ```
+    pub fn spawn_input_thread(self, capacity: usize, raw: bool) -> (Receiver<EventResult>, RenderHandle) {
+        handle::spawn_input_thread(self, capacity, raw)
+    }
+
+    /// Convenience method to lock all (both input/output) access to
+    /// Rustbox, shutdown termbox itself, and then defer to the caller (via F,
+    /// while access is still locked). Once F completes, termbox is started and
+    /// the locks are released.
+    ///
+    /// While F runs the terminal is back in its normal state, so this can be
+    /// used to run an interactive subprocess such as `$EDITOR`. Afterwards the
+    /// input/output modes and the back buffer are restored, and the next
+    /// `present` repaints the whole screen. Returns whatever F returns.
+    ///
+    /// If termbox fails to start again, F's result is lost and termbox is left
+    /// shut down as described for `invalidate`.
+    pub fn suspend<F, R>(&self, func: F) -> Result<R, InitError>
+        where F: FnOnce() -> R
+    {
+        // Lock I/O until we've resumed.
+        let _input_lock = self.input_lock.lock();
+        let _output_lock = self.output_lock.lock();
+
+        self.restart(func)
+    }
+
+    /// Shut RustBox down and give the terminal back, like dropping it does, but report any
+    /// error from writing the sequences that restore the terminal's title and cursor, or from
+    /// finishing an output or input recording.
+    ///
+    /// termbox's own cleanup is always attempted, and doesn't report errors.
+    pub fn shutdown(mut self) -> io::Result<()> {
+        self.restore_terminal()
+    }
+
+    fn restore_terminal(&mut self) -> io::Result<()> {
+        if self.shut_down {
+            return Ok(());
+        }
+        self.shut_down = true;
+
+        // Keep going after an error so that termbox still gets shut down, but report the first
+        // one.
+        let mut result = Ok(());
+        if self.cursor_style_changed.load(Ordering::SeqCst) {
+            result = result.and(self.tty.write(b"\x1b[0 q"));
+        }
+        if self.title_saved.load(Ordering::SeqCst) {
+            result = result.and(self.tty.write(b"\x1b[23;0t"));
+        }
+        if let Some(recorder) = self.recorder.lock().unwrap().take() {
+            result = result.and(recorder.finish());
+        }
+        if let Some(recorder) = self.input_recorder.lock().unwrap().take() {
+            result = result.and(recorder.finish());
+        }
+        result = result.and(self.delete_kitty_images());
+        if *self.mouse_tracking.lock().unwrap() == MouseTracking::Motion {
+            result = result.and(self.tty.write(b"\x1b[?1003l"));
+        }
+        // If termbox failed to start again after a `restart`, it has already handed the terminal
+        // back.
+        if self.termbox_stopped() {
+            signals::uninstall();
+            return result;
+        }
+        result = result.and(self.leave_main_screen());
+        winch::unhook();
         // Since only one instance of the RustBox is ever accessible, we should not
         // need to do this atomically.
         // Note: we should definitely have RUSTBOX_RUNNING = true here.
         unsafe {
             termbox::tb_shutdown();
         }
+        if self.screen != ScreenMode::Alternate {
+            // Move on past the lines drawn in, now that termbox is back on the main screen.
+            result = result.and(self.tty.write(b"\r\n"));
+        }
+        signals::uninstall();
+        result
+    }
+}
+
+/// The terminal, which becomes readable when input arrives, so that RustBox can be part of an
+/// application's own `poll` or `epoll` loop. Once it is readable, `drain_events` takes the
+/// events without blocking, and leaves none behind in termbox's buffer.
+///
+/// Only wait for the descriptor to become readable; reading from it takes the input from
+/// termbox. Resizes don't make it readable, so wait for `resize_fd` as well.
+///
+/// ```no_run
+/// extern crate libc;
+/// extern crate rustbox;
+///
+/// use std::os::unix::io::AsRawFd;
+/// use rustbox::RustBox;
+///
+/// # fn main() {
+/// let rb = RustBox::init(Default::default()).unwrap();
+/// let mut fds = [
+///     libc::pollfd { fd: rb.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+///     libc::pollfd { fd: rb.resize_fd(), events: libc::POLLIN, revents: 0 },
+/// ];
+/// loop {
+///     // Add the application's own descriptors to `fds`, and deal with them here.
+///     unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+///     for event in rb.drain_events(false).unwrap() {
+///         // Handle the event.
+///     }
+///     rb.present().unwrap();
+/// }
+/// # }
+/// ```
+impl AsRawFd for RustBox {
+    fn as_raw_fd(&self) -> RawFd {
+        self.tty.as_raw_fd()
+    }
+}
+
+/// The terminal, as with `AsRawFd`.
+impl AsFd for RustBox {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.tty.as_fd()
+    }
+}
+
+impl Drop for RustBox {
+    fn drop(&mut self) {
+        let _ = self.restore_terminal();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::{attributes, Cell, Color, OutputMode, RB_NORMAL, RB_REVERSE};
+
+    #[test]
+    fn default_colors_in_every_mode() {
+        // A 24-bit color terminal, so that `Auto` picks `EightBit`.
+        env::set_var("COLORTERM", "truecolor");
+        let auto = OutputMode::Auto.resolve();
+        assert!(matches!(auto, OutputMode::EightBit));
+        let blank = Cell::default();
+        assert_eq!(attributes(auto, blank.style, blank.fg, blank.bg), (0x07, 0x00));
+
+        for &mode in &[OutputMode::Normal, OutputMode::EightBit, OutputMode::WebSafe, OutputMode::Grayscale] {
+            attributes(mode, RB_REVERSE, Color::Default, Color::Default);
+            attributes(mode, RB_NORMAL, Color::Default, Color::Rgb(0x12, 0x34, 0x56));
+        }
     }
 }
@@ -0,0 +1,134 @@
+// Putting the terminal back when the process is killed while RustBox has it, for
+// `InitOptions::restore_on_signal`, and whenever `reset_terminal` is called.
+//
+// Only async-signal-safe calls may be made from a signal handler, so everything the handler
+// needs is worked out beforehand and kept in statics: the terminal's file descriptor, its
+// settings from before termbox started, and which signals are being handled.
+
+use std::cell::UnsafeCell;
+use std::io;
+use std::mem;
+use std::os::raw::c_int;
+use std::os::unix::io::RawFd;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use libc;
+
+// The signals that kill the process unless something is done about them.
+const SIGNALS: [c_int; 4] = [libc::SIGTERM, libc::SIGHUP, libc::SIGINT, libc::SIGQUIT];
+
+// Resets the colors, shows the cursor, turns off mouse reporting in all of its forms and the
+// application keypad mode, and leaves the alternate screen: everything termbox and RustBox
+// might have turned on. The screen isn't cleared, since with `ScreenMode::Main` and
+// `ScreenMode::Inline` it is the main one, which has the shell's output on it.
+const RESET: &[u8] = b"\x1b[0m\x1b[?25h\x1b[?1000l\x1b[?1002l\x1b[?1003l\x1b[?1006l\x1b[?1015l\
+                       \x1b[?1l\x1b>\x1b[?1049l";
+
+struct State {
+    termios: libc::termios,
+    // Which of `SIGNALS` `install` handled.
+    handled: [bool; 4],
+}
+
+struct Saved(UnsafeCell<Option<State>>);
+
+// Only written while the handler isn't armed, and only read by it while it is.
+unsafe impl Sync for Saved {}
+
+static SAVED: Saved = Saved(UnsafeCell::new(None));
+static ARMED: AtomicBool = AtomicBool::new(false);
+static FD: AtomicI32 = AtomicI32::new(-1);
+
+// Save the settings of the terminal open as `fd`, for `reset`. With `handle` set, also
+// handle those of `SIGNALS` whose disposition is still the default, leaving any the
+// application handles itself alone.
+pub fn install(fd: RawFd, handle: bool) -> io::Result<()> {
+    let mut termios: libc::termios = unsafe { mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut termios) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut handled = [false; 4];
+    for (i, &signal) in SIGNALS.iter().enumerate().filter(|_| handle) {
+        let mut old: libc::sigaction = unsafe { mem::zeroed() };
+        let ok = unsafe { libc::sigaction(signal, ptr::null(), &mut old) } == 0;
+        handled[i] = ok && old.sa_sigaction == libc::SIG_DFL;
+    }
+    unsafe {
+        *SAVED.0.get() = Some(State { termios, handled });
+    }
+    FD.store(fd, Ordering::SeqCst);
+    ARMED.store(true, Ordering::SeqCst);
+    for (&signal, _) in SIGNALS.iter().zip(handled.iter()).filter(|&(_, &handled)| handled) {
+        let mut action: libc::sigaction = unsafe { mem::zeroed() };
+        action.sa_sigaction = handle_signal as extern "C" fn(c_int) as libc::sighandler_t;
+        unsafe {
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal, &action, ptr::null_mut());
+        }
+    }
+    Ok(())
+}
+
+// Put back the default dispositions, once RustBox has restored the terminal itself.
+pub fn uninstall() {
+    if !ARMED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    unsafe {
+        if let Some(state) = (*SAVED.0.get()).take() {
+            for (&signal, _) in SIGNALS.iter().zip(state.handled.iter()).filter(|&(_, &handled)| handled) {
+                libc::signal(signal, libc::SIG_DFL);
+            }
+        }
+    }
+}
+
+// Write `RESET` and put back the terminal's settings from before RustBox started, or, with no
+// RustBox running, turn line editing, echo, signal keys and output processing back on. Only
+// makes async-signal-safe calls.
+pub fn reset() -> io::Result<()> {
+    if ARMED.load(Ordering::SeqCst) {
+        if let Some(ref state) = unsafe { &*SAVED.0.get() } {
+            return restore(FD.load(Ordering::SeqCst), &state.termios);
+        }
+    }
+    let fd = unsafe { libc::open(b"/dev/tty\0".as_ptr() as *const libc::c_char, libc::O_RDWR | libc::O_NOCTTY) };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut termios: libc::termios = unsafe { mem::zeroed() };
+    let result = if unsafe { libc::tcgetattr(fd, &mut termios) } == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        termios.c_iflag |= libc::ICRNL;
+        termios.c_oflag |= libc::OPOST;
+        termios.c_lflag |= libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN;
+        restore(fd, &termios)
+    };
+    unsafe { libc::close(fd) };
+    result
+}
+
+fn restore(fd: RawFd, termios: &libc::termios) -> io::Result<()> {
+    let written = unsafe { libc::write(fd, RESET.as_ptr() as *const libc::c_void, RESET.len()) };
+    if written == -1 || unsafe { libc::tcsetattr(fd, libc::TCSANOW, termios) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+extern "C" fn handle_signal(signal: c_int) {
+    if ARMED.swap(false, Ordering::SeqCst) {
+        unsafe {
+            if let Some(ref state) = *SAVED.0.get() {
+                let _ = restore(FD.load(Ordering::SeqCst), &state.termios);
+            }
+        }
+    }
+    unsafe {
+        // With the default disposition back, this kills the process as the signal would have.
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
@@ -0,0 +1,32 @@
+// A `mio` event source, so that RustBox's input can be waited for with a `mio::Poll` along
+// with an application's sockets and pipes.
+//
+// The terminal's descriptor becomes readable when input arrives, as with `AsRawFd`, and
+// `resize_fd` when the terminal is resized, so all there is to do is to hand both to mio
+// with the same token.
+
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+use mio::event::Source;
+use mio::unix::SourceFd;
+use mio::{Interest, Registry, Token};
+
+use super::RustBox;
+
+impl Source for RustBox {
+    fn register(&mut self, registry: &Registry, token: Token, interests: Interest) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).register(registry, token, interests)?;
+        SourceFd(&self.resize_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &Registry, token: Token, interests: Interest) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)?;
+        SourceFd(&self.resize_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)?;
+        SourceFd(&self.resize_fd()).deregister(registry)
+    }
+}
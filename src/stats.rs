@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use super::Cell;
+use export;
+use text;
+
+/// What `present` did, for one frame or added up over many. See `RustBox::frame_stats`.
+///
+/// termbox writes frames itself and doesn't say what it wrote, so `bytes` and `escapes` are
+/// worked out by following the same steps it takes for each changed cell: a cursor movement
+/// unless the cell follows on from the last one written, and an attribute change whenever the
+/// colors or style differ from the last cell written. termbox's exact escape sequences come
+/// from the terminal's terminfo entry, so these are estimates, but they go up and down with
+/// the real figures. Bytes written for images are counted exactly.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Stats {
+    /// The number of frames presented.
+    pub frames: u64,
+    /// The number of cells compared with what was on screen.
+    pub cells: u64,
+    /// The number of cells that were different, and so were written.
+    pub changed: u64,
+    pub bytes: u64,
+    pub escapes: u64,
+    /// The time spent in `present`.
+    pub duration: Duration,
+}
+
+impl Stats {
+    pub(crate) fn add(&mut self, frame: &Stats) {
+        self.frames += frame.frames;
+        self.cells += frame.cells;
+        self.changed += frame.changed;
+        self.bytes += frame.bytes;
+        self.escapes += frame.escapes;
+        self.duration += frame.duration;
+    }
+}
+
+// Follows what termbox has put on screen, so that the effect of each frame can be worked out.
+#[derive(Default)]
+pub struct Tracker {
+    width: usize,
+    front: Vec<Cell>,
+    // The last cell termbox wrote and its attributes, which carry over between frames.
+    last_pos: Option<(usize, usize)>,
+    last_cell: Option<Cell>,
+}
+
+impl Tracker {
+    // Work out what termbox writes to show `cells`, and remember them as being on screen.
+    // `blank` is what a cleared cell looks like.
+    pub fn frame(&mut self, width: usize, cells: &[Cell], blank: Cell) -> Stats {
+        let mut stats = Stats { frames: 1, cells: cells.len() as u64, ..Default::default() };
+        if width != self.width || cells.len() != self.front.len() {
+            // termbox starts out with a cleared screen, and clears it again after a resize.
+            self.width = width;
+            self.front = vec![blank; cells.len()];
+            self.last_pos = None;
+        }
+        for (i, cell) in cells.iter().enumerate() {
+            if self.front.get(i) == Some(cell) {
+                continue;
+            }
+            let (x, y) = (i % width, i / width);
+            stats.changed += 1;
+            let attrs = |c: &Cell| (c.fg, c.bg, c.style);
+            if self.last_cell.as_ref().map(attrs) != Some(attrs(cell)) {
+                stats.escapes += 1;
+                stats.bytes += export::sgr(cell).len() as u64;
+            }
+            if self.last_pos.map(|(lx, ly)| (lx + 1, ly)) != Some((x, y)) {
+                stats.escapes += 1;
+                stats.bytes += format!("\x1b[{};{}H", y + 1, x + 1).len() as u64;
+            }
+            stats.bytes += cell.ch.len_utf8() as u64;
+            // A wide character moves the cursor past the cell after it too.
+            let w = text::char_width(cell.ch).max(1);
+            self.last_pos = Some((x + w - 1, y));
+            self.last_cell = Some(*cell);
+        }
+        self.front = cells.to_vec();
+        stats
+    }
+
+    // Forget what is on screen, e.g. because termbox has been restarted and will repaint it.
+    pub fn reset(&mut self) {
+        *self = Tracker::default();
+    }
+}
@@ -0,0 +1,390 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::{unpack_event, Alignment, BorderStyle, Buffer, Cell, CellError, Color, Event, EventError, EventResult,
+            Layer, Rect, SignalKeys, Style, Text, RB_UNDERLINE};
+use border;
+use buffer::BLANK;
+use ime;
+use layer;
+use record;
+use text::{self, ControlPolicy};
+use theme::{Role, Theme};
+
+/// An in-memory stand-in for `RustBox`, for testing user interfaces without a terminal.
+///
+/// It offers the same drawing methods as `RustBox`, drawing into a back buffer which `present`
+/// copies to a front buffer. The assertion helpers look at the front buffer, i.e. at what a
+/// real terminal would be showing. Events queued with `push_event` are handed out by
+/// `poll_event` and `peek_event`.
+///
+/// ```
+/// use rustbox::{Color, Event, Key, TestBackend, RB_NORMAL};
+///
+/// let mut rb = TestBackend::new(10, 2);
+/// rb.push_event(Event::KeyEvent(Key::Char('q')));
+///
+/// rb.print(0, 1, RB_NORMAL, Color::White, Color::Black, "hi");
+/// rb.present().unwrap();
+/// assert_eq!(rb.buffer_as_string(), "          \nhi        ");
+///
+/// match rb.poll_event(false) {
+///     Ok(Event::KeyEvent(Key::Char('q'))) => (),
+///     other => panic!("unexpected {:?}", other),
+/// }
+/// ```
+pub struct TestBackend {
+    back: Buffer,
+    front: Buffer,
+    clear_cell: Cell,
+    cursor: Option<(usize, usize)>,
+    events: VecDeque<Event>,
+    layers: layer::Stack,
+    theme: Theme,
+    control_policy: ControlPolicy,
+    preedit: Option<ime::Preedit>,
+    signal_keys: SignalKeys,
+}
+
+impl TestBackend {
+    pub fn new(width: usize, height: usize) -> TestBackend {
+        TestBackend {
+            back: Buffer::new(width, height),
+            front: Buffer::new(width, height),
+            clear_cell: BLANK,
+            cursor: None,
+            events: VecDeque::new(),
+            layers: Default::default(),
+            theme: Theme::default(),
+            control_policy: ControlPolicy::default(),
+            preedit: None,
+            signal_keys: SignalKeys::Keys,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.back.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.back.height()
+    }
+
+    pub fn clear(&mut self) {
+        let (w, h) = (self.width(), self.height());
+        self.clear_region(0, 0, w, h);
+    }
+
+    pub fn clear_region(&mut self, x: usize, y: usize, w: usize, h: usize) {
+        let right = cmp::min(x.saturating_add(w), self.width());
+        let bottom = cmp::min(y.saturating_add(h), self.height());
+        for cy in y..bottom {
+            for cx in x..right {
+                self.back.set(cx, cy, self.clear_cell);
+            }
+        }
+    }
+
+    pub fn set_clear_attributes(&mut self, fg: Color, bg: Color, sty: Style) {
+        self.clear_cell = Cell { ch: ' ', fg, bg, style: sty };
+    }
+
+    /// Copy the back buffer to the front buffer, with the layers drawn over it. Never fails,
+    /// but returns a `Result` like `RustBox::present`, so that code can be written for both.
+    pub fn present(&mut self) -> io::Result<()> {
+        self.front = self.back.clone();
+        let (w, h) = (self.width(), self.height());
+        for layer in self.layers.resized(w, h) {
+            for y in 0..h {
+                for x in 0..w {
+                    let below = self.front.get(x, y).cloned().unwrap_or(self.clear_cell);
+                    self.front.set(x, y, layer.get(x, y).unwrap_or_default().over(below));
+                }
+            }
+        }
+        for (x, y, ch) in self.preedit.iter().flat_map(|preedit| preedit.cells()) {
+            if let Some(&below) = self.front.get(x, y) {
+                self.front.set(x, y, Cell { ch, style: below.style | RB_UNDERLINE, ..below });
+            }
+        }
+        Ok(())
+    }
+
+    /// See `RustBox::set_preedit`.
+    pub fn set_preedit(&mut self, x: usize, y: usize, text: &str, cursor: usize) {
+        self.preedit = Some(ime::Preedit { x, y, text: text.to_string(), cursor });
+    }
+
+    pub fn clear_preedit(&mut self) {
+        self.preedit = None;
+    }
+
+    /// See `RustBox::add_layer`.
+    pub fn add_layer(&mut self, name: &str, z: i32) {
+        let (w, h) = (self.width(), self.height());
+        self.layers.add(name, z, w, h);
+    }
+
+    pub fn remove_layer(&mut self, name: &str) -> bool {
+        self.layers.remove(name)
+    }
+
+    /// The layer called `name`, if there is one.
+    pub fn layer(&mut self, name: &str) -> Option<&mut Layer> {
+        let (w, h) = (self.width(), self.height());
+        let layer = self.layers.get_mut(name, w, h)?;
+        layer.set_control_policy(self.control_policy);
+        Some(layer)
+    }
+
+    pub fn set_cursor(&mut self, x: isize, y: isize) {
+        self.cursor = if x < 0 || y < 0 { None } else { Some((x as usize, y as usize)) };
+    }
+
+    pub fn hide_cursor(&mut self) {
+        self.cursor = None;
+    }
+
+    /// Where the cursor was last put with `set_cursor`, or `None` if it is hidden.
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor
+    }
+
+    /// See `RustBox::set_control_policy`.
+    pub fn set_control_policy(&mut self, policy: ControlPolicy) {
+        self.control_policy = policy;
+    }
+
+    pub fn control_policy(&self) -> ControlPolicy {
+        self.control_policy
+    }
+
+    pub fn print(&mut self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+        for (dx, dy, ch) in self.control_policy.place(s) {
+            self.print_char(x.saturating_add(dx), y.saturating_add(dy), sty, fg, bg, ch);
+        }
+    }
+
+    pub fn print_char(&mut self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, ch: char) {
+        self.back.set(x, y, Cell { ch, fg, bg, style: sty });
+    }
+
+    /// See `RustBox::print_themed`.
+    pub fn print_themed(&mut self, x: usize, y: usize, role: Role, s: &str) {
+        let attr = self.theme.get(role);
+        self.print(x, y, attr.style, attr.fg, attr.bg, s);
+    }
+
+    /// See `RustBox::set_theme`.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        let background = theme.get(Role::Background);
+        self.set_clear_attributes(background.fg, background.bg, background.style);
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    pub fn print_text(&mut self, x: usize, y: usize, text: &Text) -> usize {
+        for (dx, ch, attr) in text.columns() {
+            self.print_char(x.saturating_add(dx), y, attr.style, attr.fg, attr.bg, ch);
+        }
+        text.width()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn print_wrapped(&mut self, rect: Rect, align: Alignment, ellipsis: bool, sty: Style,
+                         fg: Color, bg: Color, s: &str) -> usize {
+        let lines = text::layout(s, rect.w, rect.h, align, ellipsis);
+        for (i, &(offset, ref line)) in lines.iter().enumerate() {
+            for (dx, ch) in text::columns(line) {
+                self.print_char(rect.x + offset + dx, rect.y + i, sty, fg, bg, ch);
+            }
+        }
+        lines.len()
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, cell: Cell) {
+        let rect = rect.intersection(Rect::new(0, 0, self.width(), self.height()));
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                self.back.set(x, y, cell);
+            }
+        }
+    }
+
+    pub fn scroll_up(&mut self, rect: Rect, n: usize) {
+        self.back.scroll(rect, n, true, self.clear_cell);
+    }
+
+    pub fn scroll_down(&mut self, rect: Rect, n: usize) {
+        self.back.scroll(rect, n, false, self.clear_cell);
+    }
+
+    pub fn paint_attrs(&mut self, rect: Rect, fg: Color, bg: Color, sty: Style) {
+        let rect = rect.intersection(Rect::new(0, 0, self.width(), self.height()));
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                let ch = self.back.get(x, y).unwrap().ch;
+                self.back.set(x, y, Cell { ch, fg, bg, style: sty });
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_hline(&mut self, x: usize, y: usize, len: usize, border: BorderStyle, sty: Style,
+                      fg: Color, bg: Color) {
+        self.draw_border(&border::hline(x, y, len), border, sty, fg, bg);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_vline(&mut self, x: usize, y: usize, len: usize, border: BorderStyle, sty: Style,
+                      fg: Color, bg: Color) {
+        self.draw_border(&border::vline(x, y, len), border, sty, fg, bg);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_box(&mut self, x: usize, y: usize, w: usize, h: usize, border: BorderStyle,
+                    sty: Style, fg: Color, bg: Color) {
+        self.draw_border(&border::rect(x, y, w, h), border, sty, fg, bg);
+    }
+
+    fn draw_border(&mut self, cells: &[(usize, usize, u8)], border: BorderStyle, sty: Style,
+                   fg: Color, bg: Color) {
+        for &(x, y, segments) in cells {
+            if let Some(existing) = self.back.get(x, y).map(|cell| cell.ch) {
+                let ch = border::join(existing, segments, border);
+                self.back.set(x, y, Cell { ch, fg, bg, style: sty });
+            }
+        }
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        self.back.get(x, y).cloned()
+    }
+
+    pub fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        self.back.set(x, y, cell);
+    }
+
+    pub fn try_set_cell(&mut self, x: usize, y: usize, cell: Cell) -> Result<(), CellError> {
+        self.back.try_set(x, y, cell)
+    }
+
+    /// Returns a copy of the back buffer.
+    pub fn buffer(&self) -> Buffer {
+        self.back.clone()
+    }
+
+    pub fn blit(&mut self, x: usize, y: usize, buffer: &Buffer) {
+        for (by, row) in buffer.iter_rows().enumerate() {
+            for (bx, cell) in row.iter().enumerate() {
+                self.back.set(x.saturating_add(bx), y.saturating_add(by), *cell);
+            }
+        }
+    }
+
+    pub fn blit_region(&mut self, x: usize, y: usize, buffer: &Buffer, src_rect: Rect) {
+        self.back.blit(x, y, buffer, src_rect);
+    }
+
+    /// Returns the next queued event, or `Event::NoEvent` if there are none left. Unlike
+    /// `RustBox::poll_event` this never blocks.
+    pub fn poll_event(&mut self, _raw: bool) -> EventResult {
+        Ok(self.signal_keys.apply(self.events.pop_front().unwrap_or(Event::NoEvent)))
+    }
+
+    pub fn peek_event(&mut self, _timeout: Duration, raw: bool) -> EventResult {
+        self.poll_event(raw)
+    }
+
+    /// Like `poll_event`; the deadline is ignored.
+    pub fn poll_event_deadline(&mut self, _deadline: Instant, raw: bool) -> EventResult {
+        self.poll_event(raw)
+    }
+
+    /// Returns all of the queued events, leaving none, like `RustBox::drain_events`.
+    pub fn drain_events(&mut self, raw: bool) -> Result<Vec<Event>, EventError> {
+        let mut events = Vec::new();
+        loop {
+            match self.poll_event(raw)? {
+                Event::NoEvent => return Ok(events),
+                event => events.push(event),
+            }
+        }
+    }
+
+    /// See `RustBox::set_signal_keys`. `SignalKeys::Raise` raises real signals.
+    pub fn set_signal_keys(&mut self, keys: SignalKeys) {
+        self.signal_keys = keys;
+    }
+
+    pub fn signal_keys(&self) -> SignalKeys {
+        self.signal_keys
+    }
+
+    /// Queue an event to be returned by `poll_event` or `peek_event`.
+    pub fn push_event(&mut self, event: Event) {
+        self.events.push_back(event);
+    }
+
+    /// Queue all of the events from a recording made with `RustBox::start_input_recording`.
+    /// Their timing is ignored.
+    pub fn play_input<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        for recorded in record::read_input(path.as_ref())? {
+            if let Ok(event) = unpack_event(recorded.rc, &recorded.ev, false) {
+                self.push_event(event);
+            }
+        }
+        Ok(())
+    }
+
+    /// Simulate the terminal being resized: both buffers are resized, keeping whatever fits,
+    /// and a `ResizeEvent` is queued.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let mut back = Buffer::new(width, height);
+        let mut front = Buffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                back.set(x, y, self.back.get(x, y).cloned().unwrap_or(self.clear_cell));
+                front.set(x, y, self.front.get(x, y).cloned().unwrap_or(self.clear_cell));
+            }
+        }
+        self.back = back;
+        self.front = front;
+        self.push_event(Event::ResizeEvent(width as i32, height as i32));
+    }
+
+    /// Returns a copy of the front buffer, i.e. the last presented frame.
+    pub fn presented(&self) -> Buffer {
+        self.front.clone()
+    }
+
+    /// The characters of the last presented frame, one line per row.
+    pub fn buffer_as_string(&self) -> String {
+        self.front.text()
+    }
+
+    /// Panic unless the last presented frame has `expected` at `(x, y)`.
+    pub fn assert_cell(&self, x: usize, y: usize, expected: Cell) {
+        match self.front.get(x, y) {
+            Some(cell) => assert!(*cell == expected,
+                                  "cell ({}, {}) is {:?}, expected {:?}", x, y, cell, expected),
+            None => panic!("cell ({}, {}) is outside the {}x{} buffer",
+                           x, y, self.front.width(), self.front.height()),
+        }
+    }
+
+    /// Panic unless the last presented frame shows `text` starting at `(x, y)`, ignoring
+    /// colors and style.
+    pub fn assert_text(&self, x: usize, y: usize, text: &str) {
+        let actual: String = (0..text.chars().count())
+            .map(|i| self.front.get(x + i, y).map(|cell| cell.ch).unwrap_or('\0'))
+            .collect();
+        assert!(actual == text, "text at ({}, {}) is {:?}, expected {:?}", x, y, actual, text);
+    }
+}
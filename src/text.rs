@@ -0,0 +1,574 @@
+//! Measuring and laying out text by the number of terminal columns it takes up.
+//!
+//! East Asian wide characters take two columns and combining marks none, so the length of a
+//! string is no guide to how much room it needs. These functions go by display width instead.
+
+#[cfg(feature = "bidi")]
+use unicode_bidi::ParagraphBidiInfo;
+use std::sync::RwLock;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use super::{Color, Style, RB_BOLD, RB_NORMAL, RB_REVERSE, RB_UNDERLINE};
+
+/// How lines are placed within the width they are given.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// The colors and style to draw a span of text with.
+///
+/// ```
+/// use rustbox::Color;
+/// use rustbox::text::Attr;
+///
+/// let error = Attr::from(Color::Red).bold();
+/// assert_eq!(error.fg, Color::Red);
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Attr {
+    pub fg: Color,
+    pub bg: Color,
+    pub style: Style,
+}
+
+impl Attr {
+    pub fn new(fg: Color, bg: Color, style: Style) -> Attr {
+        Attr { fg, bg, style }
+    }
+
+    pub fn fg(self, fg: Color) -> Attr {
+        Attr { fg, ..self }
+    }
+
+    pub fn bg(self, bg: Color) -> Attr {
+        Attr { bg, ..self }
+    }
+
+    pub fn bold(self) -> Attr {
+        Attr { style: self.style | RB_BOLD, ..self }
+    }
+
+    pub fn underline(self) -> Attr {
+        Attr { style: self.style | RB_UNDERLINE, ..self }
+    }
+
+    pub fn reverse(self) -> Attr {
+        Attr { style: self.style | RB_REVERSE, ..self }
+    }
+}
+
+/// The terminal's default colors, with no style.
+impl Default for Attr {
+    fn default() -> Attr {
+        Attr::new(Color::Default, Color::Default, RB_NORMAL)
+    }
+}
+
+/// Just a foreground color, on the default background.
+impl From<Color> for Attr {
+    fn from(fg: Color) -> Attr {
+        Attr::default().fg(fg)
+    }
+}
+
+/// A piece of text drawn with the same attributes throughout.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Span {
+    pub text: String,
+    pub attr: Attr,
+}
+
+/// A line of text made up of spans with their own attributes, so that a colored status line
+/// can be built up and then printed in one go with `RustBox::print_text`.
+///
+/// ```
+/// use rustbox::Color;
+/// use rustbox::text::{Attr, Text};
+///
+/// let line = Text::new()
+///     .push("error: ", Attr::from(Color::Red).bold())
+///     .push("file not found", Attr::default());
+/// assert_eq!(line.width(), 21);
+/// ```
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Text {
+    spans: Vec<Span>,
+}
+
+impl Text {
+    pub fn new() -> Text {
+        Text { spans: Vec::new() }
+    }
+
+    /// Add a span to the end of the text.
+    pub fn push<S: Into<String>, A: Into<Attr>>(mut self, text: S, attr: A) -> Text {
+        self.spans.push(Span { text: text.into(), attr: attr.into() });
+        self
+    }
+
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// The number of columns the whole text takes up.
+    pub fn width(&self) -> usize {
+        self.spans.iter().map(|span| width(&span.text)).sum()
+    }
+
+    // Each character with its column and attributes, as for `columns`.
+    pub(crate) fn columns(&self) -> Vec<(usize, char, Attr)> {
+        let mut x = 0;
+        let mut out = Vec::new();
+        for span in &self.spans {
+            for (dx, ch) in columns(&span.text) {
+                out.push((x + dx, ch, span.attr));
+            }
+            x += width(&span.text);
+        }
+        out
+    }
+}
+
+/// How many columns characters are taken to take up, by `width`, `char_width` and everything
+/// that lays out text with them, `print` and `wrap` among them. Terminals disagree about some
+/// characters, and when they don't agree with the crate, text drifts out of line, so this
+/// should be set to match the terminal in use. See `set_width_policy`.
+///
+/// ```
+/// use rustbox::text::{self, WidthPolicy};
+///
+/// assert_eq!(text::width("±1"), 2);
+/// text::set_width_policy(WidthPolicy { ambiguous_wide: true, ..Default::default() });
+/// assert_eq!(text::width("±1"), 3);
+/// # text::set_width_policy(Default::default());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct WidthPolicy {
+    /// Whether characters of East Asian Ambiguous width, such as Greek and Cyrillic letters,
+    /// `±` and box drawing characters, take two columns, as they do in terminals set up for
+    /// Chinese, Japanese or Korean.
+    pub ambiguous_wide: bool,
+    /// Whether emoji take two columns, as in most terminals, rather than one, as in some older
+    /// ones.
+    pub emoji_wide: bool,
+    /// Asked first about each character; an answer other than `None` is its width, whatever
+    /// the rest of the policy says.
+    pub overrides: Option<fn(char) -> Option<usize>>,
+}
+
+/// Ambiguous characters narrow and emoji wide, by the Unicode tables.
+impl Default for WidthPolicy {
+    fn default() -> WidthPolicy {
+        DEFAULT_WIDTH_POLICY
+    }
+}
+
+const DEFAULT_WIDTH_POLICY: WidthPolicy = WidthPolicy { ambiguous_wide: false, emoji_wide: true, overrides: None };
+
+static WIDTH_POLICY: RwLock<WidthPolicy> = RwLock::new(DEFAULT_WIDTH_POLICY);
+
+/// Measure characters by `policy` from now on, throughout the program. `present` also takes it
+/// into account, so that the terminal's idea of where each character goes agrees with the
+/// back buffer's.
+pub fn set_width_policy(policy: WidthPolicy) {
+    *WIDTH_POLICY.write().unwrap() = policy;
+}
+
+pub fn width_policy() -> WidthPolicy {
+    *WIDTH_POLICY.read().unwrap()
+}
+
+/// The number of columns `s` takes up.
+pub fn width(s: &str) -> usize {
+    if is_default_policy(&width_policy()) {
+        return UnicodeWidthStr::width(s);
+    }
+    s.chars().map(char_width).sum()
+}
+
+/// The number of columns `ch` takes up. Control characters and combining marks take none.
+pub fn char_width(ch: char) -> usize {
+    let policy = width_policy();
+    if let Some(width) = policy.overrides.and_then(|overrides| overrides(ch)) {
+        return width;
+    }
+    let width = if policy.ambiguous_wide {
+        UnicodeWidthChar::width_cjk(ch)
+    } else {
+        UnicodeWidthChar::width(ch)
+    }.unwrap_or(0);
+    if width == 2 && !policy.emoji_wide && is_emoji(ch) { 1 } else { width }
+}
+
+// Whether the width policy makes `ch` take up a different number of cells on screen than
+// termbox expects, going by the Unicode tables as the C library's `wcwidth` does. Characters
+// that take none still take a cell there.
+pub(crate) fn width_differs(ch: char) -> bool {
+    let policy = width_policy();
+    !is_default_policy(&policy) &&
+        char_width(ch).clamp(1, 2) != UnicodeWidthChar::width(ch).unwrap_or(0).clamp(1, 2)
+}
+
+fn is_default_policy(policy: &WidthPolicy) -> bool {
+    !policy.ambiguous_wide && policy.emoji_wide && policy.overrides.is_none()
+}
+
+// Whether `ch` is in one of the blocks where the wide emoji are.
+fn is_emoji(ch: char) -> bool {
+    matches!(ch as u32, 0x231a..=0x231b | 0x23e9..=0x23f3 | 0x23f8..=0x23fa | 0x25fd..=0x25fe |
+             0x2600..=0x27bf | 0x2b1b..=0x2b1c | 0x2b50 | 0x2b55 | 0x1f000..=0x1faff)
+}
+
+/// Wrap `s` into lines at most `width` columns wide.
+///
+/// Lines are broken between words where possible, and words too long for a line of their own
+/// are broken between characters. Each `\n` starts a new line; other runs of whitespace become
+/// a single space. A character wider than `width` still gets a line of its own.
+pub fn wrap(s: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    if width == 0 {
+        return lines;
+    }
+    for paragraph in s.split('\n') {
+        let mut line = String::new();
+        let mut line_width = 0;
+        for word in paragraph.split_whitespace() {
+            let word_width = self::width(word);
+            let space = if line.is_empty() { 0 } else { 1 };
+            if line_width + space + word_width <= width {
+                if space == 1 {
+                    line.push(' ');
+                }
+                line.push_str(word);
+                line_width += space + word_width;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(line);
+                line = String::new();
+                line_width = 0;
+            }
+            for ch in word.chars() {
+                let ch_width = char_width(ch);
+                if line_width + ch_width > width && !line.is_empty() {
+                    lines.push(line);
+                    line = String::new();
+                    line_width = 0;
+                }
+                line.push(ch);
+                line_width += ch_width;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Cut `s` down to at most `width` columns, ending it with `…` if anything had to go.
+pub fn truncate(s: &str, width: usize) -> String {
+    if self::width(s) <= width {
+        return s.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for ch in s.chars() {
+        let ch_width = char_width(ch);
+        if used + ch_width + 1 > width {
+            break;
+        }
+        out.push(ch);
+        used += ch_width;
+    }
+    if width > 0 {
+        out.push('…');
+    }
+    out
+}
+
+// Lay out `s` in a `w` by `h` box, returning each line with the column it starts at. If it
+// doesn't fit and `ellipsis` is set, the last line is truncated to show that there's more.
+pub(crate) fn layout(s: &str, w: usize, h: usize, align: Alignment, ellipsis: bool)
+                     -> Vec<(usize, String)> {
+    let mut lines = wrap(s, w);
+    if lines.len() > h {
+        let more = ellipsis && h > 0;
+        lines.truncate(h);
+        if more {
+            // The last line may well fit as it is, but still needs to show that there's more.
+            let last = lines.pop().unwrap();
+            lines.push(truncate(&format!("{}…", last), w));
+        }
+    }
+    lines.into_iter().map(|line| {
+        let room = w.saturating_sub(self::width(&line));
+        let offset = match align {
+            Alignment::Left => 0,
+            Alignment::Center => room / 2,
+            Alignment::Right => room,
+        };
+        (offset, line)
+    }).collect()
+}
+
+// The characters of `s` with the column each one starts at, skipping those that take up no
+// room, since a cell can only hold one character.
+pub(crate) fn columns(s: &str) -> Vec<(usize, char)> {
+    let mut x = 0;
+    let mut out = Vec::new();
+    for ch in s.chars() {
+        let ch_width = char_width(ch);
+        if ch_width > 0 {
+            out.push((x, ch));
+            x += ch_width;
+        }
+    }
+    out
+}
+
+/// What `print` does with tabs, newlines and other control characters, which would otherwise
+/// go into cells as they are and throw the layout out. See `RustBox::set_control_policy`.
+///
+/// ```
+/// use rustbox::{Color, TestBackend, RB_NORMAL};
+///
+/// let mut rb = TestBackend::new(12, 2);
+/// rb.print(1, 0, RB_NORMAL, Color::Default, Color::Default, "a\tb\x03\nc");
+/// rb.present().unwrap();
+/// assert_eq!(rb.buffer_as_string(), " a       b^C\n c          ");
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ControlPolicy {
+    /// Tabs move on to the next multiple of this many columns, counting from where printing
+    /// started. With 0, they are shown like other control characters.
+    pub tab_width: usize,
+    pub newline: Newline,
+    pub controls: Controls,
+}
+
+/// What a newline does.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Newline {
+    /// Carry on at the start of the next row, back in the column printing started in. A
+    /// carriage return just before it is dropped.
+    NextRow,
+    /// Show it like other control characters, so that a newline that shouldn't be there
+    /// stands out.
+    Show,
+}
+
+/// How control characters are shown.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Controls {
+    /// In caret notation, taking two cells: `^C` for Ctrl-C, `^?` for DEL. Other controls,
+    /// from the C1 range, are shown as U+FFFD.
+    Caret,
+    /// As the given character.
+    Replace(char),
+}
+
+/// Tabs every 8 columns, as terminals have them, newlines starting the next row, and other
+/// controls in caret notation.
+impl Default for ControlPolicy {
+    fn default() -> ControlPolicy {
+        ControlPolicy { tab_width: 8, newline: Newline::NextRow, controls: Controls::Caret }
+    }
+}
+
+impl ControlPolicy {
+    // Where each character printed for `s` goes, as `(dx, dy, ch)` from where printing starts.
+    // Other characters are placed by their width, like `columns` places them.
+    pub(crate) fn place(&self, s: &str) -> Vec<(usize, usize, char)> {
+        #[cfg(feature = "bidi")]
+        let s = &*visual_order(s);
+        let mut out = Vec::new();
+        let (mut dx, mut dy) = (0, 0);
+        let mut chars = s.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\t' if self.tab_width > 0 => {
+                    dx = (dx / self.tab_width + 1) * self.tab_width;
+                    continue;
+                },
+                '\r' if self.newline == Newline::NextRow && chars.peek() == Some(&'\n') => continue,
+                '\n' if self.newline == Newline::NextRow => {
+                    dx = 0;
+                    dy += 1;
+                    continue;
+                },
+                _ => (),
+            }
+            if !ch.is_control() {
+                let ch_width = char_width(ch);
+                if ch_width > 0 {
+                    out.push((dx, dy, ch));
+                    dx += ch_width;
+                }
+                continue;
+            }
+            match (self.controls, caret(ch)) {
+                (Controls::Caret, Some(c)) => {
+                    out.push((dx, dy, '^'));
+                    out.push((dx + 1, dy, c));
+                    dx += 2;
+                },
+                (Controls::Caret, None) => {
+                    out.push((dx, dy, '\u{fffd}'));
+                    dx += 1;
+                },
+                (Controls::Replace(r), _) => {
+                    out.push((dx, dy, r));
+                    dx += 1;
+                },
+            }
+        }
+        out
+    }
+}
+
+// The letter that follows `^` in the caret notation for `ch`, if it has one.
+fn caret(ch: char) -> Option<char> {
+    match ch as u32 {
+        c @ 0..=0x1f => Some((c as u8 + b'@') as char),
+        0x7f => Some('?'),
+        _ => None,
+    }
+}
+
+/// Put each line of `s` into the order its characters are shown in, by the Unicode
+/// bidirectional algorithm, so that right-to-left scripts such as Arabic and Hebrew read the
+/// right way round, including within left-to-right text and the other way about. Each line's
+/// direction is taken from its first strongly directional character. `print` does this itself
+/// when the `bidi` feature is enabled.
+///
+/// Brackets in right-to-left runs are mirrored, so that they still face the text they enclose,
+/// and combining marks stay after the character they combine with.
+///
+/// ```
+/// use rustbox::text::visual_order;
+///
+/// assert_eq!(visual_order("abc"), "abc");
+/// assert_eq!(visual_order("\u{5e9}\u{5dc}\u{5d5}\u{5dd}"), "\u{5dd}\u{5d5}\u{5dc}\u{5e9}");
+/// assert_eq!(visual_order("see \u{5d0}\u{5d1} here"), "see \u{5d1}\u{5d0} here");
+/// assert_eq!(visual_order("\u{5d0}(\u{5d1})"), "(\u{5d1})\u{5d0}");
+/// ```
+#[cfg(feature = "bidi")]
+pub fn visual_order(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for (i, line) in s.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let info = ParagraphBidiInfo::new(line, None);
+        if !info.has_rtl() {
+            out.push_str(line);
+            continue;
+        }
+        let (levels, runs) = info.visual_runs(0..line.len());
+        for run in runs {
+            let text = &line[run.clone()];
+            if !levels[run.start].is_rtl() {
+                out.push_str(text);
+                continue;
+            }
+            // Reverse whole characters, each with the marks that combine with it.
+            let mut clusters: Vec<String> = Vec::new();
+            for ch in text.chars() {
+                match clusters.last_mut() {
+                    Some(cluster) if char_width(ch) == 0 && !ch.is_control() => cluster.push(ch),
+                    _ => clusters.push(mirror(ch).to_string()),
+                }
+            }
+            for cluster in clusters.iter().rev() {
+                out.push_str(cluster);
+            }
+        }
+    }
+    out
+}
+
+// The mirror image of the brackets `ch` might be, for rule L4 of the bidirectional algorithm.
+#[cfg(feature = "bidi")]
+fn mirror(ch: char) -> char {
+    const PAIRS: &[(char, char)] = &[
+        ('(', ')'), ('[', ']'), ('{', '}'), ('<', '>'), ('\u{ab}', '\u{bb}'),
+        ('\u{2039}', '\u{203a}'), ('\u{2264}', '\u{2265}'), ('\u{3008}', '\u{3009}'),
+        ('\u{300a}', '\u{300b}'), ('\u{300c}', '\u{300d}'), ('\u{3010}', '\u{3011}'),
+    ];
+    for &(open, close) in PAIRS {
+        if ch == open {
+            return close;
+        }
+        if ch == close {
+            return open;
+        }
+    }
+    ch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{layout, truncate, wrap};
+    use Alignment;
+
+    fn strings(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn wrap_between_words() {
+        assert_eq!(wrap("the quick  brown\tfox", 9), strings(&["the quick", "brown fox"]));
+        assert_eq!(wrap("a\n\nb ", 5), strings(&["a", "", "b"]));
+        assert_eq!(wrap("", 5), strings(&[""]));
+        assert_eq!(wrap("   ", 5), strings(&[""]));
+    }
+
+    #[test]
+    fn wrap_long_words() {
+        assert_eq!(wrap("abcdefg", 3), strings(&["abc", "def", "g"]));
+        assert_eq!(wrap("ab abcdefg h", 3), strings(&["ab", "abc", "def", "g h"]));
+        assert_eq!(wrap("漢字漢", 4), strings(&["漢字", "漢"]));
+        assert_eq!(wrap("漢字漢", 3), strings(&["漢", "字", "漢"]));
+    }
+
+    #[test]
+    fn wrap_narrow() {
+        assert!(wrap("abc", 0).is_empty());
+        assert_eq!(wrap("ab", 1), strings(&["a", "b"]));
+        assert_eq!(wrap("漢a", 1), strings(&["漢", "a"]));
+    }
+
+    #[test]
+    fn truncate_to_width() {
+        assert_eq!(truncate("abc", 3), "abc");
+        assert_eq!(truncate("abcd", 3), "ab…");
+        assert_eq!(truncate("abc", 1), "…");
+        assert_eq!(truncate("abc", 0), "");
+        assert_eq!(truncate("", 0), "");
+        assert_eq!(truncate("漢字", 3), "漢…");
+        assert_eq!(truncate("漢字", 2), "…");
+    }
+
+    #[test]
+    fn layout_in_a_box() {
+        assert_eq!(layout("ab cd", 4, 2, Alignment::Right, false), vec![(2, "ab".to_string()), (2, "cd".to_string())]);
+        assert_eq!(layout("a bcd", 5, 1, Alignment::Center, false), vec![(0, "a bcd".to_string())]);
+        assert_eq!(layout("a b", 4, 1, Alignment::Center, false), vec![(0, "a b".to_string())]);
+        assert_eq!(layout("ab", 5, 1, Alignment::Center, false), vec![(1, "ab".to_string())]);
+    }
+
+    #[test]
+    fn layout_cut_short() {
+        assert_eq!(layout("ab cd ef", 3, 2, Alignment::Left, false), vec![(0, "ab".to_string()), (0, "cd".to_string())]);
+        // The last line fits, but still shows that there's more.
+        assert_eq!(layout("ab cd ef", 3, 2, Alignment::Left, true), vec![(0, "ab".to_string()), (0, "cd…".to_string())]);
+        assert_eq!(layout("abc def", 3, 1, Alignment::Right, true), vec![(0, "ab…".to_string())]);
+        assert!(layout("ab cd", 3, 0, Alignment::Left, true).is_empty());
+        assert!(layout("ab cd", 0, 3, Alignment::Left, true).is_empty());
+        assert_eq!(layout("ab cd", 1, 1, Alignment::Left, true), vec![(0, "…".to_string())]);
+    }
+}
@@ -0,0 +1,149 @@
+//! Colors chosen by what they are for rather than what they look like.
+//!
+//! Drawing code asks for a `Role`, such as `Role::Error`, and the `Theme` in use decides what
+//! that looks like, so an application can offer light and dark variants, or let users supply
+//! their own, without raw colors spread through its drawing code.
+//!
+//! ```
+//! use rustbox::Color;
+//! use rustbox::text::Attr;
+//! use rustbox::theme::{Role, Theme};
+//!
+//! let mut theme = Theme::dark();
+//! theme.set(Role::Accent, Attr::new(Color::Yellow, Color::Black, rustbox::RB_BOLD));
+//! assert_eq!(theme.get(Role::Accent).fg, Color::Yellow);
+//! assert_eq!(theme.get(Role::Text).bg, Color::Black);
+//! ```
+
+use super::{Color, RB_BOLD, RB_NORMAL};
+use text::Attr;
+
+/// What a piece of the interface is for.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum Role {
+    /// Empty space, and what the screen is cleared with.
+    Background,
+    /// Ordinary text.
+    Text,
+    /// Text that should stand out, such as headings and key hints.
+    Accent,
+    Error,
+    /// The selected item in a list, or selected text.
+    Selection,
+}
+
+/// Whether the terminal's background is light or dark, as worked out by
+/// `RustBox::background_kind`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BackgroundKind {
+    Light,
+    Dark,
+    Unknown,
+}
+
+impl BackgroundKind {
+    // Light if the color's relative luminance is over half.
+    pub(crate) fn from_rgb((r, g, b): (u8, u8, u8)) -> BackgroundKind {
+        let luminance = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+        if luminance > 127.5 { BackgroundKind::Light } else { BackgroundKind::Dark }
+    }
+
+    // Some terminals, rxvt and Konsole among them, set `COLORFGBG` to `fg;bg` (or
+    // `fg;default;bg`) as numbers from the 16 color palette. White (7) and the bright colors
+    // other than grey (8) are light.
+    pub(crate) fn from_colorfgbg(value: &str) -> BackgroundKind {
+        match value.rsplit(';').next().and_then(|bg| bg.parse::<u8>().ok()) {
+            Some(7) | Some(9..=15) => BackgroundKind::Light,
+            Some(0..=6) | Some(8) => BackgroundKind::Dark,
+            _ => BackgroundKind::Unknown,
+        }
+    }
+}
+
+/// The attributes to draw each `Role` with.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Theme {
+    pub background: Attr,
+    pub text: Attr,
+    pub accent: Attr,
+    pub error: Attr,
+    pub selection: Attr,
+}
+
+impl Theme {
+    /// Light text on a black background.
+    pub fn dark() -> Theme {
+        Theme {
+            background: Attr::new(Color::White, Color::Black, RB_NORMAL),
+            text: Attr::new(Color::White, Color::Black, RB_NORMAL),
+            accent: Attr::new(Color::Cyan, Color::Black, RB_BOLD),
+            error: Attr::new(Color::Red, Color::Black, RB_BOLD),
+            selection: Attr::new(Color::White, Color::Blue, RB_NORMAL),
+        }
+    }
+
+    /// Dark text on a white background.
+    pub fn light() -> Theme {
+        Theme {
+            background: Attr::new(Color::Black, Color::White, RB_NORMAL),
+            text: Attr::new(Color::Black, Color::White, RB_NORMAL),
+            accent: Attr::new(Color::Blue, Color::White, RB_BOLD),
+            error: Attr::new(Color::Red, Color::White, RB_BOLD),
+            selection: Attr::new(Color::Black, Color::Cyan, RB_NORMAL),
+        }
+    }
+
+    /// `light` or `dark` to suit the terminal's background, or the default theme if it isn't
+    /// known.
+    ///
+    /// ```no_run
+    /// use rustbox::RustBox;
+    /// use rustbox::theme::Theme;
+    ///
+    /// let rb = RustBox::init(Default::default()).unwrap();
+    /// rb.set_theme(Theme::for_background(rb.background_kind()));
+    /// ```
+    pub fn for_background(kind: BackgroundKind) -> Theme {
+        match kind {
+            BackgroundKind::Light => Theme::light(),
+            BackgroundKind::Dark => Theme::dark(),
+            BackgroundKind::Unknown => Theme::default(),
+        }
+    }
+
+    pub fn get(&self, role: Role) -> Attr {
+        match role {
+            Role::Background => self.background,
+            Role::Text => self.text,
+            Role::Accent => self.accent,
+            Role::Error => self.error,
+            Role::Selection => self.selection,
+        }
+    }
+
+    pub fn set(&mut self, role: Role, attr: Attr) {
+        match role {
+            Role::Background => self.background = attr,
+            Role::Text => self.text = attr,
+            Role::Accent => self.accent = attr,
+            Role::Error => self.error = attr,
+            Role::Selection => self.selection = attr,
+        }
+    }
+}
+
+/// The terminal's own colors, with roles set apart by style and errors in red, so it suits
+/// light and dark terminals alike.
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            background: Attr::default(),
+            text: Attr::default(),
+            accent: Attr::default().bold(),
+            error: Attr::from(Color::Red).bold(),
+            selection: Attr::default().reverse(),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Direct access to the controlling terminal, for the few things termbox doesn't do for us.
+//
+// termbox opens its own handle on /dev/tty and only writes to it from tb_present and friends, so
+// as long as we hold the output lock we can write escape sequences through a second handle
+// without them being interleaved with termbox's output. Terminal state such as the window size
+// is shared by every handle on the same device.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::time::{Duration, Instant};
+
+use libc;
+
+use probe;
+
+const TTY_PATH: &str = "/dev/tty";
+
+pub struct Tty {
+    file: File,
+    budget: probe::Budget,
+}
+
+impl Tty {
+    pub fn open() -> io::Result<Tty> {
+        let file = OpenOptions::new().read(true).write(true).open(TTY_PATH)?;
+        Ok(Tty { file, budget: probe::Budget::new() })
+    }
+
+    pub fn write(&self, bytes: &[u8]) -> io::Result<()> {
+        let mut file = &self.file;
+        file.write_all(bytes)?;
+        file.flush()
+    }
+
+    /// Send `request` and collect the terminal's reply until `done` says it is complete.
+    ///
+    /// This reads from the terminal directly, so the caller must hold the input lock to keep
+    /// termbox from reading at the same time. Any input that arrives before the reply is
+    /// consumed along with it.
+    ///
+    /// Nothing is sent to dumb terminals, and once queries have gone unanswered for long
+    /// enough in all, nothing more is sent at all. See `probe::Budget`.
+    pub fn query<F>(&self, request: &[u8], timeout: Duration, done: F) -> io::Result<Vec<u8>>
+        where F: Fn(&[u8]) -> bool
+    {
+        let timeout = self.budget.allow(timeout)?;
+        self.write(request)?;
+
+        let deadline = Instant::now() + timeout;
+        let mut reply = Vec::new();
+        let mut buf = [0; 64];
+        while !done(&reply) {
+            let now = Instant::now();
+            if now >= deadline || !self.wait_readable(deadline - now)? {
+                self.budget.unanswered(timeout);
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "terminal did not reply"));
+            }
+            let n = (&self.file).read(&mut buf)?;
+            reply.extend_from_slice(&buf[..n]);
+        }
+        Ok(reply)
+    }
+
+    fn wait_readable(&self, timeout: Duration) -> io::Result<bool> {
+        let mut fd = libc::pollfd { fd: self.file.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        let ms = timeout.as_secs() * 1000 + timeout.subsec_nanos() as u64 / 1000000;
+        match unsafe { libc::poll(&mut fd, 1, ms as libc::c_int) } {
+            -1 => Err(io::Error::last_os_error()),
+            0 => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    /// Fails with `BrokenPipe` if the terminal has hung up, e.g. because the terminal emulator
+    /// or ssh session it belongs to has gone away.
+    pub fn check_open(&self) -> io::Result<()> {
+        let mut fd = libc::pollfd { fd: self.file.as_raw_fd(), events: 0, revents: 0 };
+        if unsafe { libc::poll(&mut fd, 1, 0) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        if fd.revents & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) != 0 {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "the terminal has been closed"));
+        }
+        Ok(())
+    }
+
+    /// The size of the terminal in columns and rows, as reported by the kernel.
+    pub fn size(&self) -> io::Result<(usize, usize)> {
+        let ws = self.winsize()?;
+        Ok((ws.ws_col as usize, ws.ws_row as usize))
+    }
+
+    /// The size of the terminal in pixels. Many terminals don't fill this in, in which case it
+    /// is `(0, 0)`.
+    pub fn pixel_size(&self) -> io::Result<(usize, usize)> {
+        let ws = self.winsize()?;
+        Ok((ws.ws_xpixel as usize, ws.ws_ypixel as usize))
+    }
+
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+
+    pub fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+
+    fn winsize(&self) -> io::Result<libc::winsize> {
+        let mut ws: libc::winsize = unsafe { mem::zeroed() };
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), libc::TIOCGWINSZ, &mut ws) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ws)
+    }
+}
@@ -0,0 +1,923 @@
+//! A few ready-made widgets, drawn with `Draw::draw`.
+//!
+//! ```
+//! use rustbox::{Draw, Key, Rect, TestBackend};
+//! use rustbox::widgets::{Gauge, List};
+//!
+//! let mut rb = TestBackend::new(6, 3);
+//! let mut list = List::new(vec!["one", "two", "three"]);
+//! list.handle_key(Key::Down);
+//! list.draw(&mut rb, Rect::new(0, 0, 6, 2));
+//! Gauge::new(0.5).draw(&mut rb, Rect::new(0, 2, 6, 1));
+//! rb.present().unwrap();
+//! assert_eq!(list.selected(), Some(1));
+//! assert_eq!(rb.buffer_as_string(), "one   \ntwo   \n      ");
+//! ```
+
+use std::cell::Cell as Shared;
+use std::cmp;
+use std::mem;
+
+use super::{Alignment, Cell, Draw, Key, Mouse, Rect, Surface, Text};
+use text::{self, Attr};
+
+// How far a turn of the mouse wheel scrolls.
+const WHEEL_LINES: usize = 3;
+
+// Draw the characters of a line starting `x` columns into `area` on row `y`, cutting off
+// anything that doesn't fit.
+fn put_line(target: &mut dyn Surface, area: Rect, x: usize, y: usize, s: &str, attr: Attr) {
+    for (dx, ch) in text::columns(s) {
+        let col = x + dx;
+        if col + text::char_width(ch) > area.w {
+            break;
+        }
+        target.set_cell(area.x + col, area.y + y, Cell::new(ch).attr(attr));
+    }
+}
+
+// Like `put_line`, but starting `scroll` columns into `s`. Wide characters cut in half by
+// either edge are left out.
+fn put_scrolled(target: &mut dyn Surface, area: Rect, y: usize, s: &str, scroll: usize, attr: Attr) {
+    for (x, ch) in text::columns(s) {
+        if x < scroll {
+            continue;
+        }
+        if x + text::char_width(ch) > scroll + area.w {
+            break;
+        }
+        target.set_cell(area.x + x - scroll, area.y + y, Cell::new(ch).attr(attr));
+    }
+}
+
+/// A line of styled text, aligned within the area it is drawn in and cut off at its edge.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Label {
+    text: Text,
+    align: Alignment,
+}
+
+impl Label {
+    pub fn new(text: Text) -> Label {
+        Label { text, align: Alignment::Left }
+    }
+
+    pub fn align(self, align: Alignment) -> Label {
+        Label { align, ..self }
+    }
+}
+
+impl Draw for Label {
+    fn draw(&self, target: &mut dyn Surface, area: Rect) {
+        if area.is_empty() {
+            return;
+        }
+        let room = area.w.saturating_sub(self.text.width());
+        let mut x = match self.align {
+            Alignment::Left => 0,
+            Alignment::Center => room / 2,
+            Alignment::Right => room,
+        };
+        for span in self.text.spans() {
+            put_line(target, area, x, 0, &span.text, span.attr);
+            x += text::width(&span.text);
+        }
+    }
+}
+
+/// A list of items, one per row, of which one may be selected.
+///
+/// When drawn, the list scrolls just far enough to show the selected item, and fills the rest
+/// of its row with the highlight attributes. `handle_key` moves the selection with the arrow,
+/// Home, End and Page keys.
+#[derive(Clone, Debug)]
+pub struct List {
+    items: Vec<String>,
+    selected: Option<usize>,
+    attr: Attr,
+    highlight: Attr,
+    // The first item shown and the number of rows drawn last time, kept up to date by `draw`.
+    offset: Shared<usize>,
+    rows: Shared<usize>,
+}
+
+impl List {
+    /// A list of `items`, with the first one selected if there are any.
+    pub fn new<I, S>(items: I) -> List
+        where I: IntoIterator<Item = S>, S: Into<String>
+    {
+        let items: Vec<String> = items.into_iter().map(Into::into).collect();
+        List {
+            selected: if items.is_empty() { None } else { Some(0) },
+            items,
+            attr: Attr::default(),
+            highlight: Attr::default().reverse(),
+            offset: Shared::new(0),
+            rows: Shared::new(1),
+        }
+    }
+
+    /// The attributes of the items that aren't selected.
+    pub fn attr(self, attr: Attr) -> List {
+        List { attr, ..self }
+    }
+
+    /// The attributes of the selected item. Reverse video by default.
+    pub fn highlight(self, highlight: Attr) -> List {
+        List { highlight, ..self }
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// Replace the items, keeping the selection where it was as far as that's possible.
+    pub fn set_items<I, S>(&mut self, items: I)
+        where I: IntoIterator<Item = S>, S: Into<String>
+    {
+        self.items = items.into_iter().map(Into::into).collect();
+        let selected = self.selected.unwrap_or(0);
+        self.select(Some(selected));
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Select item `index`, or the last one if there aren't that many, or none at all.
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = match index {
+            Some(_) if self.items.is_empty() => None,
+            Some(i) => Some(cmp::min(i, self.items.len() - 1)),
+            None => None,
+        };
+    }
+
+    /// Move the selection down by `n` items, stopping at the last one.
+    pub fn select_next(&mut self, n: usize) {
+        let next = self.selected.map_or(0, |i| i.saturating_add(n));
+        self.select(Some(next));
+    }
+
+    /// Move the selection up by `n` items, stopping at the first one.
+    pub fn select_previous(&mut self, n: usize) {
+        let previous = self.selected.map_or(0, |i| i.saturating_sub(n));
+        self.select(Some(previous));
+    }
+
+    /// Move the selection for `key`, returning whether it was one the list handles. Page Up
+    /// and Page Down move by the number of rows the list was last drawn with.
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        let page = cmp::max(self.rows.get(), 1);
+        match key {
+            Key::Up => self.select_previous(1),
+            Key::Down => self.select_next(1),
+            Key::PageUp => self.select_previous(page),
+            Key::PageDown => self.select_next(page),
+            Key::Home => self.select(Some(0)),
+            Key::End => self.select(Some(usize::MAX)),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Move the selection for the mouse wheel, returning whether `mouse` was the wheel turning.
+    /// Whether the mouse is over the list is up to the caller.
+    pub fn handle_mouse(&mut self, mouse: Mouse) -> bool {
+        match mouse {
+            Mouse::WheelUp => self.select_previous(WHEEL_LINES),
+            Mouse::WheelDown => self.select_next(WHEEL_LINES),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Draw for List {
+    fn draw(&self, target: &mut dyn Surface, area: Rect) {
+        self.rows.set(area.h);
+        if area.is_empty() {
+            return;
+        }
+        let mut offset = cmp::min(self.offset.get(), self.items.len().saturating_sub(1));
+        if let Some(selected) = self.selected {
+            if selected < offset {
+                offset = selected;
+            } else if selected >= offset + area.h {
+                offset = selected + 1 - area.h;
+            }
+        }
+        self.offset.set(offset);
+
+        for row in 0..area.h {
+            let index = offset + row;
+            let attr = if Some(index) == self.selected { self.highlight } else { self.attr };
+            target.fill_rect(Rect::new(area.x, area.y + row, area.w, 1), Cell::new(' ').attr(attr));
+            if let Some(item) = self.items.get(index) {
+                put_line(target, area, 0, row, &text::truncate(item, area.w), attr);
+            }
+        }
+    }
+}
+
+/// A progress bar, filled from the left in proportion to how far along it is, with an
+/// optional label in the middle.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Gauge {
+    ratio: f64,
+    label: Option<String>,
+    filled: Attr,
+    empty: Attr,
+}
+
+impl Gauge {
+    /// A gauge `ratio` of the way full, where 1.0 is full. The ratio is clamped to 0.0..=1.0.
+    pub fn new(ratio: f64) -> Gauge {
+        Gauge {
+            ratio: if ratio.is_nan() { 0.0 } else { ratio.clamp(0.0, 1.0) },
+            label: None,
+            filled: Attr::default().reverse(),
+            empty: Attr::default(),
+        }
+    }
+
+    pub fn label<S: Into<String>>(self, label: S) -> Gauge {
+        Gauge { label: Some(label.into()), ..self }
+    }
+
+    /// The attributes of the filled part, which is drawn with spaces. Reverse video by
+    /// default.
+    pub fn filled(self, filled: Attr) -> Gauge {
+        Gauge { filled, ..self }
+    }
+
+    pub fn empty(self, empty: Attr) -> Gauge {
+        Gauge { empty, ..self }
+    }
+}
+
+impl Draw for Gauge {
+    fn draw(&self, target: &mut dyn Surface, area: Rect) {
+        if area.is_empty() {
+            return;
+        }
+        let filled = (area.w as f64 * self.ratio).round() as usize;
+        let label = self.label.as_ref().map(|label| text::truncate(label, area.w)).unwrap_or_default();
+        let start = (area.w - text::width(&label)) / 2;
+        // `None` marks the second column of a wide character, which mustn't be drawn over.
+        let mut chars = vec![Some(' '); area.w];
+        for (dx, ch) in text::columns(&label) {
+            chars[start + dx] = Some(ch);
+            if text::char_width(ch) == 2 {
+                chars[start + dx + 1] = None;
+            }
+        }
+        let row = area.y + area.h / 2;
+        for (x, &slot) in chars.iter().enumerate() {
+            let attr = if x < filled { self.filled } else { self.empty };
+            for y in area.y..area.bottom() {
+                let ch = match slot {
+                    _ if y != row => ' ',
+                    Some(ch) => ch,
+                    None => continue,
+                };
+                target.set_cell(area.x + x, y, Cell::new(ch).attr(attr));
+            }
+        }
+    }
+}
+
+/// A single line of editable text, such as a prompt or a search box.
+///
+/// Keys are fed to it with `handle_key`. Besides typing, it understands the arrow keys, Home
+/// and End, Backspace and Delete, and the readline shortcuts Ctrl-A, Ctrl-E, Ctrl-K, Ctrl-U and
+/// Ctrl-W. Enter is left to the caller, which usually wants to `submit` the line. When the text
+/// is wider than the area it is drawn in, it scrolls sideways to keep the cursor in view.
+///
+/// ```
+/// use rustbox::{Draw, Key, Rect, TestBackend};
+/// use rustbox::widgets::LineEdit;
+///
+/// let mut rb = TestBackend::new(4, 1);
+/// let mut edit = LineEdit::new().history(100);
+/// for ch in "hello".chars() {
+///     edit.handle_key(Key::Char(ch));
+/// }
+/// edit.handle_key(Key::Backspace);
+/// edit.draw(&mut rb, Rect::new(0, 0, 4, 1));
+/// rb.present().unwrap();
+/// // The text is scrolled so that the cursor, after the last character, stays in view.
+/// assert_eq!(rb.buffer_as_string(), "ell ");
+/// assert_eq!(edit.cursor_position(Rect::new(0, 0, 4, 1)), (3, 0));
+///
+/// assert_eq!(edit.submit(), "hell");
+/// edit.handle_key(Key::Up);
+/// assert_eq!(edit.text(), "hell");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LineEdit {
+    text: String,
+    // A byte offset into `text`, always on a character boundary.
+    cursor: usize,
+    attr: Attr,
+    history: Vec<String>,
+    history_limit: usize,
+    // Which history entry is being shown, and the line that was being edited before Up was
+    // first pressed.
+    history_pos: Option<usize>,
+    draft: String,
+    // The first column shown, kept up to date by `draw`.
+    scroll: Shared<usize>,
+}
+
+impl LineEdit {
+    pub fn new() -> LineEdit {
+        Default::default()
+    }
+
+    pub fn attr(self, attr: Attr) -> LineEdit {
+        LineEdit { attr, ..self }
+    }
+
+    /// Remember up to `limit` submitted lines, to be brought back with Up and Down. History is
+    /// off (a limit of 0) by default.
+    pub fn history(self, limit: usize) -> LineEdit {
+        LineEdit { history_limit: limit, ..self }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, putting the cursor at the end.
+    pub fn set_text<S: Into<String>>(&mut self, text: S) {
+        self.text = text.into();
+        self.cursor = self.text.len();
+    }
+
+    /// The cursor's position, as a byte offset into `text`.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Take the text, leaving the line empty, and add it to the history if that's on.
+    pub fn submit(&mut self) -> String {
+        let text = mem::take(&mut self.text);
+        self.cursor = 0;
+        self.history_pos = None;
+        if self.history_limit > 0 && !text.is_empty() && self.history.last() != Some(&text) {
+            self.history.push(text.clone());
+            if self.history.len() > self.history_limit {
+                self.history.remove(0);
+            }
+        }
+        text
+    }
+
+    /// Edit the line for `key`, returning whether it was one the line handles.
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        match key {
+            Key::Char(ch) => {
+                self.text.insert(self.cursor, ch);
+                self.cursor += ch.len_utf8();
+            },
+            Key::Backspace | Key::Ctrl('h') => {
+                let start = self.previous(self.cursor);
+                self.text.drain(start..self.cursor);
+                self.cursor = start;
+            },
+            Key::Delete | Key::Ctrl('d') => {
+                let end = self.next(self.cursor);
+                self.text.drain(self.cursor..end);
+            },
+            Key::Left | Key::Ctrl('b') => self.cursor = self.previous(self.cursor),
+            Key::Right | Key::Ctrl('f') => self.cursor = self.next(self.cursor),
+            Key::Home | Key::Ctrl('a') => self.cursor = 0,
+            Key::End | Key::Ctrl('e') => self.cursor = self.text.len(),
+            Key::Ctrl('k') => self.text.truncate(self.cursor),
+            Key::Ctrl('u') => {
+                self.text.drain(..self.cursor);
+                self.cursor = 0;
+            },
+            Key::Ctrl('w') => {
+                let before = &self.text[..self.cursor];
+                let start = before.trim_end().char_indices().rev().find(|&(_, ch)| ch.is_whitespace())
+                    .map_or(0, |(i, ch)| i + ch.len_utf8());
+                self.text.drain(start..self.cursor);
+                self.cursor = start;
+            },
+            Key::Up if self.history_limit > 0 => {
+                let pos = match self.history_pos {
+                    _ if self.history.is_empty() => return true,
+                    Some(pos) => pos.saturating_sub(1),
+                    None => {
+                        self.draft = self.text.clone();
+                        self.history.len() - 1
+                    },
+                };
+                self.history_pos = Some(pos);
+                let text = self.history[pos].clone();
+                self.set_text(text);
+            },
+            Key::Down if self.history_limit > 0 => {
+                match self.history_pos {
+                    Some(pos) if pos + 1 < self.history.len() => {
+                        self.history_pos = Some(pos + 1);
+                        let text = self.history[pos + 1].clone();
+                        self.set_text(text);
+                    },
+                    Some(_) => {
+                        self.history_pos = None;
+                        let draft = mem::take(&mut self.draft);
+                        self.set_text(draft);
+                    },
+                    None => (),
+                }
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    // The character boundaries either side of `i`.
+    fn previous(&self, i: usize) -> usize {
+        self.text[..i].char_indices().next_back().map_or(0, |(j, _)| j)
+    }
+
+    fn next(&self, i: usize) -> usize {
+        self.text[i..].chars().next().map_or(i, |ch| i + ch.len_utf8())
+    }
+
+    // The column the cursor is in, counted from the start of the text.
+    fn cursor_column(&self) -> usize {
+        text::width(&self.text[..self.cursor])
+    }
+
+    /// Where the cursor should be shown on screen when the line has been drawn in `area`, for
+    /// passing to `RustBox::set_cursor`.
+    pub fn cursor_position(&self, area: Rect) -> (usize, usize) {
+        let column = self.cursor_column().saturating_sub(self.scroll.get());
+        (area.x + cmp::min(column, area.w.saturating_sub(1)), area.y)
+    }
+}
+
+impl Draw for LineEdit {
+    fn draw(&self, target: &mut dyn Surface, area: Rect) {
+        if area.is_empty() {
+            return;
+        }
+        // Scroll just far enough to keep the cursor, and the column after it, in view.
+        let column = self.cursor_column();
+        let mut scroll = self.scroll.get();
+        if column < scroll {
+            scroll = column;
+        } else if column >= scroll + area.w {
+            scroll = column + 1 - area.w;
+        }
+        self.scroll.set(scroll);
+
+        let attr = self.attr;
+        target.fill_rect(Rect::new(area.x, area.y, area.w, 1), Cell::new(' ').attr(attr));
+        put_scrolled(target, area, 0, &self.text, scroll, attr);
+    }
+}
+
+/// A scrolling view of a long text, for pagers and log viewers.
+///
+/// The text is either given up front or fetched a line at a time from a callback, and only the
+/// lines that are on screen are laid out when it is drawn, so it can be as long as it likes.
+/// Long lines are either wrapped, breaking between characters so that spacing is kept, or cut
+/// off and scrolled sideways.
+///
+/// ```
+/// use rustbox::{Draw, Key, Rect, TestBackend};
+/// use rustbox::widgets::TextView;
+///
+/// let mut rb = TestBackend::new(4, 2);
+/// let mut view = TextView::from_lines(1000, |i| format!("{}", i * 100));
+/// view.draw(&mut rb, Rect::new(0, 0, 4, 2));
+/// view.handle_key(Key::End);
+/// view.draw(&mut rb, Rect::new(0, 0, 4, 2));
+/// rb.present().unwrap();
+/// assert_eq!(rb.buffer_as_string(), "9980\n9990");
+///
+/// view.set_wrap(true);
+/// view.draw(&mut rb, Rect::new(0, 0, 4, 2));
+/// rb.present().unwrap();
+/// assert_eq!(rb.buffer_as_string(), "9980\n0   ");
+/// ```
+pub struct TextView {
+    source: Source,
+    wrap: bool,
+    attr: Attr,
+    // The first line shown, and the first of its wrapped rows shown. `draw` keeps it in range
+    // for the size it draws at.
+    top: Shared<(usize, usize)>,
+    // How far the lines are scrolled sideways when not wrapping.
+    column: usize,
+    // The size of the area last drawn in, kept up to date by `draw`.
+    size: Shared<(usize, usize)>,
+}
+
+enum Source {
+    Lines(Vec<String>),
+    Callback(usize, Box<dyn Fn(usize) -> String>),
+}
+
+impl TextView {
+    /// A view of `text`, split into lines at each `\n`.
+    pub fn new(text: &str) -> TextView {
+        TextView::with_source(Source::Lines(text.lines().map(str::to_string).collect()))
+    }
+
+    /// A view of `len` lines, each of which is fetched with `line` when it is needed.
+    pub fn from_lines<F>(len: usize, line: F) -> TextView
+        where F: Fn(usize) -> String + 'static
+    {
+        TextView::with_source(Source::Callback(len, Box::new(line)))
+    }
+
+    fn with_source(source: Source) -> TextView {
+        TextView {
+            source,
+            wrap: false,
+            attr: Attr::default(),
+            top: Shared::new((0, 0)),
+            column: 0,
+            size: Shared::new((80, 24)),
+        }
+    }
+
+    pub fn attr(self, attr: Attr) -> TextView {
+        TextView { attr, ..self }
+    }
+
+    /// Wrap long lines instead of cutting them off. Off by default.
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        self.column = 0;
+        self.top.set((self.top.get().0, 0));
+        self.clamp();
+    }
+
+    /// The number of lines.
+    pub fn len(&self) -> usize {
+        match self.source {
+            Source::Lines(ref lines) => lines.len(),
+            Source::Callback(len, _) => len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The first line shown.
+    pub fn top_line(&self) -> usize {
+        self.top.get().0
+    }
+
+    /// Scroll so that `line` is at the top, or as near as it can be.
+    pub fn scroll_to(&mut self, line: usize) {
+        self.top.set((line, 0));
+        self.clamp();
+    }
+
+    pub fn scroll_down(&mut self, rows: usize) {
+        if self.is_empty() {
+            return;
+        }
+        for _ in 0..rows {
+            let (line, row) = self.top.get();
+            if row + 1 < self.rows(line).len() {
+                self.top.set((line, row + 1));
+            } else if line + 1 < self.len() {
+                self.top.set((line + 1, 0));
+            } else {
+                break;
+            }
+        }
+        self.clamp();
+    }
+
+    pub fn scroll_up(&mut self, rows: usize) {
+        for _ in 0..rows {
+            let top = match self.top.get() {
+                (line, row) if row > 0 => (line, row - 1),
+                (line, _) if line > 0 => (line - 1, self.rows(line - 1).len() - 1),
+                _ => break,
+            };
+            self.top.set(top);
+        }
+    }
+
+    /// Scroll sideways by `columns`, to the right if it is positive. Does nothing when wrapping.
+    pub fn scroll_right(&mut self, columns: isize) {
+        if !self.wrap {
+            self.column = if columns < 0 {
+                self.column.saturating_sub(columns.unsigned_abs())
+            } else {
+                self.column.saturating_add(columns as usize)
+            };
+        }
+    }
+
+    /// Scroll for `key`, returning whether it was one the view handles: the arrow keys, Page
+    /// Up and Page Down, Home and End.
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        let (width, height) = self.size.get();
+        match key {
+            Key::Up => self.scroll_up(1),
+            Key::Down => self.scroll_down(1),
+            Key::PageUp => self.scroll_up(cmp::max(height, 1)),
+            Key::PageDown => self.scroll_down(cmp::max(height, 1)),
+            Key::Home => self.top.set((0, 0)),
+            Key::End => self.top.set(self.bottom()),
+            Key::Left => self.scroll_right(-(cmp::max(width / 2, 1) as isize)),
+            Key::Right => self.scroll_right(cmp::max(width / 2, 1) as isize),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Scroll for the mouse wheel, returning whether `mouse` was the wheel turning.
+    /// Whether the mouse is over the view is up to the caller.
+    pub fn handle_mouse(&mut self, mouse: Mouse) -> bool {
+        match mouse {
+            Mouse::WheelUp => self.scroll_up(WHEEL_LINES),
+            Mouse::WheelDown => self.scroll_down(WHEEL_LINES),
+            _ => return false,
+        }
+        true
+    }
+
+    fn line(&self, i: usize) -> String {
+        match self.source {
+            Source::Lines(ref lines) => lines[i].clone(),
+            Source::Callback(_, ref line) => line(i),
+        }
+    }
+
+    // The rows line `i` takes up at the width last drawn with.
+    fn rows(&self, i: usize) -> Vec<String> {
+        let line = self.line(i);
+        let width = self.size.get().0;
+        if !self.wrap || width == 0 {
+            return vec![line];
+        }
+        let mut rows = vec![String::new()];
+        let mut used = 0;
+        for ch in line.chars() {
+            let ch_width = text::char_width(ch);
+            if used + ch_width > width && used > 0 {
+                rows.push(String::new());
+                used = 0;
+            }
+            rows.last_mut().unwrap().push(ch);
+            used += ch_width;
+        }
+        rows
+    }
+
+    // The furthest the view can be scrolled down, with the last row at the bottom.
+    fn bottom(&self) -> (usize, usize) {
+        let height = cmp::max(self.size.get().1, 1);
+        let mut top = (0, 0);
+        let mut rows = 0;
+        for line in (0..self.len()).rev() {
+            let n = self.rows(line).len();
+            if rows + n >= height {
+                return (line, rows + n - height);
+            }
+            rows += n;
+            top = (line, 0);
+        }
+        top
+    }
+
+    // Don't leave the view scrolled past the end, e.g. after the size or the wrapping changed.
+    fn clamp(&self) {
+        if self.is_empty() {
+            self.top.set((0, 0));
+            return;
+        }
+        let (mut line, mut row) = self.top.get();
+        if line >= self.len() {
+            line = self.len() - 1;
+            row = 0;
+        }
+        row = cmp::min(row, self.rows(line).len() - 1);
+        self.top.set(cmp::min((line, row), self.bottom()));
+    }
+}
+
+impl Draw for TextView {
+    fn draw(&self, target: &mut dyn Surface, area: Rect) {
+        if self.size.get() != (area.w, area.h) {
+            self.size.set((area.w, area.h));
+        }
+        self.clamp();
+        let attr = self.attr;
+        target.fill_rect(area, Cell::new(' ').attr(attr));
+        if area.is_empty() || self.is_empty() {
+            return;
+        }
+        let (mut line, mut skip) = self.top.get();
+        let mut y = 0;
+        while y < area.h && line < self.len() {
+            let rows = self.rows(line);
+            for row in rows.iter().skip(skip).take(area.h - y) {
+                put_scrolled(target, area, y, row, self.column, attr);
+                y += 1;
+            }
+            line += 1;
+            skip = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineEdit, TextView};
+    use {Draw, Key, Mouse, Rect, TestBackend};
+
+    fn typed(text: &str) -> LineEdit {
+        let mut edit = LineEdit::new().history(2);
+        for ch in text.chars() {
+            edit.handle_key(Key::Char(ch));
+        }
+        edit
+    }
+
+    #[test]
+    fn line_edit_ctrl_w() {
+        let mut edit = typed("a\u{3000}b\u{3000}");
+        edit.handle_key(Key::Ctrl('w'));
+        assert_eq!(edit.text(), "a\u{3000}");
+        assert_eq!(edit.cursor(), edit.text().len());
+        edit.handle_key(Key::Ctrl('w'));
+        assert_eq!(edit.text(), "");
+
+        let mut edit = typed("foo  bar  baz");
+        edit.handle_key(Key::Left);
+        edit.handle_key(Key::Left);
+        edit.handle_key(Key::Ctrl('w'));
+        assert_eq!((edit.text(), edit.cursor()), ("foo  bar  az", 10));
+        edit.handle_key(Key::Ctrl('w'));
+        assert_eq!((edit.text(), edit.cursor()), ("foo  az", 5));
+    }
+
+    #[test]
+    fn line_edit_multibyte_editing() {
+        let mut edit = typed("漢字é");
+        edit.handle_key(Key::Left);
+        edit.handle_key(Key::Backspace);
+        assert_eq!((edit.text(), edit.cursor()), ("漢é", 3));
+        edit.handle_key(Key::Delete);
+        assert_eq!(edit.text(), "漢");
+        edit.handle_key(Key::Delete);
+        edit.handle_key(Key::Right);
+        assert_eq!((edit.text(), edit.cursor()), ("漢", 3));
+        edit.handle_key(Key::Home);
+        edit.handle_key(Key::Backspace);
+        edit.handle_key(Key::Left);
+        assert_eq!((edit.text(), edit.cursor()), ("漢", 0));
+
+        let mut edit = typed("abcdef");
+        edit.handle_key(Key::Left);
+        edit.handle_key(Key::Left);
+        edit.handle_key(Key::Ctrl('k'));
+        assert_eq!(edit.text(), "abcd");
+        edit.handle_key(Key::Left);
+        edit.handle_key(Key::Ctrl('u'));
+        assert_eq!((edit.text(), edit.cursor()), ("d", 0));
+    }
+
+    #[test]
+    fn line_edit_history() {
+        let mut edit = typed("");
+        for line in &["one", "two", "", "two", "three"] {
+            edit.set_text(*line);
+            edit.submit();
+        }
+        // Only the last two, with no empty lines or repeats.
+        edit.set_text("draft");
+        edit.handle_key(Key::Up);
+        assert_eq!(edit.text(), "three");
+        edit.handle_key(Key::Up);
+        assert_eq!(edit.text(), "two");
+        edit.handle_key(Key::Up);
+        assert_eq!(edit.text(), "two");
+        edit.handle_key(Key::Down);
+        assert_eq!(edit.text(), "three");
+        edit.handle_key(Key::Down);
+        assert_eq!((edit.text(), edit.cursor()), ("draft", 5));
+        edit.handle_key(Key::Down);
+        assert_eq!(edit.text(), "draft");
+
+        let mut edit = LineEdit::new();
+        edit.set_text("x");
+        edit.submit();
+        assert!(!edit.handle_key(Key::Up));
+        assert_eq!(edit.text(), "");
+    }
+
+    #[test]
+    fn line_edit_scrolls_by_width() {
+        let mut rb = TestBackend::new(3, 1);
+        let area = Rect::new(0, 0, 3, 1);
+        let mut edit = typed("漢字a");
+        edit.draw(&mut rb, area);
+        rb.present().unwrap();
+        // 字 is cut in half by the left edge, so it isn't shown.
+        assert_eq!(rb.buffer_as_string(), " a ");
+        assert_eq!(edit.cursor_position(area), (2, 0));
+
+        edit.handle_key(Key::Home);
+        edit.draw(&mut rb, area);
+        rb.present().unwrap();
+        // Nor when it is cut by the right edge.
+        assert_eq!(rb.buffer_as_string(), "漢 ");
+        assert_eq!(edit.cursor_position(area), (0, 0));
+
+        // Nothing to draw in.
+        edit.draw(&mut rb, Rect::new(0, 0, 0, 1));
+        assert_eq!(edit.cursor_position(Rect::new(0, 0, 0, 1)), (0, 0));
+    }
+
+    fn shown(view: &TextView, w: usize, h: usize) -> String {
+        let mut rb = TestBackend::new(w, h);
+        view.draw(&mut rb, Rect::new(0, 0, w, h));
+        rb.present().unwrap();
+        rb.buffer_as_string()
+    }
+
+    #[test]
+    fn text_view_clamps_when_drawn_bigger() {
+        let mut view = TextView::from_lines(10, |i| i.to_string());
+        shown(&view, 1, 3);
+        view.handle_key(Key::End);
+        assert_eq!(view.top_line(), 7);
+        assert_eq!(shown(&view, 1, 5), "5\n6\n7\n8\n9");
+        assert_eq!(view.top_line(), 5);
+        assert_eq!(shown(&view, 1, 20).lines().count(), 20);
+        assert_eq!(view.top_line(), 0);
+    }
+
+    #[test]
+    fn text_view_clamps_when_the_width_changes() {
+        let mut view = TextView::new("abcdefgh\nijklmnop\nqrstuvwx");
+        view.set_wrap(true);
+        shown(&view, 2, 2);
+        view.handle_key(Key::End);
+        assert_eq!(shown(&view, 2, 2), "uv\nwx");
+        // The last line now takes two rows, and the view stops with them at the bottom rather
+        // than scrolled past them.
+        assert_eq!(shown(&view, 4, 2), "qrst\nuvwx");
+        assert_eq!(shown(&view, 8, 2), "ijklmnop\nqrstuvwx");
+        assert_eq!(view.top_line(), 1);
+    }
+
+    #[test]
+    fn text_view_scrolls_through_wrapped_rows() {
+        let mut view = TextView::new("abcdef\ng\nhijk");
+        view.set_wrap(true);
+        shown(&view, 2, 2);
+        view.scroll_down(2);
+        assert_eq!(shown(&view, 2, 2), "ef\ng ");
+        view.scroll_down(100);
+        assert_eq!(shown(&view, 2, 2), "hi\njk");
+        view.scroll_up(3);
+        assert_eq!(shown(&view, 2, 2), "cd\nef");
+        view.handle_key(Key::PageUp);
+        assert_eq!(shown(&view, 2, 2), "ab\ncd");
+        view.scroll_up(1);
+        assert_eq!(view.top_line(), 0);
+
+        view.scroll_to(100);
+        assert_eq!(shown(&view, 2, 2), "hi\njk");
+        assert!(view.handle_mouse(Mouse::WheelUp));
+        assert_eq!(shown(&view, 2, 2), "cd\nef");
+        assert!(!view.handle_mouse(Mouse::Left));
+    }
+
+    #[test]
+    fn text_view_sideways_and_empty() {
+        let mut view = TextView::new("abcdef");
+        shown(&view, 2, 1);
+        view.handle_key(Key::Right);
+        assert_eq!(shown(&view, 2, 1), "bc");
+        view.scroll_right(-5);
+        assert_eq!(shown(&view, 2, 1), "ab");
+        view.set_wrap(true);
+        view.scroll_right(2);
+        assert_eq!(shown(&view, 2, 1), "ab");
+
+        let mut view = TextView::new("");
+        assert!(view.is_empty());
+        view.handle_key(Key::End);
+        view.scroll_down(3);
+        assert_eq!(shown(&view, 2, 1), "  ");
+        assert_eq!(view.top_line(), 0);
+    }
+}
@@ -0,0 +1,120 @@
+// Waking up an application's own `poll` loop when the terminal is resized.
+//
+// termbox hears about resizes through a SIGWINCH handler that writes to a pipe only it can
+// see, so the terminal's descriptor never becomes readable for them. Ours sits in front of
+// termbox's: it writes a byte to a pipe of our own, which can be waited for along with the
+// terminal, and then hands over to termbox's handler as before. The pipe doesn't block, so
+// once it is full further resizes are only noted by termbox, and the wakeups they would have
+// caused run together.
+
+use std::cell::UnsafeCell;
+use std::fs::File;
+use std::io::{self, Read};
+use std::mem;
+use std::os::raw::c_int;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::ptr;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use libc;
+
+// The pipe that a byte is written to for each resize.
+pub struct Pipe {
+    read: File,
+    write: File,
+}
+
+impl Pipe {
+    pub fn new() -> io::Result<Pipe> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let pipe = unsafe { Pipe { read: File::from_raw_fd(fds[0]), write: File::from_raw_fd(fds[1]) } };
+        for &fd in &fds {
+            unsafe {
+                libc::fcntl(fd, libc::F_SETFL, libc::fcntl(fd, libc::F_GETFL) | libc::O_NONBLOCK);
+                libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            }
+        }
+        Ok(pipe)
+    }
+
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.read.as_raw_fd()
+    }
+
+    // Read whatever has been written, so that the pipe stops being readable until the next
+    // resize.
+    pub fn empty(&self) {
+        let mut buf = [0; 64];
+        let mut read = &self.read;
+        while let Ok(n) = read.read(&mut buf) {
+            if n < buf.len() {
+                break;
+            }
+        }
+    }
+}
+
+struct Saved(UnsafeCell<Option<libc::sigaction>>);
+
+// Only written while `FD` is -1, when the handler leaves it alone.
+unsafe impl Sync for Saved {}
+
+// termbox's handler, to be called after ours.
+static TERMBOX: Saved = Saved(UnsafeCell::new(None));
+// The write end of the pipe, or -1 when not hooked.
+static FD: AtomicI32 = AtomicI32::new(-1);
+
+// Put our handler in front of the one `tb_init` installed. termbox installs its handler
+// afresh each time it starts, so this must come after every `tb_init`.
+pub fn hook(pipe: &Pipe) {
+    unhook();
+    let mut old: libc::sigaction = unsafe { mem::zeroed() };
+    if unsafe { libc::sigaction(libc::SIGWINCH, ptr::null(), &mut old) } == -1 {
+        return;
+    }
+    unsafe {
+        *TERMBOX.0.get() = Some(old);
+    }
+    FD.store(pipe.write.as_raw_fd(), Ordering::SeqCst);
+    let mut action: libc::sigaction = unsafe { mem::zeroed() };
+    action.sa_sigaction = handle as extern "C" fn(c_int, *mut libc::siginfo_t, *mut libc::c_void) as libc::sighandler_t;
+    action.sa_flags = libc::SA_SIGINFO;
+    unsafe {
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGWINCH, &action, ptr::null_mut());
+    }
+}
+
+// Put termbox's handler back, before the pipe is closed or termbox shut down.
+pub fn unhook() {
+    if FD.swap(-1, Ordering::SeqCst) == -1 {
+        return;
+    }
+    unsafe {
+        if let Some(old) = (*TERMBOX.0.get()).take() {
+            libc::sigaction(libc::SIGWINCH, &old, ptr::null_mut());
+        }
+    }
+}
+
+extern "C" fn handle(signal: c_int, info: *mut libc::siginfo_t, context: *mut libc::c_void) {
+    let fd = FD.load(Ordering::SeqCst);
+    if fd == -1 {
+        return;
+    }
+    unsafe {
+        libc::write(fd, b"w".as_ptr() as *const libc::c_void, 1);
+        if let Some(ref old) = *TERMBOX.0.get() {
+            if old.sa_flags & libc::SA_SIGINFO != 0 {
+                let f: extern "C" fn(c_int, *mut libc::siginfo_t, *mut libc::c_void) = mem::transmute(old.sa_sigaction);
+                f(signal, info, context);
+            } else if old.sa_sigaction != libc::SIG_DFL && old.sa_sigaction != libc::SIG_IGN {
+                let f: extern "C" fn(c_int) = mem::transmute(old.sa_sigaction);
+                f(signal);
+            }
+        }
+    }
+}
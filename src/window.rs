@@ -0,0 +1,163 @@
+use super::{Alignment, BorderStyle, Cell, Color, Rect, RustBox, Style, Text};
+use border;
+use text;
+
+/// A rectangular region of the screen with coordinates of its own.
+///
+/// Everything drawn through a window is positioned relative to its top left corner and clipped
+/// to its edges, so a panel can be drawn without knowing where on screen it ends up. Windows
+/// are made with `RustBox::window`, or from another window with `Window::window`.
+///
+/// ```no_run
+/// use rustbox::{Color, Rect, RustBox, RB_NORMAL};
+///
+/// let rb = RustBox::init(Default::default()).unwrap();
+/// let sidebar = rb.window(Rect::new(0, 0, 20, rb.height()));
+/// // Only "a long lin" fits, and it appears at (1, 1) on screen.
+/// sidebar.print(1, 1, RB_NORMAL, Color::White, Color::Default, "a long line that won't fit");
+/// ```
+#[derive(Clone, Copy)]
+pub struct Window<'a> {
+    rb: &'a RustBox,
+    rect: Rect,
+}
+
+impl<'a> Window<'a> {
+    pub(crate) fn new(rb: &'a RustBox, rect: Rect) -> Window<'a> {
+        Window { rb, rect }
+    }
+
+    /// Where the window is on screen.
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn width(&self) -> usize {
+        self.rect.w
+    }
+
+    pub fn height(&self) -> usize {
+        self.rect.h
+    }
+
+    /// A window onto part of this one, given in this window's coordinates. Whatever lies
+    /// outside this window is cut off.
+    pub fn window(&self, rect: Rect) -> Window<'a> {
+        Window::new(self.rb, self.screen_rect(rect))
+    }
+
+    // Translate a rectangle to screen coordinates, clipped to the window.
+    fn screen_rect(&self, rect: Rect) -> Rect {
+        let local = rect.intersection(Rect::new(0, 0, self.rect.w, self.rect.h));
+        Rect::new(self.rect.x + local.x, self.rect.y + local.y, local.w, local.h)
+    }
+
+    // Draw a single cell, if it lies within the window.
+    fn put(&self, x: usize, y: usize, cell: Cell) {
+        if x < self.rect.w && y < self.rect.h {
+            self.rb.set_cell(self.rect.x + x, self.rect.y + y, cell);
+        }
+    }
+
+    /// Returns the cell at `(x, y)`, or `None` if it lies outside the window or off screen.
+    pub fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        if x < self.rect.w && y < self.rect.h {
+            self.rb.cell(self.rect.x + x, self.rect.y + y)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_cell(&self, x: usize, y: usize, cell: Cell) {
+        self.put(x, y, cell);
+    }
+
+    /// Reset the whole window, as `RustBox::clear_region` does.
+    pub fn clear(&self) {
+        self.rb.clear_region(self.rect.x, self.rect.y, self.rect.w, self.rect.h);
+    }
+
+    /// Print `s` starting at `(x, y)`, dealing with control characters as the `RustBox`'s
+    /// `control_policy` says.
+    pub fn print(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, s: &str) {
+        for (dx, dy, ch) in self.rb.control_policy().place(s) {
+            self.print_char(x.saturating_add(dx), y.saturating_add(dy), sty, fg, bg, ch);
+        }
+    }
+
+    pub fn print_char(&self, x: usize, y: usize, sty: Style, fg: Color, bg: Color, ch: char) {
+        self.put(x, y, Cell { ch, fg, bg, style: sty });
+    }
+
+    pub fn print_text(&self, x: usize, y: usize, text: &Text) -> usize {
+        for (dx, ch, attr) in text.columns() {
+            self.print_char(x.saturating_add(dx), y, attr.style, attr.fg, attr.bg, ch);
+        }
+        text.width()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn print_wrapped(&self, rect: Rect, align: Alignment, ellipsis: bool, sty: Style,
+                         fg: Color, bg: Color, s: &str) -> usize {
+        let lines = text::layout(s, rect.w, rect.h, align, ellipsis);
+        for (i, &(offset, ref line)) in lines.iter().enumerate() {
+            for (dx, ch) in text::columns(line) {
+                self.print_char(rect.x + offset + dx, rect.y + i, sty, fg, bg, ch);
+            }
+        }
+        lines.len()
+    }
+
+    pub fn fill_rect(&self, rect: Rect, cell: Cell) {
+        self.rb.fill_rect(self.screen_rect(rect), cell);
+    }
+
+    pub fn scroll_up(&self, rect: Rect, n: usize) {
+        self.rb.scroll_up(self.screen_rect(rect), n);
+    }
+
+    pub fn scroll_down(&self, rect: Rect, n: usize) {
+        self.rb.scroll_down(self.screen_rect(rect), n);
+    }
+
+    pub fn paint_attrs(&self, rect: Rect, fg: Color, bg: Color, sty: Style) {
+        self.rb.paint_attrs(self.screen_rect(rect), fg, bg, sty);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_hline(&self, x: usize, y: usize, len: usize, border: BorderStyle, sty: Style,
+                      fg: Color, bg: Color) {
+        self.draw_border(&border::hline(x, y, len), border, sty, fg, bg);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_vline(&self, x: usize, y: usize, len: usize, border: BorderStyle, sty: Style,
+                      fg: Color, bg: Color) {
+        self.draw_border(&border::vline(x, y, len), border, sty, fg, bg);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_box(&self, x: usize, y: usize, w: usize, h: usize, border: BorderStyle,
+                    sty: Style, fg: Color, bg: Color) {
+        self.draw_border(&border::rect(x, y, w, h), border, sty, fg, bg);
+    }
+
+    fn draw_border(&self, cells: &[(usize, usize, u8)], border: BorderStyle, sty: Style,
+                   fg: Color, bg: Color) {
+        for &(x, y, segments) in cells {
+            if let Some(existing) = self.cell(x, y) {
+                let ch = border::join(existing.ch, segments, border);
+                self.put(x, y, Cell { ch, fg, bg, style: sty });
+            }
+        }
+    }
+
+    /// Show the cursor at `(x, y)` in the window, or hide it if that lies outside.
+    pub fn set_cursor(&self, x: usize, y: usize) {
+        if x < self.rect.w && y < self.rect.h {
+            self.rb.set_cursor((self.rect.x + x) as isize, (self.rect.y + y) as isize);
+        } else {
+            self.rb.hide_cursor();
+        }
+    }
+}